@@ -1,5 +1,11 @@
 pub const CUBE_SIZE: f32 = 1.0;
 pub const GRAVITY: f32 = -9.8 * 4.0;
+/// Vertical speed a player drifts to in water, making them float up to the surface
+pub const WATER_FLOAT_VELOCITY: f32 = 1.0;
+/// Vertical speed a player swims up at while jumping in water
+pub const WATER_SWIM_VELOCITY: f32 = 4.0;
+/// How fast water brings the vertical speed of a player to the float or swim speed
+pub const WATER_DRAG: f32 = 4.0;
 
 pub const TEXTURE_SIZE: u32 = 16;
 
@@ -9,10 +15,12 @@ pub const BASE_SPECULAR_HIGHLIGHT: f32 = 0.;
 
 // increase render distance if we build the project in release mode
 pub const DEFAULT_CHUNK_RENDER_DISTANCE_RADIUS: u32 = if cfg!(debug_assertions) { 2 } else { 4 };
+/// Chunk layers loaded above and below the player
+pub const DEFAULT_CHUNK_VERTICAL_RENDER_DISTANCE: u32 = if cfg!(debug_assertions) { 2 } else { 4 };
 
 pub const CELESTIAL_SIZE: f32 = 10.;
 pub const CELESTIAL_DISTANCE: f32 = 50.; // Low value for testing ; will be increased later
-pub const DAY_DURATION: f32 = 60.;
+pub const DAY_DURATION: f32 = shared::DAY_DURATION as f32;
 
 pub const MAX_INVENTORY_SLOTS: u32 = 4 * 9;
 pub const MAX_HOTBAR_SLOTS: u32 = 9;
@@ -22,10 +30,13 @@ pub const HOTBAR_PADDING: f32 = 5.;
 pub const HOTBAR_BORDER: f32 = 5.;
 
 pub const SAVE_PATH: &str = "saves/";
+/// File marking a folder of `SAVE_PATH` as a world
+pub const LEVEL_FILE: &str = "level.ron";
 pub const SERVER_LIST_SAVE_NAME: &str = "servers.ron";
 pub const BINDS_PATH: &str = "keybindings.ron";
 
 pub const GRASS_COLOR: [f32; 4] = [0.1, 1.0, 0.3, 1.0];
+pub const WATER_COLOR: [f32; 4] = [0.2, 0.4, 1.0, 1.0];
 
 pub const TEXTURE_PATH_BASE: &str = "graphics/base_textures/";
 pub const TEXTURE_PATH_CUSTOM: &str = "graphics/custom_textures/";
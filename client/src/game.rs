@@ -30,14 +30,14 @@ use crate::camera::*;
 use crate::input::*;
 use crate::player::*;
 use crate::ui::hud::inventory::*;
-use shared::world::{BlockId, ItemId, WorldSeed};
+use shared::world::{BlockId, ItemId, WorldHeight, WorldSeed};
 
 use crate::menus::loading::load_loading_screen;
 use crate::network::{
     establish_authenticated_connection_to_server, init_server_connection,
-    launch_local_server_system, network_failure_handler, poll_network_messages,
-    send_player_position_to_server, terminate_server_connection, upload_player_inputs_system,
-    CurrentPlayerProfile, TargetServer, TargetServerState,
+    launch_local_server_system, monitor_connection_system, poll_network_messages,
+    reset_target_server, send_player_position_to_server, terminate_server_connection,
+    upload_player_inputs_system, CurrentPlayerProfile, TargetServer, TargetServerState,
 };
 
 use crate::GameState;
@@ -161,21 +161,36 @@ pub fn game_plugin(app: &mut App) {
             Update,
             (
                 poll_network_messages,
-                network_failure_handler,
                 upload_player_inputs_system,
                 send_player_position_to_server,
                 spawn_player,
             )
                 .run_if(in_state(GameState::Game)),
         )
+        .add_systems(
+            Update,
+            monitor_connection_system
+                .run_if(in_state(GameState::PreGameLoading).or_else(in_state(GameState::Game))),
+        )
         .add_systems(
             OnExit(GameState::Game),
             (clear_resources, terminate_server_connection).chain(),
-        );
+        )
+        // Players spawned while loading are only scoped to the game state
+        .add_systems(OnEnter(GameState::Disconnected), despawn_players)
+        .add_systems(OnEnter(GameState::Menu), reset_target_server);
+}
+
+fn despawn_players(mut commands: Commands, players: Query<Entity, With<Player>>) {
+    for entity in players.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
 fn clear_resources(mut world_map: ResMut<ClientWorldMap>) {
     world_map.map = HashMap::new();
+    world_map.empty_chunks.clear();
+    world_map.height = WorldHeight::default();
     world_map.total_blocks_count = 0;
     world_map.total_chunks_count = 0;
     world_map.name = "".into();
@@ -185,8 +200,12 @@ fn check_pre_loading_complete(
     loading: Res<PreLoadingCompletion>,
     mut game_state: ResMut<NextState<GameState>>,
     target_server: Res<TargetServer>,
+    current_player: Query<(), With<CurrentPlayerMarker>>,
 ) {
-    if loading.textures_loaded && target_server.state == TargetServerState::FullyReady {
+    if loading.textures_loaded
+        && target_server.state == TargetServerState::LoadingTerrain
+        && !current_player.is_empty()
+    {
         game_state.set(GameState::Game);
     }
 }
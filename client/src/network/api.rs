@@ -10,12 +10,14 @@ pub enum NetworkAction {
         requested_chunks: Vec<IVec3>,
         player_chunk_pos: IVec3,
         render_distance: u32,
+        vertical_render_distance: u32,
     },
     SaveWorldRequest,
     BlockInteraction {
         position: IVec3,
         block_type: Option<BlockData>, // None = suppression, Some = ajout
     },
+    OpenToLan,
 }
 
 pub fn send_network_action(client: &mut ResMut<RenetClient>, action: NetworkAction) {
@@ -39,12 +41,14 @@ pub fn send_network_action(client: &mut ResMut<RenetClient>, action: NetworkActi
             requested_chunks,
             player_chunk_pos,
             render_distance,
+            vertical_render_distance,
         } => {
             let input_message = bincode::options()
                 .serialize(&ClientToServerMessage::WorldUpdateRequest {
                     player_chunk_position: player_chunk_pos,
                     requested_chunks,
                     render_distance,
+                    vertical_render_distance,
                 })
                 .unwrap();
 
@@ -73,5 +77,12 @@ pub fn send_network_action(client: &mut ResMut<RenetClient>, action: NetworkActi
 
             client.send_message(DefaultChannel::ReliableOrdered, message);
         }
+        NetworkAction::OpenToLan => {
+            let message = bincode::options()
+                .serialize(&ClientToServerMessage::OpenToLan)
+                .unwrap();
+
+            client.send_message(DefaultChannel::ReliableOrdered, message);
+        }
     }
 }
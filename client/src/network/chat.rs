@@ -1,7 +1,7 @@
 use crate::network::api::{send_network_action, NetworkAction};
 use bevy::prelude::*;
 use bevy_renet::renet::RenetClient;
-use shared::messages::ChatConversation;
+use shared::messages::{ChatConversation, ChatMessage};
 
 #[derive(Resource, Default, Debug)]
 pub struct CachedChatConversation {
@@ -27,3 +27,20 @@ pub fn update_cached_chat_state(
 
     trace!("new CachedChatConversation: {:?}", &chat_state);
 }
+
+/// Adds a message from the server to the chat of this player only
+pub fn show_server_message(chat_state: &mut ResMut<CachedChatConversation>, content: String) {
+    let message = ChatMessage {
+        author_name: "Server".into(),
+        date: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+        content,
+    };
+    chat_state
+        .data
+        .get_or_insert_with(Default::default)
+        .messages
+        .push(message);
+}
@@ -9,14 +9,30 @@ pub fn terminate_server_connection(
     mut target: ResMut<TargetServer>,
 ) {
     info!("Terminating server connection");
-    let order = ClientToServerMessage::Exit(ExitOrder {
-        session_token: target.session_token.unwrap_or_default(),
-    });
-    let payload = bincode::options().serialize(&order).unwrap();
-    client.send_message(DefaultChannel::ReliableOrdered, payload);
+    if client.is_connected() {
+        let order = ClientToServerMessage::Exit(ExitOrder {
+            session_token: target.session_token.unwrap_or_default(),
+        });
+        let payload = bincode::options().serialize(&order).unwrap();
+        client.send_message(DefaultChannel::ReliableOrdered, payload);
+    }
 
+    target.username = None;
+    target.session_token = None;
+    // Keep the failure reason around for the disconnect screen
+    if !matches!(
+        target.state,
+        TargetServerState::Disconnected(_) | TargetServerState::Failed(_)
+    ) {
+        target.state = TargetServerState::Initial;
+    }
+}
+
+/// Forgets the last server once back in the menus, the address is kept until then to allow retrying
+pub fn reset_target_server(mut target: ResMut<TargetServer>) {
     target.address = None;
     target.username = None;
     target.session_token = None;
     target.state = TargetServerState::Initial;
+    target.is_local = false;
 }
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::mem::{discriminant, Discriminant};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_renet::renet::transport::{NetcodeDisconnectReason, NetcodeError, NetcodeTransportError};
+use bevy_renet::renet::{self, RenetClient};
+use shared::messages;
+
+use crate::network::{MemoryClientTransport, TargetServer, TargetServerState};
+use crate::world::FirstChunkReceived;
+use crate::GameState;
+
+/// Maximum time spent waiting for the server to answer the connection request
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum time spent waiting for the server to accept our username
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum time spent waiting for the first chunks once in game
+pub const TERRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Why the connection to a server was lost, or could not be established
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisconnectReason {
+    /// The server did not answer in time
+    TimedOut,
+    /// No server answered at this address
+    Unreachable,
+    /// The server refused the connection, usually because it is full
+    Denied,
+    /// The server stopped or closed the connection
+    ServerClosed,
+    /// The server runs another version of the game, unknown when its answer could not be read
+    VersionMismatch {
+        server_version: Option<String>,
+    },
+    /// The server closed the connection and told us why
+    Server(messages::DisconnectReason),
+    Error(String),
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisconnectReason::TimedOut => write!(f, "Timed out"),
+            DisconnectReason::Unreachable => write!(f, "Could not reach the server"),
+            DisconnectReason::Denied => write!(f, "The server refused the connection"),
+            DisconnectReason::ServerClosed => write!(f, "Server closed"),
+            DisconnectReason::VersionMismatch {
+                server_version: Some(server_version),
+            } => write!(
+                f,
+                "Version mismatch: server is on {}, you are on {}",
+                server_version,
+                shared::GAME_VERSION
+            ),
+            DisconnectReason::VersionMismatch {
+                server_version: None,
+            } => write!(
+                f,
+                "Version mismatch: server is on another version, you are on {}",
+                shared::GAME_VERSION
+            ),
+            DisconnectReason::Server(reason) => write!(f, "{}", reason),
+            DisconnectReason::Error(e) => write!(f, "Network error: {}", e),
+        }
+    }
+}
+
+impl From<&NetcodeTransportError> for DisconnectReason {
+    fn from(error: &NetcodeTransportError) -> Self {
+        match error {
+            NetcodeTransportError::Netcode(NetcodeError::Disconnected(reason)) => match reason {
+                NetcodeDisconnectReason::ConnectTokenExpired
+                | NetcodeDisconnectReason::ConnectionRequestTimedOut
+                | NetcodeDisconnectReason::ConnectionResponseTimedOut => {
+                    DisconnectReason::Unreachable
+                }
+                NetcodeDisconnectReason::ConnectionTimedOut => DisconnectReason::TimedOut,
+                NetcodeDisconnectReason::ConnectionDenied => DisconnectReason::Denied,
+                NetcodeDisconnectReason::DisconnectedByServer => DisconnectReason::ServerClosed,
+                NetcodeDisconnectReason::DisconnectedByClient => {
+                    DisconnectReason::Error(error.to_string())
+                }
+            },
+            NetcodeTransportError::Renet(reason) => reason.into(),
+            e => DisconnectReason::Error(e.to_string()),
+        }
+    }
+}
+
+impl From<&renet::DisconnectReason> for DisconnectReason {
+    fn from(reason: &renet::DisconnectReason) -> Self {
+        match reason {
+            // The in-memory transport only fails when the local server has stopped
+            renet::DisconnectReason::Transport | renet::DisconnectReason::DisconnectedByServer => {
+                DisconnectReason::ServerClosed
+            }
+            reason => DisconnectReason::Error(reason.to_string()),
+        }
+    }
+}
+
+/// Marks the connection as lost, the game is left on the next run of `monitor_connection_system`
+pub fn connection_lost(target: &mut TargetServer, reason: DisconnectReason) {
+    target.state = match target.state {
+        TargetServerState::InGame => TargetServerState::Disconnected(reason),
+        _ => TargetServerState::Failed(reason),
+    };
+}
+
+/// Time spent in the current connection state
+#[derive(Default)]
+pub struct StateTimer {
+    state: Option<Discriminant<TargetServerState>>,
+    elapsed: Duration,
+}
+
+fn state_timeout(state: &TargetServerState) -> Option<Duration> {
+    match state {
+        TargetServerState::Connecting => Some(CONNECT_TIMEOUT),
+        TargetServerState::Authenticating => Some(AUTH_TIMEOUT),
+        TargetServerState::LoadingTerrain => Some(TERRAIN_TIMEOUT),
+        _ => None,
+    }
+}
+
+/// Drives the connection through its states, and leaves the game when the connection fails
+pub fn monitor_connection_system(
+    mut commands: Commands,
+    mut target: ResMut<TargetServer>,
+    mut client: ResMut<RenetClient>,
+    mut transport_errors: EventReader<NetcodeTransportError>,
+    first_chunk_received: Res<FirstChunkReceived>,
+    time: Res<Time>,
+    mut timer: Local<StateTimer>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if target.state == TargetServerState::LoadingTerrain && first_chunk_received.0 {
+        info!("Terrain loaded");
+        target.state = TargetServerState::InGame;
+    }
+
+    let current = discriminant(&target.state);
+    if timer.state != Some(current) {
+        timer.state = Some(current);
+        timer.elapsed = Duration::ZERO;
+    }
+    timer.elapsed += time.delta();
+
+    let mut failure = None;
+    for e in transport_errors.read() {
+        error!("network error: {}", e);
+        failure.get_or_insert_with(|| DisconnectReason::from(e));
+    }
+
+    if failure.is_none() && client.is_disconnected() {
+        failure = client
+            .disconnect_reason()
+            .map(|reason| DisconnectReason::from(&reason));
+    }
+
+    if failure.is_none() {
+        if let Some(timeout) = state_timeout(&target.state) {
+            if timer.elapsed > timeout {
+                warn!("Timed out in state {:?}", target.state);
+                failure = Some(DisconnectReason::TimedOut);
+            }
+        }
+    }
+
+    if let Some(reason) = failure {
+        connection_lost(&mut target, reason);
+    }
+
+    if matches!(
+        target.state,
+        TargetServerState::Disconnected(_) | TargetServerState::Failed(_)
+    ) {
+        info!("Connection lost: {:?}", target.state);
+        client.disconnect();
+        // Dropping our end of the channel stops the local server
+        commands.remove_resource::<MemoryClientTransport>();
+        game_state.set(GameState::Disconnected);
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use bevy_renet::renet::RenetClient;
+use bevy_renet::{RenetClientPlugin, RenetReceive, RenetSend};
+use server::MemoryChannel;
+
+/// Client end of the in-memory connection to the embedded solo server
+#[derive(Resource, Debug)]
+pub struct MemoryClientTransport {
+    pub channel: MemoryChannel,
+}
+
+pub struct MemoryClientPlugin;
+
+impl Plugin for MemoryClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            receive_packets_system
+                .in_set(RenetReceive)
+                .run_if(resource_exists::<MemoryClientTransport>)
+                .run_if(resource_exists::<RenetClient>)
+                .after(RenetClientPlugin::update_system),
+        );
+
+        app.add_systems(
+            PostUpdate,
+            send_packets_system
+                .in_set(RenetSend)
+                .run_if(resource_exists::<MemoryClientTransport>)
+                .run_if(resource_exists::<RenetClient>),
+        );
+    }
+}
+
+fn receive_packets_system(
+    mut commands: Commands,
+    transport: Res<MemoryClientTransport>,
+    mut client: ResMut<RenetClient>,
+) {
+    loop {
+        match transport.channel.try_receive() {
+            Ok(Some(packet)) => client.process_packet(&packet),
+            Ok(None) => break,
+            Err(_) => {
+                info!("Local server closed the connection");
+                client.disconnect_due_to_transport();
+                commands.remove_resource::<MemoryClientTransport>();
+                break;
+            }
+        }
+    }
+}
+
+fn send_packets_system(transport: Res<MemoryClientTransport>, mut client: ResMut<RenetClient>) {
+    for packet in client.get_packets_to_send() {
+        if transport.channel.send(packet).is_err() {
+            client.disconnect_due_to_transport();
+            break;
+        }
+    }
+}
@@ -1,7 +1,9 @@
 pub mod api;
 mod chat;
 mod cleanup;
+mod connection;
 mod inputs;
+mod memory;
 pub mod player;
 pub mod save;
 mod setup;
@@ -9,7 +11,9 @@ mod world;
 
 pub use chat::*;
 pub use cleanup::*;
+pub use connection::*;
 pub use inputs::*;
+pub use memory::*;
 pub use player::*;
 pub use setup::*;
 pub use world::request_world_update;
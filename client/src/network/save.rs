@@ -1,9 +1,24 @@
 use crate::network::api::{send_network_action, NetworkAction};
+use crate::network::{show_server_message, CachedChatConversation};
 use bevy::prelude::*;
 use bevy_renet::renet::RenetClient;
+use shared::messages::SaveWorldResponse;
 
 // Send save request to server
 pub fn send_save_request_to_server(client: &mut ResMut<RenetClient>) {
     send_network_action(client, NetworkAction::SaveWorldRequest);
     debug!("Save request sent to server.");
 }
+
+/// Shows the outcome of a save in the chat, to this player only
+pub fn show_save_response(
+    chat_state: &mut ResMut<CachedChatConversation>,
+    response: SaveWorldResponse,
+) {
+    let content = match response {
+        SaveWorldResponse::Saved { .. } => "World saved".to_string(),
+        SaveWorldResponse::Failed { error } => format!("Failed to save the world: {}", error),
+    };
+    info!("{}", content);
+    show_server_message(chat_state, content);
+}
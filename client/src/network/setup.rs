@@ -4,12 +4,16 @@ use rand::Rng;
 use shared::{get_shared_renet_config, GameServerConfig};
 
 use crate::menus::solo::SelectedWorld;
+use crate::network::save::show_save_response;
 use crate::network::world::update_world_from_network;
-use crate::network::{update_cached_chat_state, CachedChatConversation};
+use crate::network::{
+    connection_lost, show_server_message, update_cached_chat_state, CachedChatConversation,
+    DisconnectReason, MemoryClientPlugin, MemoryClientTransport,
+};
 use crate::player::{CurrentPlayerMarker, Player};
 use crate::world::render_distance::RenderDistance;
 use crate::world::time::ClientTime;
-use crate::world::WorldRenderRequestUpdateEvent;
+use crate::world::{FirstChunkReceived, WorldRenderRequestUpdateEvent};
 use bevy_renet::renet::transport::{
     ClientAuthentication, NetcodeClientTransport, NetcodeTransportError,
 };
@@ -17,10 +21,11 @@ use bevy_renet::renet::DefaultChannel;
 use bevy_renet::transport::NetcodeClientPlugin;
 use bincode::Options;
 use shared::messages::{
-    AuthRegisterRequest, ChatConversation, ClientToServerMessage, PlayerId, PlayerSpawnEvent,
+    AuthRegisterRequest, ClientToServerMessage, PlayerId, PlayerSpawnEvent, ServerToClientMessage,
 };
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::{net::UdpSocket, thread, time::SystemTime};
+use std::net::SocketAddr;
+use std::thread::{self, JoinHandle};
+use std::{net::UdpSocket, time::SystemTime};
 
 use crate::world::ClientWorldMap;
 use shared::GameFolderPaths;
@@ -28,9 +33,17 @@ use shared::GameFolderPaths;
 #[derive(Debug, Clone, PartialEq)]
 pub enum TargetServerState {
     Initial,
-    Establising,
-    ConnectionEstablished,
-    FullyReady, // player has spawned
+    /// Waiting for the transport to connect
+    Connecting,
+    /// Connected, waiting for the server to accept our username
+    Authenticating,
+    /// Authenticated, waiting for the first chunks
+    LoadingTerrain,
+    InGame,
+    /// The connection was lost while playing
+    Disconnected(DisconnectReason),
+    /// The connection could not be established
+    Failed(DisconnectReason),
 }
 
 #[derive(Resource, Clone)]
@@ -56,16 +69,24 @@ pub struct TargetServer {
     pub username: Option<String>,
     pub session_token: Option<u128>,
     pub state: TargetServerState,
+    /// Whether the server is embedded in this client, running a solo world
+    pub is_local: bool,
 }
 
+/// Thread of the last local server, which keeps running while it saves the world after the
+/// client left it
+#[derive(Resource, Default)]
+pub struct LocalServerThread(Option<JoinHandle<()>>);
+
 pub fn add_base_netcode(app: &mut App) {
     app.add_plugins(RenetClientPlugin);
 
     let client = RenetClient::new(get_shared_renet_config());
     app.insert_resource(client);
 
-    // Setup the transport layer
+    // Setup the transport layers, netcode for remote servers and memory for solo worlds
     app.add_plugins(NetcodeClientPlugin);
+    app.add_plugins(MemoryClientPlugin);
 
     // TODO: change username
     app.insert_resource(TargetServer {
@@ -73,13 +94,18 @@ pub fn add_base_netcode(app: &mut App) {
         username: None,
         session_token: None,
         state: TargetServerState::Initial,
+        is_local: false,
     });
+    app.init_resource::<LocalServerThread>();
 }
 
 pub fn launch_local_server_system(
+    mut commands: Commands,
     mut target: ResMut<TargetServer>,
     selected_world: Res<SelectedWorld>,
     paths: Res<GameFolderPaths>,
+    current_player_id: Res<CurrentPlayerProfile>,
+    mut server_thread: ResMut<LocalServerThread>,
 ) {
     if target.address.is_some() {
         debug!("Skipping launch local server");
@@ -89,26 +115,43 @@ pub fn launch_local_server_system(
     if let Some(world_name) = &selected_world.name {
         info!("Launching local server with world: {}", world_name);
 
-        let socket =
-            server::acquire_local_ephemeral_udp_socket(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
-        let addr = socket.local_addr().unwrap();
-        debug!("Obtained UDP socket: {}", addr);
+        let (client_channel, server_channel) = server::memory_channel_pair();
+        let client_id = current_player_id.id;
 
         let world_name_clone = world_name.clone();
+        let creation = selected_world.creation.clone();
         let game_folder_path = paths.clone().game_folder_path;
-        //
-        thread::spawn(move || {
-            server::init(
-                socket,
+        let previous_server = server_thread.0.take();
+        server_thread.0 = Some(thread::spawn(move || {
+            // A retry or a quick return to the same world must not load it while it is saved
+            if let Some(previous_server) = previous_server {
+                if previous_server.join().is_err() {
+                    error!("Previous local server panicked");
+                }
+            }
+
+            // The client notices the server is gone once the memory channel closes
+            if let Err(e) = server::init(
+                server::ServerEndpoint::Memory {
+                    client_id,
+                    channel: server_channel,
+                },
                 GameServerConfig {
                     world_name: world_name_clone,
                     is_solo: true,
+                    chunk_memory_budget_mb: None,
+                    creation,
                 },
                 game_folder_path,
-            );
-        });
+            ) {
+                error!("Local server failed to start: {}", e);
+            }
+        }));
 
-        target.address = Some(addr);
+        commands.insert_resource(MemoryClientTransport {
+            channel: client_channel,
+        });
+        target.is_local = true;
     } else {
         error!("Error: No world selected. Unable to launch the server.");
     }
@@ -117,13 +160,25 @@ pub fn launch_local_server_system(
 fn poll_reliable_ordered_messages(
     client: &mut ResMut<RenetClient>,
     chat_state: &mut ResMut<CachedChatConversation>,
+    target: &mut ResMut<TargetServer>,
 ) {
     while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
-        let message = bincode::options().deserialize::<ChatConversation>(&message);
+        let message = bincode::options().deserialize::<ServerToClientMessage>(&message);
         match message {
-            Ok(data) => {
+            Ok(ServerToClientMessage::ChatConversation(data)) => {
                 update_cached_chat_state(chat_state, data);
             }
+            Ok(ServerToClientMessage::Disconnect { reason }) => {
+                info!("Disconnected by the server: {:?}", reason);
+                connection_lost(target, DisconnectReason::Server(reason));
+            }
+            Ok(ServerToClientMessage::SaveWorldResponse(response)) => {
+                show_save_response(chat_state, response);
+            }
+            Ok(ServerToClientMessage::CommandReply { content }) => {
+                show_server_message(chat_state, content);
+            }
+            Ok(msg) => debug!("Unexpected message: {:?}", msg),
             Err(e) => error!("err {}", e),
         };
     }
@@ -161,8 +216,9 @@ pub fn poll_network_messages(
     current_player_entity: Query<Entity, With<CurrentPlayerMarker>>,
     render_distance: Res<RenderDistance>,
     mut ev_spawn: EventWriter<PlayerSpawnEvent>,
+    mut target: ResMut<TargetServer>,
 ) {
-    poll_reliable_ordered_messages(&mut client, &mut chat_state);
+    poll_reliable_ordered_messages(&mut client, &mut chat_state, &mut target);
     poll_reliable_unordered_messages(
         &mut client,
         &mut world,
@@ -177,17 +233,36 @@ pub fn poll_network_messages(
 
 pub fn init_server_connection(
     mut commands: Commands,
-    target: Res<TargetServer>,
+    mut target: ResMut<TargetServer>,
     current_player_id: Res<CurrentPlayerProfile>,
 ) {
-    let addr = target.address.unwrap();
+    target.state = TargetServerState::Connecting;
+
+    let addr = target.address;
     let id = current_player_id.into_inner().id;
     commands.add(move |world: &mut World| {
         world.remove_resource::<RenetClient>();
         world.remove_resource::<NetcodeClientTransport>();
         world.remove_resource::<CachedChatConversation>();
+        world.insert_resource(FirstChunkReceived(false));
+        // Errors of a previous connection must not fail this one
+        world
+            .resource_mut::<Events<NetcodeTransportError>>()
+            .clear();
+
+        let mut client = RenetClient::new(get_shared_renet_config());
 
-        let client = RenetClient::new(get_shared_renet_config());
+        let Some(addr) = addr else {
+            // Solo world, the memory transport is already in place
+            info!("Connecting to local server");
+            client.set_connected();
+            world.insert_resource(client);
+            world.insert_resource(CachedChatConversation { ..default() });
+            info!("Network subsystem initialized");
+            return;
+        };
+
+        world.remove_resource::<MemoryClientTransport>();
         world.insert_resource(client);
 
         info!("Attempting to connect to: {}", addr);
@@ -212,27 +287,14 @@ pub fn init_server_connection(
     })
 }
 
-pub fn network_failure_handler(mut renet_error: EventReader<NetcodeTransportError>) {
-    for e in renet_error.read() {
-        error!("network error: {}", e);
-    }
-}
-
 pub fn establish_authenticated_connection_to_server(
     mut client: ResMut<RenetClient>,
     mut target: ResMut<TargetServer>,
     current_profile: Res<CurrentPlayerProfile>,
     mut ev_spawn: EventWriter<PlayerSpawnEvent>,
+    mut world_map: ResMut<ClientWorldMap>,
 ) {
-    if target.session_token.is_some() {
-        info!(
-            "Successfully acquired a session token as {}",
-            &target.username.clone().unwrap()
-        );
-        return;
-    }
-
-    if target.state == TargetServerState::Initial {
+    if target.state == TargetServerState::Connecting && client.is_connected() {
         if target.username.is_none() {
             target.username = Some(current_profile.into_inner().name.clone());
         }
@@ -244,16 +306,43 @@ pub fn establish_authenticated_connection_to_server(
         });
         let auth_msg_encoded = bincode::options().serialize(&auth_msg).unwrap();
         client.send_message(DefaultChannel::ReliableOrdered, auth_msg_encoded);
-        target.state = TargetServerState::Establising;
+        target.state = TargetServerState::Authenticating;
+    }
+
+    if target.state != TargetServerState::Authenticating {
+        return;
     }
 
     while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
-        let message =
-            bincode::options().deserialize::<shared::messages::AuthRegisterResponse>(&message);
-        if let Ok(message) = message {
+        let message = match bincode::options().deserialize::<ServerToClientMessage>(&message) {
+            Ok(message) => message,
+            Err(e) => {
+                // Servers of other versions answer with messages we cannot read, including the
+                // version they run
+                warn!("Failed to decode the authentication response: {}", e);
+                target.state = TargetServerState::Failed(DisconnectReason::VersionMismatch {
+                    server_version: None,
+                });
+                return;
+            }
+        };
+        if let ServerToClientMessage::Disconnect { reason } = message {
+            info!("Disconnected by the server: {:?}", reason);
+            connection_lost(&mut target, DisconnectReason::Server(reason));
+            return;
+        }
+        if let ServerToClientMessage::AuthRegisterResponse(message) = message {
+            if message.server_version != shared::GAME_VERSION {
+                target.state = TargetServerState::Failed(DisconnectReason::VersionMismatch {
+                    server_version: Some(message.server_version),
+                });
+                return;
+            }
+
             target.username = Some(message.username);
             target.session_token = Some(message.session_token);
-            target.state = TargetServerState::ConnectionEstablished;
+            target.state = TargetServerState::LoadingTerrain;
+            world_map.height = message.world_height;
             ev_spawn.send(message.spawn_event);
             info!("Connected! {:?}", target);
         }
@@ -33,10 +33,11 @@ pub fn update_world_from_network(
 
     let player_pos = IVec3::new(
         block_to_chunk_coord(player_pos.translation.x as i32),
-        0,
+        block_to_chunk_coord(player_pos.translation.y as i32),
         block_to_chunk_coord(player_pos.translation.z as i32),
     );
     let r = render_distance.distance as i32;
+    let vertical_r = render_distance.vertical_distance as i32;
 
     while let Some(bytes) = client.receive_message(DefaultChannel::ReliableUnordered) {
         let msg = bincode::options()
@@ -52,11 +53,18 @@ pub fn update_world_from_network(
 
                 trace!("Chunks positions : {:?}", world_update.new_map.keys());
 
+                for pos in world_update.empty_chunks {
+                    if chunk_in_radius(&player_pos, &pos, r, vertical_r) {
+                        world.empty_chunks.insert(pos);
+                    }
+                }
+
                 for (pos, chunk) in world_update.new_map {
                     // If the chunk is not in render distance range or is empty, do not consider it
-                    if !chunk_in_radius(&player_pos, &pos, r) || chunk.map.is_empty() {
+                    if !chunk_in_radius(&player_pos, &pos, r, vertical_r) || chunk.map.is_empty() {
                         continue;
                     }
+                    world.empty_chunks.remove(&pos);
 
                     let chunk = ClientChunk {
                         map: chunk.map,
@@ -112,6 +120,7 @@ pub fn request_world_update(
             requested_chunks,
             player_chunk_pos,
             render_distance: render_distance.distance,
+            vertical_render_distance: render_distance.vertical_distance,
         },
     );
 }
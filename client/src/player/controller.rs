@@ -1,5 +1,5 @@
 use crate::camera::CameraController;
-use crate::constants::GRAVITY;
+use crate::constants::{GRAVITY, WATER_DRAG, WATER_FLOAT_VELOCITY, WATER_SWIM_VELOCITY};
 use crate::input::data::GameAction;
 use crate::input::keyboard::*;
 use crate::network::request_world_update;
@@ -11,7 +11,7 @@ use crate::world::{ClientWorldMap, WorldRenderRequestUpdateEvent};
 use crate::KeyMap;
 use bevy::prelude::*;
 use bevy_renet::renet::RenetClient;
-use shared::world::{block_to_chunk_coord, chunk_in_radius};
+use shared::world::{block_to_chunk_coord, chunk_in_radius, BlockTransparency};
 
 use super::CurrentPlayerMarker;
 use crate::world::FirstChunkReceived;
@@ -28,6 +28,16 @@ fn is_block_at_position(position: Vec3, world_map: &ClientWorldMap) -> bool {
     }
 }
 
+fn is_liquid_at_position(position: Vec3, world_map: &ClientWorldMap) -> bool {
+    world_map
+        .get_block_by_coordinates(&IVec3::new(
+            position.x.floor() as i32,
+            position.y.floor() as i32,
+            position.z.floor() as i32,
+        ))
+        .is_some_and(|block| block.id.get_visibility() == BlockTransparency::Liquid)
+}
+
 fn check_player_collision(
     player_position: Vec3,
     player: &Player,
@@ -143,25 +153,40 @@ pub fn player_movement_system(
     // If player changed chunks between this frame and the previous
     if player_chunk != *previous_player_chunk {
         let r = render_distance.distance as i32;
+        let vertical_r = render_distance.vertical_distance as i32;
         let mut requested_chunks: Vec<IVec3> = Vec::new();
 
+        // Only the layers of the world within the vertical render distance
+        let layers = world_map.height.chunk_layers();
+        let min_y = (player_chunk.y - vertical_r).max(*layers.start());
+        let max_y = (player_chunk.y + vertical_r).min(*layers.end());
+
         for x in -r..=r {
             for z in -r..=r {
-                for y in 0..=8 {
+                for y in min_y..=max_y {
                     let chunk_pos = IVec3::new(player_chunk.x + x, y, player_chunk.z + z);
-                    let chunk = world_map.map.get(&chunk_pos);
 
-                    if chunk.is_none() {
+                    if !world_map.map.contains_key(&chunk_pos)
+                        && !world_map.empty_chunks.contains(&chunk_pos)
+                    {
                         requested_chunks.push(chunk_pos);
                     }
                 }
             }
         }
 
+        // The closest chunks are asked for first, so that they are generated and sent first
+        requested_chunks.sort_by_key(|chunk_pos| (*chunk_pos - player_chunk).length_squared());
+
+        // Chunks out of view may be filled by the time they are back in it
+        world_map
+            .empty_chunks
+            .retain(|pos| chunk_in_radius(&player_chunk, pos, r, vertical_r));
+
         // Only retain chunks in the render radius
         world_map.map.retain(|pos, chunk| {
             // If chunk is empty, or not in render radius
-            if !chunk_in_radius(&player_chunk, pos, r) || chunk.map.is_empty() {
+            if !chunk_in_radius(&player_chunk, pos, r, vertical_r) || chunk.map.is_empty() {
                 // Remove chunk, and delete its associated entity if it exists
                 if let Some(entity) = chunk.entity {
                     commands.entity(entity).despawn_recursive();
@@ -214,7 +239,15 @@ pub fn player_movement_system(
         }
     }
 
-    let speed = if player.is_flying { 15.0 } else { 5.0 };
+    let in_water = is_liquid_at_position(player_transform.translation, &world_map);
+
+    let speed = if player.is_flying {
+        15.0
+    } else if in_water {
+        2.5
+    } else {
+        5.0
+    };
 
     let jump_velocity = 10.0;
 
@@ -276,7 +309,18 @@ pub fn player_movement_system(
 
     // Handle jumping (if on the ground) and gravity, only if not flying
     if !player.is_flying {
-        if player.on_ground && is_action_pressed(GameAction::Jump, &keyboard_input, &key_map) {
+        let jump_pressed = is_action_pressed(GameAction::Jump, &keyboard_input, &key_map);
+        if in_water {
+            // Water slows the player down and carries them up, faster when they swim
+            let target_velocity = if jump_pressed {
+                WATER_SWIM_VELOCITY
+            } else {
+                WATER_FLOAT_VELOCITY
+            };
+            player.vertical_velocity += (target_velocity - player.vertical_velocity)
+                * (WATER_DRAG * time.delta_seconds()).min(1.0);
+            player.on_ground = false;
+        } else if player.on_ground && jump_pressed {
             // Player can jump only when grounded
             player.vertical_velocity = jump_velocity;
             player.on_ground = false;
@@ -12,7 +12,7 @@ use bevy::math::NormedVectorSpace;
 use bevy::prelude::*;
 use bevy_mod_raycast::prelude::*;
 use bevy_renet::renet::RenetClient;
-use shared::world::{BlockData, ItemStack, ItemType};
+use shared::world::{BlockData, BlockTransparency, ItemStack, ItemType};
 
 use super::CurrentPlayerMarker;
 
@@ -64,8 +64,17 @@ pub fn handle_block_interactions(
                     block_pos.z.floor() as i32,
                 );
 
+                // Liquids cannot be broken
+                let is_liquid = world_map
+                    .get_block_by_coordinates(&global_block_coords)
+                    .is_some_and(|block| block.id.get_visibility() == BlockTransparency::Liquid);
+
                 // Remove the hit block
-                let block = world_map.remove_block_by_coordinates(&global_block_coords);
+                let block = if is_liquid {
+                    None
+                } else {
+                    world_map.remove_block_by_coordinates(&global_block_coords)
+                };
 
                 if let Some(block) = block {
                     // add the block to the player's inventory
@@ -108,8 +117,18 @@ pub fn handle_block_interactions(
 
             // Get the normal of the face where the block will be placed
             let normal = intersection.normal(); // This is already a Vec3, no need to unwrap
-                                                // Calculate the block position by adding a small offset to the intersection point
-            let mut position = global_block_coords.as_vec3() + normal * 0.51;
+
+            // A block placed on a liquid replaces it
+            let is_liquid = world_map
+                .get_block_by_coordinates(&global_block_coords)
+                .is_some_and(|block| block.id.get_visibility() == BlockTransparency::Liquid);
+
+            // Calculate the block position by adding a small offset to the intersection point
+            let mut position = if is_liquid {
+                global_block_coords.as_vec3()
+            } else {
+                global_block_coords.as_vec3() + normal * 0.51
+            };
             // Snap the position to the grid
             position = snap_to_grid(position);
 
@@ -120,6 +139,8 @@ pub fn handle_block_interactions(
             // Check if target space is close enough to the player
             if (intersection.position() - p_transform.single_mut().translation).norm()
                 <= INTERACTION_DISTANCE
+                // Blocks cannot be placed above or below the world
+                && world_map.height.contains(position.y as i32)
                 // Guarantees a block cannot be placed too close to the player (which would be unable to move because of constant collision)
                 && (distance.x.abs() > (CUBE_SIZE + player.width) / 2. || distance.z.abs() > (CUBE_SIZE + player.width ) / 2. || distance.y.abs() > (CUBE_SIZE + player.height) / 2.)
             {
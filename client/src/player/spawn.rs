@@ -1,7 +1,4 @@
-use crate::{
-    network::{CurrentPlayerProfile, TargetServer, TargetServerState},
-    GameState,
-};
+use crate::{network::CurrentPlayerProfile, GameState};
 use bevy::prelude::*;
 use shared::messages::{PlayerId, PlayerSpawnEvent};
 
@@ -62,7 +59,6 @@ pub fn spawn_player(
     mut materials: ResMut<Assets<StandardMaterial>>,
     player_profile: Res<CurrentPlayerProfile>,
     mut ev_spawn: EventReader<PlayerSpawnEvent>,
-    mut target_server: ResMut<TargetServer>,
     players: Query<&Player>,
 ) {
     let current_id = player_profile.into_inner().id;
@@ -106,7 +102,6 @@ pub fn spawn_player(
         ));
 
         if is_current_player {
-            target_server.state = TargetServerState::FullyReady;
             entity.insert(CurrentPlayerMarker {});
         }
     }
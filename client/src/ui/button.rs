@@ -22,6 +22,7 @@ pub struct SelectedOption;
 #[derive(Component)]
 pub enum MenuButtonAction {
     Solo,
+    CreateWorld,
     Multi,
     Settings,
     SettingsControls,
@@ -34,6 +35,7 @@ pub enum MenuButtonAction {
 pub enum MenuState {
     Main,
     Solo,
+    CreateWorld,
     Multi,
     Settings,
     SettingsControls,
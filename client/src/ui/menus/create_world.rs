@@ -0,0 +1,321 @@
+use super::solo::SelectedWorld;
+use super::world_files::{default_world_name, validate_world_name};
+use super::{MenuButtonAction, MenuState, SelectedOption};
+use crate::ui::assets::*;
+use crate::ui::style::*;
+use crate::{GameState, LoadWorldEvent};
+use bevy::prelude::*;
+use bevy_simple_text_input::{
+    TextInputBundle, TextInputInactive, TextInputPlaceholder, TextInputSettings,
+    TextInputTextStyle, TextInputValue,
+};
+use shared::world::{seed_from_text, GameMode, GeneratorPreset, TimeOfDay, WorldCreationSettings};
+use shared::GameFolderPaths;
+use std::mem::discriminant;
+
+#[derive(Component)]
+pub struct WorldNameInput;
+
+#[derive(Component)]
+pub struct SeedInput;
+
+#[derive(Component)]
+pub struct CreateWorldButton;
+
+/// Tells why the world could not be created
+#[derive(Component)]
+pub struct CreateWorldErrorText;
+
+/// One of the choices of a setting, the chosen one is marked with `SelectedOption`
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum WorldOption {
+    Preset(GeneratorPreset),
+    GameMode(GameMode),
+    TimeOfDay(TimeOfDay),
+}
+
+impl WorldOption {
+    fn label(&self) -> &'static str {
+        match self {
+            WorldOption::Preset(GeneratorPreset::Default) => "Default",
+            WorldOption::Preset(GeneratorPreset::Flat) => "Flat",
+            WorldOption::Preset(GeneratorPreset::Void) => "Void",
+            WorldOption::Preset(GeneratorPreset::Debug) => "Debug",
+            WorldOption::GameMode(GameMode::Survival) => "Survival",
+            WorldOption::GameMode(GameMode::Creative) => "Creative",
+            WorldOption::TimeOfDay(TimeOfDay::Sunrise) => "Sunrise",
+            WorldOption::TimeOfDay(TimeOfDay::Noon) => "Noon",
+            WorldOption::TimeOfDay(TimeOfDay::Sunset) => "Sunset",
+            WorldOption::TimeOfDay(TimeOfDay::Midnight) => "Midnight",
+        }
+    }
+
+    fn apply(&self, settings: &mut WorldCreationSettings) {
+        match *self {
+            WorldOption::Preset(preset) => settings.generator = preset.settings(),
+            WorldOption::GameMode(game_mode) => settings.game_mode = game_mode,
+            WorldOption::TimeOfDay(time_of_day) => settings.time_of_day = time_of_day,
+        }
+    }
+
+    fn is_chosen(&self, settings: &WorldCreationSettings) -> bool {
+        match *self {
+            WorldOption::Preset(preset) => settings.generator == preset.settings(),
+            WorldOption::GameMode(game_mode) => settings.game_mode == game_mode,
+            WorldOption::TimeOfDay(time_of_day) => settings.time_of_day == time_of_day,
+        }
+    }
+}
+
+/// Settings chosen on the creation screen, the seed is read from its input on creation
+#[derive(Resource, Default)]
+pub struct WorldCreationForm(WorldCreationSettings);
+
+pub fn create_world_menu_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    paths: Res<GameFolderPaths>,
+) {
+    let form = WorldCreationForm::default();
+
+    let background_image = load_background_image(&asset_server);
+    let font = load_font(&asset_server);
+    let button_background_image = load_button_background_image(&asset_server);
+
+    let txt_style = TextStyle {
+        font: font.clone(),
+        font_size: 20.,
+        color: TEXT_COLOR,
+    };
+    let txt_style_inactive = TextStyle {
+        font,
+        font_size: 20.,
+        color: Color::srgb(0.3, 0.3, 0.3),
+    };
+    let btn_style = Style {
+        display: Display::Flex,
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(2.)),
+        width: Val::Percent(100.),
+        height: Val::Px(40.0),
+        ..Default::default()
+    };
+
+    let settings = [
+        (
+            "Generator",
+            vec![
+                WorldOption::Preset(GeneratorPreset::Default),
+                WorldOption::Preset(GeneratorPreset::Flat),
+                WorldOption::Preset(GeneratorPreset::Void),
+                WorldOption::Preset(GeneratorPreset::Debug),
+            ],
+        ),
+        (
+            "Game mode",
+            vec![
+                WorldOption::GameMode(GameMode::Survival),
+                WorldOption::GameMode(GameMode::Creative),
+            ],
+        ),
+        (
+            "Time of day",
+            vec![
+                WorldOption::TimeOfDay(TimeOfDay::Sunrise),
+                WorldOption::TimeOfDay(TimeOfDay::Noon),
+                WorldOption::TimeOfDay(TimeOfDay::Sunset),
+                WorldOption::TimeOfDay(TimeOfDay::Midnight),
+            ],
+        ),
+    ];
+
+    commands
+        .spawn((
+            StateScoped(MenuState::CreateWorld),
+            NodeBundle {
+                style: Style {
+                    width: Val::Vw(100.0),
+                    height: Val::Vh(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::horizontal(Val::Percent(20.)),
+                    row_gap: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            UiImage::new(background_image),
+        ))
+        .with_children(|root| {
+            root.spawn(TextBundle::from_section("Create world", txt_style.clone()));
+
+            for (marker_is_name, placeholder) in [
+                (true, default_world_name(&paths)),
+                (false, "Seed (random if empty)".to_string()),
+            ] {
+                let mut input = root.spawn((
+                    NodeBundle {
+                        border_color: BorderColor(BACKGROUND_COLOR),
+                        background_color: BackgroundColor(Color::BLACK),
+                        style: btn_style.clone(),
+                        ..Default::default()
+                    },
+                    TextInputBundle {
+                        settings: TextInputSettings {
+                            retain_on_submit: true,
+                            mask_character: None,
+                        },
+                        placeholder: TextInputPlaceholder {
+                            value: placeholder,
+                            text_style: Some(txt_style_inactive.clone()),
+                        },
+                        inactive: TextInputInactive(true),
+                        text_style: TextInputTextStyle(txt_style.clone()),
+                        ..Default::default()
+                    },
+                ));
+                if marker_is_name {
+                    input.insert(WorldNameInput);
+                } else {
+                    input.insert(SeedInput);
+                }
+            }
+
+            for (label, options) in settings {
+                root.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(5.),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|row| {
+                    row.spawn(TextBundle {
+                        text: Text::from_section(label, txt_style.clone()),
+                        style: Style {
+                            width: Val::Percent(30.),
+                            flex_shrink: 0.,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    });
+
+                    for option in options {
+                        let mut button = row.spawn((
+                            ButtonBundle {
+                                style: btn_style.clone(),
+                                border_color: BorderColor(Color::BLACK),
+                                image: UiImage::new(button_background_image.clone()),
+                                ..Default::default()
+                            },
+                            option,
+                        ));
+                        button.with_children(|btn| {
+                            btn.spawn(TextBundle::from_section(option.label(), txt_style.clone()));
+                        });
+                        if option.is_chosen(&form.0) {
+                            button.insert(SelectedOption);
+                        }
+                    }
+                });
+            }
+
+            root.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: Color::srgb(1., 0.3, 0.3),
+                        ..txt_style.clone()
+                    },
+                ),
+                CreateWorldErrorText,
+            ));
+
+            for (label, is_create) in [("Create world", true), ("Back", false)] {
+                let mut button = root.spawn(ButtonBundle {
+                    style: btn_style.clone(),
+                    border_color: BorderColor(Color::BLACK),
+                    image: UiImage::new(button_background_image.clone()),
+                    ..Default::default()
+                });
+                button.with_children(|btn| {
+                    btn.spawn(TextBundle::from_section(label, txt_style.clone()));
+                });
+                if is_create {
+                    button.insert(CreateWorldButton);
+                } else {
+                    button.insert(MenuButtonAction::Solo);
+                }
+            }
+        });
+
+    commands.insert_resource(form);
+}
+
+pub fn create_world_action(
+    (option_query, options, create_query): (
+        Query<(Entity, &Interaction, &WorldOption), (Changed<Interaction>, With<Button>)>,
+        Query<(Entity, &WorldOption)>,
+        Query<&Interaction, (Changed<Interaction>, With<CreateWorldButton>)>,
+    ),
+    (name_query, seed_query, mut error_query): (
+        Query<&TextInputValue, With<WorldNameInput>>,
+        Query<&TextInputValue, With<SeedInput>>,
+        Query<&mut Text, With<CreateWorldErrorText>>,
+    ),
+    (mut form, mut selected_world, mut menu_state, mut game_state): (
+        ResMut<WorldCreationForm>,
+        ResMut<SelectedWorld>,
+        ResMut<NextState<MenuState>>,
+        ResMut<NextState<GameState>>,
+    ),
+    mut commands: Commands,
+    mut load_event: EventWriter<LoadWorldEvent>,
+    paths: Res<GameFolderPaths>,
+) {
+    for (entity, interaction, option) in &option_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        option.apply(&mut form.0);
+        for (other, other_option) in &options {
+            if discriminant(other_option) == discriminant(option) {
+                commands.entity(other).remove::<SelectedOption>();
+            }
+        }
+        commands.entity(entity).insert(SelectedOption);
+    }
+
+    for interaction in &create_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let name = name_query.single().0.trim().to_string();
+        let name = if name.is_empty() {
+            default_world_name(&paths)
+        } else {
+            name
+        };
+        if let Err(e) = validate_world_name(&paths, &name) {
+            error_query.single_mut().sections[0].value = format!("Invalid world name: {}", e);
+            return;
+        }
+
+        let creation = WorldCreationSettings {
+            seed: seed_from_text(&seed_query.single().0),
+            ..form.0.clone()
+        };
+        info!("Creating world {} with {:?}", name, creation);
+
+        selected_world.name = Some(name.clone());
+        selected_world.creation = creation;
+        load_event.send(LoadWorldEvent { world_name: name });
+        game_state.set(GameState::PreGameLoading);
+        menu_state.set(MenuState::Disabled);
+    }
+}
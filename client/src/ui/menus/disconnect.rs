@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+
+use crate::network::{TargetServer, TargetServerState};
+use crate::ui::assets::*;
+use crate::ui::style::{background_image_style, big_button_style, text_style, NORMAL_BUTTON};
+use crate::{GameState, TEXT_COLOR};
+
+#[derive(Component)]
+pub enum DisconnectButtonAction {
+    Retry,
+    BackToMenu,
+}
+
+pub fn disconnect_screen_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    target: Res<TargetServer>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    // The cursor may still be grabbed by the game
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
+
+    let background_image = load_background_image(&asset_server);
+    let button_background_image = load_button_background_image(&asset_server);
+    let font = load_font(&asset_server);
+
+    let (title, reason) = match &target.state {
+        TargetServerState::Disconnected(reason) => ("Disconnected", reason.to_string()),
+        TargetServerState::Failed(reason) => ("Connection failed", reason.to_string()),
+        _ => ("Disconnected", String::new()),
+    };
+
+    commands.spawn((
+        Camera2dBundle::default(),
+        StateScoped(GameState::Disconnected),
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: background_image_style(),
+                background_color: Color::NONE.into(),
+                ..Default::default()
+            },
+            UiImage::new(background_image),
+            StateScoped(GameState::Disconnected),
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(title, text_style(font.clone(), 50.0, TEXT_COLOR))
+                    .with_style(Style {
+                        margin: UiRect::bottom(Val::Px(20.0)),
+                        ..Default::default()
+                    }),
+            );
+            parent.spawn(
+                TextBundle::from_section(reason, text_style(font.clone(), 25.0, TEXT_COLOR))
+                    .with_style(Style {
+                        margin: UiRect::bottom(Val::Px(60.0)),
+                        ..Default::default()
+                    }),
+            );
+
+            for (action, label) in [
+                (DisconnectButtonAction::Retry, "Retry"),
+                (DisconnectButtonAction::BackToMenu, "Back to menu"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: big_button_style(),
+                            background_color: NORMAL_BUTTON.into(),
+                            image: UiImage::new(button_background_image.clone()),
+                            ..Default::default()
+                        },
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            text_style(font.clone(), 33.0, TEXT_COLOR),
+                        ));
+                    });
+            }
+        });
+}
+
+pub fn disconnect_screen_action(
+    interaction_query: Query<
+        (&Interaction, &DisconnectButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut target: ResMut<TargetServer>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match action {
+                DisconnectButtonAction::Retry => {
+                    target.state = TargetServerState::Initial;
+                    game_state.set(GameState::PreGameLoading);
+                }
+                DisconnectButtonAction::BackToMenu => game_state.set(GameState::Menu),
+            }
+        }
+    }
+}
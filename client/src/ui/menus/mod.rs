@@ -1,3 +1,5 @@
+pub mod create_world;
+pub mod disconnect;
 pub mod home;
 pub mod loading;
 pub mod multi;
@@ -5,6 +7,8 @@ pub mod pause;
 pub mod settings;
 pub mod solo;
 pub mod splash;
+pub mod world_dialog;
+pub mod world_files;
 
 use bevy::prelude::*;
 pub use home::*;
@@ -31,7 +35,25 @@ pub fn menu_plugin(app: &mut App) {
             OnEnter(MenuState::Solo),
             (solo::solo_menu_setup, solo::list_worlds).chain(),
         )
-        .add_systems(Update, solo::solo_action.run_if(in_state(MenuState::Solo)))
+        .add_event::<world_dialog::WorldListChangedEvent>()
+        .add_systems(
+            Update,
+            (
+                solo::solo_action,
+                world_dialog::world_dialog_action,
+                solo::refresh_world_list,
+            )
+                .chain()
+                .run_if(in_state(MenuState::Solo)),
+        )
+        .add_systems(
+            OnEnter(MenuState::CreateWorld),
+            create_world::create_world_menu_setup,
+        )
+        .add_systems(
+            Update,
+            create_world::create_world_action.run_if(in_state(MenuState::CreateWorld)),
+        )
         // Systems to handle the settings menu screen
         .add_systems(OnEnter(MenuState::Settings), settings::settings_menu_setup)
         // Systems to handle the display settings screen
@@ -57,7 +79,16 @@ pub fn menu_plugin(app: &mut App) {
             Update,
             (menu_action, button_system, mouse_scroll).run_if(in_state(GameState::Menu)),
         )
-        .add_systems(OnEnter(MenuState::SettingsControls), controls_menu_setup);
+        .add_systems(OnEnter(MenuState::SettingsControls), controls_menu_setup)
+        .add_systems(
+            OnEnter(GameState::Disconnected),
+            disconnect::disconnect_screen_setup,
+        )
+        .add_systems(
+            Update,
+            (disconnect::disconnect_screen_action, button_system)
+                .run_if(in_state(GameState::Disconnected)),
+        );
 }
 
 /// Tag component for scrolling UI lists
@@ -85,6 +116,7 @@ fn menu_action(
                     app_exit_events.send(AppExit::Success);
                 }
                 MenuButtonAction::Solo => menu_state.set(MenuState::Solo),
+                MenuButtonAction::CreateWorld => menu_state.set(MenuState::CreateWorld),
                 MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
                 MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
                 MenuButtonAction::BackToSettings => {
@@ -1,4 +1,6 @@
+use crate::network::api::{send_network_action, NetworkAction};
 use crate::network::save::send_save_request_to_server;
+use crate::network::TargetServer;
 use bevy::{
     asset::AssetServer,
     color::{Alpha, Color},
@@ -28,6 +30,7 @@ pub struct PauseMenu;
 pub enum PauseButtonAction {
     Resume,
     Save,
+    OpenToLan,
     Menu,
 }
 
@@ -35,7 +38,18 @@ pub fn setup_pause_menu(
     mut commands: Commands,
     assets: Res<AssetServer>,
     _paths: Res<GameFolderPaths>,
+    target: Res<TargetServer>,
 ) {
+    let mut buttons = vec![
+        ("Resume", PauseButtonAction::Resume),
+        ("Save", PauseButtonAction::Save),
+    ];
+    // Only the embedded server of a solo world can be opened to other players
+    if target.is_local {
+        buttons.push(("Open to LAN", PauseButtonAction::OpenToLan));
+    }
+    buttons.push(("Back to menu", PauseButtonAction::Menu));
+
     commands
         .spawn((
             PauseMenu,
@@ -72,11 +86,7 @@ pub fn setup_pause_menu(
                 ..Default::default()
             })
             .with_children(|wrapper| {
-                for (msg, action) in [
-                    ("Resume", PauseButtonAction::Resume),
-                    ("Save", PauseButtonAction::Save),
-                    ("Back to menu", PauseButtonAction::Menu),
-                ] {
+                for (msg, action) in buttons {
                     wrapper
                         .spawn((
                             action,
@@ -150,6 +160,9 @@ pub fn render_pause_menu(
                 PauseButtonAction::Save => {
                     send_save_request_to_server(&mut client);
                 }
+                PauseButtonAction::OpenToLan => {
+                    send_network_action(&mut client, NetworkAction::OpenToLan);
+                }
             },
             Interaction::Hovered => {
                 bcolor.0 = Color::WHITE;
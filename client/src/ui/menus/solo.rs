@@ -1,8 +1,14 @@
+use super::world_dialog::{
+    open_confirm_dialog, open_restore_dialog, WorldAction, WorldListChangedEvent,
+};
 use super::{MenuButtonAction, MenuState, ScrollingList};
 use crate::ui::assets::*;
 use crate::ui::style::*;
 use crate::world::ClientWorldMap;
-use crate::{constants::SAVE_PATH, GameState, LoadWorldEvent};
+use crate::{
+    constants::{LEVEL_FILE, SAVE_PATH},
+    GameState, LoadWorldEvent,
+};
 use bevy::prelude::Resource;
 use bevy::prelude::*;
 use bevy::{
@@ -21,20 +27,21 @@ use bevy::{
     },
     utils::hashbrown::HashMap,
 };
-use bevy_simple_text_input::{
-    TextInputBundle, TextInputInactive, TextInputPlaceholder, TextInputSettings,
-    TextInputTextStyle, TextInputValue,
+use shared::world::{
+    get_game_folder, DateTime, GameMode, WorldCreationSettings, WorldMetadata, METADATA_FILE,
 };
-use shared::world::get_game_folder;
 use shared::GameFolderPaths;
-use std::io;
+use std::time::UNIX_EPOCH;
 use std::{
     fs,
     path::{Path, PathBuf},
 };
 
 pub struct WorldItem {
+    /// Name of the world folder
     pub name: String,
+    /// `None` for new worlds and worlds saved before metadata files
+    pub metadata: Option<WorldMetadata>,
 }
 
 #[derive(Component, Default)]
@@ -44,17 +51,20 @@ pub struct WorldList {
 
 #[derive(Component)]
 pub enum MultiplayerButtonAction {
-    Add,
     Load(Entity),
     Delete(Entity),
+    Rename(Entity),
+    Duplicate(Entity),
+    Backup(Entity),
+    Restore(Entity),
+    OpenFolder(Entity),
 }
 
-#[derive(Component)]
-pub struct WorldNameInput;
-
 #[derive(Resource, Default, Debug, Clone)]
 pub struct SelectedWorld {
     pub name: Option<String>,
+    /// Settings of the world if it is created when loaded
+    pub creation: WorldCreationSettings,
 }
 
 pub fn solo_menu_setup(
@@ -72,12 +82,6 @@ pub fn solo_menu_setup(
         color: Color::WHITE,
     };
 
-    let txt_style_inactive = TextStyle {
-        font,
-        font_size: 20.,
-        color: Color::srgb(0.3, 0.3, 0.3),
-    };
-
     let btn_style = Style {
         display: Display::Flex,
         flex_direction: FlexDirection::Column,
@@ -162,33 +166,6 @@ pub fn solo_menu_setup(
                 ..Default::default()
             })
             .with_children(|wrapper| {
-                wrapper.spawn((
-                    NodeBundle {
-                        border_color: BorderColor(BACKGROUND_COLOR),
-                        background_color: BackgroundColor(Color::BLACK),
-                        style: {
-                            let mut style = btn_style.clone();
-                            style.grid_column = GridPlacement::span(2);
-                            style
-                        },
-                        ..Default::default()
-                    },
-                    WorldNameInput,
-                    TextInputBundle {
-                        settings: TextInputSettings {
-                            retain_on_submit: true,
-                            mask_character: None,
-                        },
-                        placeholder: TextInputPlaceholder {
-                            value: "World name".into(),
-                            text_style: Some(txt_style_inactive.clone()),
-                        },
-                        inactive: TextInputInactive(true),
-                        text_style: TextInputTextStyle(txt_style.clone()),
-                        ..Default::default()
-                    },
-                ));
-
                 wrapper
                     .spawn((
                         ButtonBundle {
@@ -202,7 +179,7 @@ pub fn solo_menu_setup(
                             image: UiImage::new(button_background_image.clone()),
                             ..Default::default()
                         },
-                        MultiplayerButtonAction::Add,
+                        MenuButtonAction::CreateWorld,
                     ))
                     .with_children(|btn| {
                         btn.spawn(TextBundle {
@@ -252,27 +229,129 @@ pub fn list_worlds(
         info!("Successfully created the saves folder : {}", path.display());
     }
 
-    let paths = fs::read_dir(path).unwrap();
+    for item in read_worlds(path) {
+        add_world_item(
+            item,
+            &mut commands,
+            &assets,
+            &mut list,
+            list_entity,
+            &mut world_map,
+            &game_paths,
+        );
+    }
+}
 
+/// Reads the list again once worlds were changed on disk
+pub fn refresh_world_list(
+    mut ev_list_changed: EventReader<WorldListChangedEvent>,
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut list_query: Query<(&mut WorldList, Entity)>,
+    mut world_map: ResMut<ClientWorldMap>,
+    game_paths: Res<GameFolderPaths>,
+) {
+    if ev_list_changed.is_empty() {
+        return;
+    }
+    ev_list_changed.clear();
+
+    let (mut list, list_entity) = list_query.single_mut();
+    for (world, _) in list.worlds.drain() {
+        commands.entity(world).despawn_recursive();
+    }
+
+    let save_path = get_game_folder(Some(&game_paths)).join(SAVE_PATH);
+    for item in read_worlds(&save_path) {
+        add_world_item(
+            item,
+            &mut commands,
+            &assets,
+            &mut list,
+            list_entity,
+            &mut world_map,
+            &game_paths,
+        );
+    }
+}
+
+/// Worlds of the saves folder, most recently played first
+fn read_worlds(save_path: &Path) -> Vec<WorldItem> {
+    let Ok(paths) = fs::read_dir(save_path) else {
+        error!("Failed to read the saves folder: {}", save_path.display());
+        return Vec::new();
+    };
+
+    let mut worlds = Vec::new();
     for path in paths {
-        let path_str = path.unwrap().file_name().into_string().unwrap();
-
-        if path_str.ends_with(".ron") {
-            add_world_item(
-                path_str.replace(".ron", ""),
-                &mut commands,
-                &assets,
-                &mut list,
-                list_entity,
-                &mut world_map,
-                &game_paths,
-            );
+        let entry = path.unwrap();
+        let path_str = entry.file_name().into_string().unwrap();
+
+        // Worlds are folders containing a level file, single `.ron` files are legacy saves
+        let (name, metadata, marker_file) = if entry.path().join(LEVEL_FILE).is_file() {
+            let metadata = read_world_metadata(&entry.path());
+            (path_str, metadata, entry.path().join(LEVEL_FILE))
+        } else if path_str.ends_with(".ron") && entry.path().is_file() {
+            (path_str.replace(".ron", ""), None, entry.path())
+        } else {
+            continue;
+        };
+
+        // Worlds saved before metadata files existed were last played when last written
+        let last_played = metadata.as_ref().map(|m| m.last_played).or_else(|| {
+            fs::metadata(marker_file)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+        });
+
+        worlds.push((
+            last_played.unwrap_or_default(),
+            WorldItem { name, metadata },
+        ));
+    }
+
+    worlds.sort_by(|(a_time, a), (b_time, b)| b_time.cmp(a_time).then_with(|| a.name.cmp(&b.name)));
+    worlds.into_iter().map(|(_, item)| item).collect()
+}
+
+fn read_world_metadata(world_dir: &Path) -> Option<WorldMetadata> {
+    let contents = fs::read_to_string(world_dir.join(METADATA_FILE)).ok()?;
+    match ron::de::from_str(&contents) {
+        Ok(metadata) => Some(metadata),
+        Err(e) => {
+            warn!("Invalid world metadata in {}: {}", world_dir.display(), e);
+            None
         }
     }
 }
 
+/// Second line of a world in the list,
+/// e.g. `Survival - last played 2024-10-19 18:03 - 2h 05m - 12.4 MB - seed 1234`
+fn world_details(metadata: &WorldMetadata) -> String {
+    let date = DateTime::from_unix(metadata.last_played);
+    let mode = match metadata.game_mode {
+        GameMode::Survival => "Survival",
+        GameMode::Creative => "Creative",
+    };
+    format!(
+        "{} - last played {:04}-{:02}-{:02} {:02}:{:02} - {}h {:02}m - {:.1} MB - seed {}",
+        mode,
+        date.year,
+        date.month,
+        date.day,
+        date.hour,
+        date.minute,
+        metadata.playtime / 3600,
+        metadata.playtime / 60 % 60,
+        metadata.size_on_disk as f64 / (1024. * 1024.),
+        metadata.seed
+    )
+}
+
 fn add_world_item(
-    name: String,
+    item: WorldItem,
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     list: &mut WorldList,
@@ -282,13 +361,13 @@ fn add_world_item(
 ) {
     info!(
         "Adding world to list : name = {:?}, entity={:?}",
-        name, list_entity
+        item.name, list_entity
     );
 
     let base_path = &paths.assets_folder_path;
 
     // udpate the name of the world_map
-    world_map.name = name.clone();
+    world_map.name = item.name.clone();
 
     let btn_style = Style {
         display: Display::Flex,
@@ -358,64 +437,97 @@ fn add_world_item(
         })
         .id();
 
+    let font = asset_server.load("./fonts/RustCraftRegular-Bmg3.otf");
+    let mut sections = vec![TextSection {
+        value: item
+            .metadata
+            .as_ref()
+            .map_or(item.name.clone(), |m| m.display_name.clone())
+            + "\n",
+        style: TextStyle {
+            font: font.clone(),
+            font_size: 20.,
+            color: Color::WHITE,
+        },
+    }];
+    if let Some(metadata) = &item.metadata {
+        sections.push(TextSection {
+            value: world_details(metadata),
+            style: TextStyle {
+                font: font.clone(),
+                font_size: 14.,
+                color: Color::srgb(0.7, 0.7, 0.7),
+            },
+        });
+    }
+
     let txt = commands
         .spawn(TextBundle {
             text: Text {
-                sections: vec![TextSection {
-                    value: name.clone() + "\n",
-                    style: TextStyle {
-                        font: asset_server.load("./fonts/RustCraftRegular-Bmg3.otf"),
-                        font_size: 20.,
-                        color: Color::WHITE,
-                    },
-                }],
+                sections,
                 ..Default::default()
             },
             style: Style {
                 display: Display::Flex,
                 flex_direction: FlexDirection::Column,
+                flex_grow: 1.,
                 ..Default::default()
             },
             ..Default::default()
         })
         .id();
 
-    commands
-        .entity(world)
-        .push_children(&[play_btn, delete_btn, txt]);
+    let mut children = vec![play_btn, delete_btn, txt];
+    let actions = [
+        (MultiplayerButtonAction::Rename(world), "Rename"),
+        (MultiplayerButtonAction::Duplicate(world), "Copy"),
+        (MultiplayerButtonAction::Backup(world), "Backup"),
+        (MultiplayerButtonAction::Restore(world), "Restore"),
+        (MultiplayerButtonAction::OpenFolder(world), "Folder"),
+    ];
+    for (action, label) in actions {
+        let btn = commands
+            .spawn((
+                action,
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::horizontal(Val::Px(6.)),
+                        ..btn_style.clone()
+                    },
+                    border_color: BorderColor(Color::BLACK),
+                    ..Default::default()
+                },
+            ))
+            .with_children(|btn| {
+                btn.spawn(TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 14.,
+                        color: Color::WHITE,
+                    },
+                ));
+            })
+            .id();
+        children.push(btn);
+    }
 
-    commands.entity(list_entity).push_children(&[world]);
+    commands.entity(world).push_children(&children);
 
-    list.worlds.insert(world, WorldItem { name: name.clone() });
-}
+    commands.entity(list_entity).push_children(&[world]);
 
-fn generate_new_world_name(world_list: &WorldList) -> String {
-    let mut index = 1;
-
-    loop {
-        let candidate = format!("new_world_{}", index);
-        if !world_list
-            .worlds
-            .values()
-            .any(|world| world.name == candidate)
-        {
-            return candidate;
-        }
-        index += 1;
-    }
+    list.worlds.insert(world, item);
 }
 
 pub fn solo_action(
-    (interaction_query, mut name_query, mut list_query): (
+    (interaction_query, list_query): (
         Query<(&Interaction, &MultiplayerButtonAction), (Changed<Interaction>, With<Button>)>,
-        Query<&mut TextInputValue, With<WorldNameInput>>,
-        Query<(Entity, &mut WorldList), With<WorldList>>,
+        Query<&WorldList>,
     ),
-    (asset_server, mut menu_state, mut game_state, mut world_map, mut selected_world): (
+    (asset_server, mut menu_state, mut game_state, mut selected_world): (
         Res<AssetServer>,
         ResMut<NextState<MenuState>>,
         ResMut<NextState<GameState>>,
-        ResMut<ClientWorldMap>,
         ResMut<SelectedWorld>,
     ),
     mut commands: Commands,
@@ -426,39 +538,16 @@ pub fn solo_action(
         return;
     }
 
-    let (entity, mut list) = list_query.single_mut();
+    let list = list_query.single();
 
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
             match *menu_button_action {
-                MultiplayerButtonAction::Add => {
-                    if !name_query.is_empty() {
-                        let mut name = name_query.single_mut();
-
-                        // if no name, create default one
-                        let new_name = if name.0.is_empty() {
-                            generate_new_world_name(&list)
-                        } else {
-                            name.0.clone()
-                        };
-
-                        add_world_item(
-                            new_name,
-                            &mut commands,
-                            &asset_server,
-                            &mut list,
-                            entity,
-                            &mut world_map,
-                            &paths,
-                        );
-
-                        name.0 = "".into();
-                    }
-                }
                 MultiplayerButtonAction::Load(world_entity) => {
                     if let Some(world) = list.worlds.get(&world_entity) {
                         // update ressource name
                         selected_world.name = Some(world.name.clone());
+                        selected_world.creation = WorldCreationSettings::default();
 
                         load_event.send(LoadWorldEvent {
                             world_name: world.name.clone(),
@@ -467,39 +556,29 @@ pub fn solo_action(
                         menu_state.set(MenuState::Disabled);
                     }
                 }
-                MultiplayerButtonAction::Delete(world_entity) => {
+                MultiplayerButtonAction::Delete(world_entity)
+                | MultiplayerButtonAction::Rename(world_entity)
+                | MultiplayerButtonAction::Duplicate(world_entity)
+                | MultiplayerButtonAction::Backup(world_entity)
+                | MultiplayerButtonAction::OpenFolder(world_entity) => {
                     if let Some(world) = list.worlds.get(&world_entity) {
-                        if let Err(e) = delete_save_files(&world.name, &paths) {
-                            error!("Error while deleting save files: {}", e);
-                        }
-                        list.worlds.remove(&world_entity);
+                        let name = world.name.clone();
+                        let action = match *menu_button_action {
+                            MultiplayerButtonAction::Delete(_) => WorldAction::Delete(name),
+                            MultiplayerButtonAction::Rename(_) => WorldAction::Rename(name),
+                            MultiplayerButtonAction::Duplicate(_) => WorldAction::Duplicate(name),
+                            MultiplayerButtonAction::Backup(_) => WorldAction::Backup(name),
+                            _ => WorldAction::OpenFolder(name),
+                        };
+                        open_confirm_dialog(&mut commands, &asset_server, &paths, action);
+                    }
+                }
+                MultiplayerButtonAction::Restore(world_entity) => {
+                    if let Some(world) = list.worlds.get(&world_entity) {
+                        open_restore_dialog(&mut commands, &asset_server, &paths, &world.name);
                     }
-                    commands.entity(entity).remove_children(&[world_entity]);
-                    commands.entity(world_entity).despawn_recursive();
                 }
             }
         }
     }
 }
-
-pub fn delete_save_files(
-    world_name: &str,
-    game_folder_path: &Res<GameFolderPaths>,
-) -> Result<(), io::Error> {
-    // Delete `world_save.ron`
-    match fs::remove_file(format!(
-        "{}{}.ron",
-        get_game_folder(Some(game_folder_path))
-            .join(SAVE_PATH)
-            .display(),
-        world_name
-    )) {
-        Ok(_) => info!("Successfully deleted world"),
-        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
-            error!("world_save.ron not found, skipping.")
-        }
-        Err(e) => error!("Failed to delete world: {}", e),
-    }
-
-    Ok(())
-}
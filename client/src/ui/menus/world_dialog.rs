@@ -0,0 +1,377 @@
+use super::world_files::*;
+use super::MenuState;
+use crate::ui::assets::*;
+use crate::ui::style::*;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use bevy_simple_text_input::{
+    TextInputBundle, TextInputInactive, TextInputSettings, TextInputTextStyle, TextInputValue,
+};
+use shared::GameFolderPaths;
+
+/// Operation on a saved world, applied once the player confirms it
+#[derive(Debug, Clone)]
+pub enum WorldAction {
+    Delete(String),
+    Rename(String),
+    Duplicate(String),
+    Backup(String),
+    Restore { world: String, backup: String },
+    OpenFolder(String),
+}
+
+impl WorldAction {
+    fn world(&self) -> &str {
+        match self {
+            WorldAction::Delete(world)
+            | WorldAction::Rename(world)
+            | WorldAction::Duplicate(world)
+            | WorldAction::Backup(world)
+            | WorldAction::Restore { world, .. }
+            | WorldAction::OpenFolder(world) => world,
+        }
+    }
+
+    fn verb(&self) -> &str {
+        match self {
+            WorldAction::Delete(_) => "delete",
+            WorldAction::Rename(_) => "rename",
+            WorldAction::Duplicate(_) => "duplicate",
+            WorldAction::Backup(_) => "back up",
+            WorldAction::Restore { .. } => "restore",
+            WorldAction::OpenFolder(_) => "open the folder of",
+        }
+    }
+}
+
+/// Modal window of the solo menu, only one is open at a time
+#[derive(Component)]
+pub struct WorldDialog {
+    /// Applied by the confirm button, `None` for dialogs which only inform
+    action: Option<WorldAction>,
+}
+
+#[derive(Component)]
+pub struct DialogNameInput;
+
+#[derive(Component)]
+pub enum DialogButtonAction {
+    Confirm,
+    Close,
+    /// Choice of a list, asks to confirm the action it leads to
+    Choose(WorldAction),
+}
+
+/// Sent when worlds were added, removed or changed on disk, so that the list is read again
+#[derive(Event)]
+pub struct WorldListChangedEvent;
+
+fn spawn_dialog(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    message: String,
+    action: Option<WorldAction>,
+    name_input: Option<String>,
+    choices: Vec<(String, WorldAction)>,
+) {
+    let font = load_font(asset_server);
+    let txt_style = TextStyle {
+        font,
+        font_size: 20.,
+        color: TEXT_COLOR,
+    };
+    let btn_style = Style {
+        display: Display::Flex,
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(2.)),
+        width: Val::Percent(100.),
+        height: Val::Px(40.),
+        ..Default::default()
+    };
+    let button_background_image = load_button_background_image(asset_server);
+    let has_action = action.is_some();
+
+    commands
+        .spawn((
+            StateScoped(MenuState::Solo),
+            WorldDialog { action },
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Vw(100.),
+                    height: Val::Vh(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::srgba(0., 0., 0., 0.7)),
+                // Keeps the menu behind from being clicked
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..Default::default()
+            },
+        ))
+        .with_children(|root| {
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(40.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.),
+                    padding: UiRect::all(Val::Px(20.)),
+                    border: UiRect::all(Val::Px(2.)),
+                    ..Default::default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: BackgroundColor(BACKGROUND_COLOR),
+                ..Default::default()
+            })
+            .with_children(|panel| {
+                panel.spawn(TextBundle {
+                    text: Text::from_section(message, txt_style.clone()),
+                    ..Default::default()
+                });
+
+                if let Some(value) = name_input {
+                    panel.spawn((
+                        NodeBundle {
+                            border_color: BorderColor(Color::BLACK),
+                            background_color: BackgroundColor(Color::BLACK),
+                            style: btn_style.clone(),
+                            ..Default::default()
+                        },
+                        DialogNameInput,
+                        TextInputBundle {
+                            settings: TextInputSettings {
+                                retain_on_submit: true,
+                                mask_character: None,
+                            },
+                            value: TextInputValue(value),
+                            inactive: TextInputInactive(false),
+                            text_style: TextInputTextStyle(txt_style.clone()),
+                            ..Default::default()
+                        },
+                    ));
+                }
+
+                let has_choices = !choices.is_empty();
+                for (label, choice) in choices {
+                    panel
+                        .spawn((
+                            ButtonBundle {
+                                style: btn_style.clone(),
+                                border_color: BorderColor(Color::BLACK),
+                                image: UiImage::new(button_background_image.clone()),
+                                ..Default::default()
+                            },
+                            DialogButtonAction::Choose(choice),
+                        ))
+                        .with_children(|btn| {
+                            btn.spawn(TextBundle::from_section(label, txt_style.clone()));
+                        });
+                }
+
+                let mut buttons = Vec::new();
+                if has_action {
+                    buttons.push((DialogButtonAction::Confirm, "Confirm"));
+                    buttons.push((DialogButtonAction::Close, "Cancel"));
+                } else {
+                    buttons.push((
+                        DialogButtonAction::Close,
+                        if has_choices { "Cancel" } else { "OK" },
+                    ));
+                }
+
+                for (button_action, label) in buttons {
+                    panel
+                        .spawn((
+                            ButtonBundle {
+                                style: btn_style.clone(),
+                                border_color: BorderColor(Color::BLACK),
+                                image: UiImage::new(button_background_image.clone()),
+                                ..Default::default()
+                            },
+                            button_action,
+                        ))
+                        .with_children(|btn| {
+                            btn.spawn(TextBundle::from_section(label, txt_style.clone()));
+                        });
+                }
+            });
+        });
+}
+
+/// Shows a message with a single button closing it
+pub fn open_message_dialog(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    message: String,
+) {
+    spawn_dialog(commands, asset_server, message, None, None, Vec::new());
+}
+
+/// Asks the player to confirm an action, with the new name prefilled for those needing one
+pub fn open_confirm_dialog(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    paths: &GameFolderPaths,
+    action: WorldAction,
+) {
+    let (message, name_input) = match &action {
+        WorldAction::Delete(world) => (
+            format!("Delete world {}? This cannot be undone.", world),
+            None,
+        ),
+        WorldAction::Rename(world) => (format!("Rename world {} to:", world), Some(world.clone())),
+        WorldAction::Duplicate(world) => (
+            format!("Duplicate world {} as:", world),
+            Some(default_copy_name(paths, world)),
+        ),
+        WorldAction::Backup(world) => (format!("Create a backup of world {} now?", world), None),
+        WorldAction::Restore { world, backup } => (
+            format!(
+                "Restore world {} from the backup of {}? Changes made since will be lost.",
+                world, backup
+            ),
+            None,
+        ),
+        WorldAction::OpenFolder(world) => (
+            format!("Open the folder of world {} in the file manager?", world),
+            None,
+        ),
+    };
+    spawn_dialog(
+        commands,
+        asset_server,
+        message,
+        Some(action),
+        name_input,
+        Vec::new(),
+    );
+}
+
+/// Lists the backups of a world, picking one asks to confirm the restoration
+pub fn open_restore_dialog(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    paths: &GameFolderPaths,
+    world: &str,
+) {
+    match world_backups(paths, world) {
+        Ok(backups) if backups.is_empty() => {
+            open_message_dialog(
+                commands,
+                asset_server,
+                format!("World {} has no backup.", world),
+            );
+        }
+        Ok(backups) => {
+            let choices = backups
+                .into_iter()
+                .map(|backup| {
+                    let action = WorldAction::Restore {
+                        world: world.to_string(),
+                        backup: backup.clone(),
+                    };
+                    (backup, action)
+                })
+                .collect();
+            spawn_dialog(
+                commands,
+                asset_server,
+                format!("Pick the backup of world {} to restore:", world),
+                None,
+                None,
+                choices,
+            );
+        }
+        Err(e) => {
+            error!("Failed to list the backups of world {}: {}", world, e);
+            open_message_dialog(
+                commands,
+                asset_server,
+                format!("Could not list the backups of world {}: {}", world, e),
+            );
+        }
+    }
+}
+
+pub fn world_dialog_action(
+    interaction_query: Query<
+        (&Interaction, &DialogButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    dialog_query: Query<(Entity, &WorldDialog)>,
+    name_query: Query<&TextInputValue, With<DialogNameInput>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    paths: Res<GameFolderPaths>,
+    mut ev_list_changed: EventWriter<WorldListChangedEvent>,
+) {
+    let Ok((dialog_entity, dialog)) = dialog_query.get_single() else {
+        return;
+    };
+
+    for (interaction, button_action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        commands.entity(dialog_entity).despawn_recursive();
+
+        let action = match button_action {
+            DialogButtonAction::Close => return,
+            DialogButtonAction::Choose(choice) => {
+                open_confirm_dialog(&mut commands, &asset_server, &paths, choice.clone());
+                return;
+            }
+            DialogButtonAction::Confirm => match &dialog.action {
+                Some(action) => action,
+                None => return,
+            },
+        };
+
+        let new_name = name_query
+            .get_single()
+            .map(|value| value.0.trim().to_string())
+            .unwrap_or_default();
+        let world = action.world();
+        let result = match action {
+            WorldAction::Delete(_) => delete_world(&paths, world).map(|_| None),
+            WorldAction::Rename(_) => rename_world(&paths, world, &new_name).map(|_| None),
+            WorldAction::Duplicate(_) => duplicate_world(&paths, world, &new_name).map(|_| None),
+            WorldAction::Backup(_) => backup_world(&paths, world)
+                .map(|backup| Some(format!("Created backup {} of world {}.", backup, world))),
+            WorldAction::Restore { backup, .. } => {
+                restore_world(&paths, world, backup).map(|replaced| {
+                    Some(format!(
+                        "World {} was restored from {}, its previous state is in backup {}.",
+                        world, backup, replaced
+                    ))
+                })
+            }
+            WorldAction::OpenFolder(_) => open_world_folder(&paths, world).map(|_| None),
+        };
+
+        match result {
+            Ok(message) => {
+                info!("World action done: {:?}", action);
+                if !matches!(action, WorldAction::OpenFolder(_)) {
+                    ev_list_changed.send(WorldListChangedEvent);
+                }
+                if let Some(message) = message {
+                    open_message_dialog(&mut commands, &asset_server, message);
+                }
+            }
+            Err(e) => {
+                error!("Failed to {} world {}: {}", action.verb(), world, e);
+                open_message_dialog(
+                    &mut commands,
+                    &asset_server,
+                    format!("Could not {} world {}: {}", action.verb(), world, e),
+                );
+            }
+        }
+        return;
+    }
+}
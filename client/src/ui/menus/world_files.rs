@@ -0,0 +1,209 @@
+//! Operations of the solo menu on the saved worlds. Worlds are folders of `SAVE_PATH`,
+//! or single `<name>.ron` files for worlds which were not played since region files.
+
+use crate::constants::SAVE_PATH;
+use ron::ser::PrettyConfig;
+use server::{copy_dir, create_backup, list_backups, restore_backup, WorldStorage};
+use shared::world::{get_game_folder, unix_timestamp, WorldMetadata, METADATA_FILE};
+use shared::GameFolderPaths;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Folders of a world which are not copied along with it
+const NOT_DUPLICATED: [&str; 1] = ["backups"];
+
+fn saves_dir(paths: &GameFolderPaths) -> PathBuf {
+    get_game_folder(Some(paths)).join(SAVE_PATH)
+}
+
+fn world_dir(paths: &GameFolderPaths, name: &str) -> PathBuf {
+    saves_dir(paths).join(name)
+}
+
+fn legacy_file(paths: &GameFolderPaths, name: &str) -> PathBuf {
+    saves_dir(paths).join(format!("{}.ron", name))
+}
+
+fn not_found(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("world {} does not exist", name),
+    )
+}
+
+/// Checks that a new world can be saved under this name
+pub fn validate_world_name(paths: &GameFolderPaths, name: &str) -> io::Result<()> {
+    let invalid = |reason: &str| Err(io::Error::new(io::ErrorKind::InvalidInput, reason));
+
+    if name.trim().is_empty() {
+        return invalid("the name cannot be empty");
+    }
+    if name != name.trim() || name.starts_with('.') {
+        return invalid("the name cannot start or end with a space, or start with a dot");
+    }
+    if name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|']) {
+        return invalid("the name cannot contain / \\ : * ? \" < > |");
+    }
+    if world_dir(paths, name).exists() || legacy_file(paths, name).exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("a world named {} already exists", name),
+        ));
+    }
+    Ok(())
+}
+
+/// Edits the metadata file of a world, if it has one
+fn update_metadata(world_dir: &Path, update: impl FnOnce(&mut WorldMetadata)) -> io::Result<()> {
+    let path = world_dir.join(METADATA_FILE);
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let contents = fs::read_to_string(&path)?;
+    let mut metadata: WorldMetadata = ron::de::from_str(&contents)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    update(&mut metadata);
+    let serialized = ron::ser::to_string_pretty(&metadata, PrettyConfig::new())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    fs::write(path, serialized)
+}
+
+/// Deletes the world folder and the legacy save, fails if the world has neither
+pub fn delete_world(paths: &GameFolderPaths, name: &str) -> io::Result<()> {
+    let dir = world_dir(paths, name);
+    let legacy = legacy_file(paths, name);
+    if !dir.exists() && !legacy.exists() {
+        return Err(not_found(name));
+    }
+
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    if legacy.exists() {
+        fs::remove_file(legacy)?;
+    }
+    Ok(())
+}
+
+pub fn rename_world(paths: &GameFolderPaths, name: &str, new_name: &str) -> io::Result<()> {
+    validate_world_name(paths, new_name)?;
+
+    let dir = world_dir(paths, name);
+    let legacy = legacy_file(paths, name);
+    if !dir.exists() && !legacy.exists() {
+        return Err(not_found(name));
+    }
+
+    if dir.exists() {
+        let new_dir = world_dir(paths, new_name);
+        fs::rename(dir, &new_dir)?;
+        update_metadata(&new_dir, |metadata| {
+            metadata.display_name = new_name.to_string();
+        })?;
+    }
+    if legacy.exists() {
+        fs::rename(legacy, legacy_file(paths, new_name))?;
+    }
+    Ok(())
+}
+
+/// Copies a world under a new name, without its backups
+pub fn duplicate_world(paths: &GameFolderPaths, name: &str, new_name: &str) -> io::Result<()> {
+    validate_world_name(paths, new_name)?;
+
+    let dir = world_dir(paths, name);
+    let legacy = legacy_file(paths, name);
+    if dir.exists() {
+        let new_dir = world_dir(paths, new_name);
+        fs::create_dir_all(&new_dir)?;
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            if NOT_DUPLICATED.iter().any(|dir| file_name == *dir) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                copy_dir(&entry.path(), &new_dir.join(&file_name))?;
+            } else if entry.path().extension().is_none_or(|ext| ext != "tmp") {
+                fs::copy(entry.path(), new_dir.join(&file_name))?;
+            }
+        }
+        update_metadata(&new_dir, |metadata| {
+            metadata.display_name = new_name.to_string();
+            metadata.created_at = unix_timestamp();
+        })
+    } else if legacy.exists() {
+        fs::copy(legacy, legacy_file(paths, new_name)).map(|_| ())
+    } else {
+        Err(not_found(name))
+    }
+}
+
+/// Name a new world gets when the player does not choose one
+pub fn default_world_name(paths: &GameFolderPaths) -> String {
+    let mut index = 1;
+    loop {
+        let candidate = format!("new_world_{}", index);
+        if validate_world_name(paths, &candidate).is_ok() {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+/// Name a copy of a world gets by default
+pub fn default_copy_name(paths: &GameFolderPaths, name: &str) -> String {
+    let mut index = 1;
+    loop {
+        let candidate = if index == 1 {
+            format!("{}_copy", name)
+        } else {
+            format!("{}_copy_{}", name, index)
+        };
+        if validate_world_name(paths, &candidate).is_ok() {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+/// Makes a new backup of a world, returns its name
+pub fn backup_world(paths: &GameFolderPaths, name: &str) -> io::Result<String> {
+    let storage = WorldStorage::new(paths, name);
+    if !storage.exists() {
+        return Err(io::Error::other(
+            "the world has to be played once before it can be backed up",
+        ));
+    }
+    create_backup(&storage)
+}
+
+/// Backups of a world, newest first
+pub fn world_backups(paths: &GameFolderPaths, name: &str) -> io::Result<Vec<String>> {
+    let mut backups = list_backups(&WorldStorage::new(paths, name))?;
+    backups.reverse();
+    Ok(backups)
+}
+
+/// Returns the name of the backup holding the world as it was before
+pub fn restore_world(paths: &GameFolderPaths, name: &str, backup: &str) -> io::Result<String> {
+    restore_backup(&WorldStorage::new(paths, name), backup)
+}
+
+/// Shows the folder of a world in the file manager of the system
+pub fn open_world_folder(paths: &GameFolderPaths, name: &str) -> io::Result<()> {
+    let dir = world_dir(paths, name);
+    let folder = if dir.exists() { dir } else { saves_dir(paths) };
+
+    let opener = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener).arg(folder).spawn().map(|_| ())
+}
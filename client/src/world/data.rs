@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use shared::world::BlockData;
+use shared::world::{BlockData, WorldHeight};
 use std::collections::HashSet;
 use std::hash::Hash;
 
@@ -29,6 +29,11 @@ pub struct ClientChunk {
 pub struct ClientWorldMap {
     pub name: String,
     pub map: HashMap<IVec3, crate::world::ClientChunk>, // Maps global chunk positions to chunks
+    /// Chunks in view the server said have no block, so that they are not requested again
+    #[serde(skip)]
+    pub empty_chunks: HashSet<IVec3>,
+    /// Heights of the world, sent by the server when joining it
+    pub height: WorldHeight,
     pub total_blocks_count: u64,
     pub total_chunks_count: u64,
 }
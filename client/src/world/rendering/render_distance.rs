@@ -1,5 +1,5 @@
 use crate::{
-    constants::DEFAULT_CHUNK_RENDER_DISTANCE_RADIUS,
+    constants::{DEFAULT_CHUNK_RENDER_DISTANCE_RADIUS, DEFAULT_CHUNK_VERTICAL_RENDER_DISTANCE},
     input::{data::GameAction, keyboard::is_action_just_pressed},
     KeyMap,
 };
@@ -8,6 +8,7 @@ use bevy::prelude::*;
 #[derive(Resource, Default)]
 pub struct RenderDistance {
     pub distance: u32,
+    pub vertical_distance: u32,
 }
 
 pub fn render_distance_update_system(
@@ -18,6 +19,9 @@ pub fn render_distance_update_system(
     if render_distance.distance == 0 {
         render_distance.distance = DEFAULT_CHUNK_RENDER_DISTANCE_RADIUS;
     }
+    if render_distance.vertical_distance == 0 {
+        render_distance.vertical_distance = DEFAULT_CHUNK_VERTICAL_RENDER_DISTANCE;
+    }
 
     if is_action_just_pressed(GameAction::RenderDistanceMinus, &keyboard_input, &key_map) {
         render_distance.distance -= 1;
@@ -1,4 +1,4 @@
-use crate::constants::GRASS_COLOR;
+use crate::constants::{GRASS_COLOR, WATER_COLOR};
 use shared::world::{BlockData, BlockId};
 
 /// Specifies which position in the voxel this face occupies
@@ -97,6 +97,19 @@ impl VoxelShape {
 
                 shape
             }
+            BlockId::Water => {
+                let mut shape = Self::full_cube(block);
+
+                // The still water texture is grey, to be tinted
+                for face in shape.faces.iter_mut() {
+                    face.texture = "WaterStill".into();
+                    for col in face.colors.iter_mut() {
+                        *col = WATER_COLOR;
+                    }
+                }
+
+                shape
+            }
             _ => Self::full_cube(block),
         }
     }
@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use shared::messages::DisconnectReason;
+use shared::world::get_game_folder;
+use shared::GameFolderPaths;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const BANS_FILE: &str = "bans.ron";
+pub const WHITELIST_FILE: &str = "whitelist.ron";
+pub const OPS_FILE: &str = "ops.ron";
+
+/// How often the access files are checked for changes made by hand
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Ban {
+    pub reason: Option<String>,
+    /// Timestamp in ms, `None` for a permanent ban
+    pub expires_at: Option<u64>,
+}
+
+impl Ban {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn to_disconnect_reason(&self) -> DisconnectReason {
+        DisconnectReason::Banned {
+            reason: self.reason.clone(),
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BanList {
+    pub players: BTreeMap<String, Ban>,
+    pub ips: BTreeMap<IpAddr, Ban>,
+}
+
+impl BanList {
+    /// Removes expired bans, returns whether any was removed
+    pub fn prune(&mut self, now: u64) -> bool {
+        let count = self.players.len() + self.ips.len();
+        self.players.retain(|_, ban| !ban.is_expired(now));
+        self.ips.retain(|_, ban| !ban.is_expired(now));
+        count != self.players.len() + self.ips.len()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Whitelist {
+    pub enabled: bool,
+    pub players: BTreeSet<String>,
+}
+
+/// Content of a file in the game folder, reloaded when it is modified on disk
+#[derive(Debug)]
+pub struct AccessFile<T> {
+    pub data: T,
+    file_name: &'static str,
+    modified: Option<SystemTime>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default + PartialEq> AccessFile<T> {
+    fn new(file_name: &'static str) -> Self {
+        Self {
+            data: T::default(),
+            file_name,
+            modified: None,
+        }
+    }
+
+    fn path(&self, paths: &GameFolderPaths) -> PathBuf {
+        get_game_folder(Some(paths)).join(self.file_name)
+    }
+
+    /// Reads the file again if it changed since it was last read or written
+    fn reload(&mut self, paths: &GameFolderPaths) -> Result<bool, Box<dyn std::error::Error>> {
+        let path = self.path(paths);
+        let modified = match fs::metadata(&path) {
+            Ok(metadata) => Some(metadata.modified()?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        if modified == self.modified {
+            return Ok(false);
+        }
+
+        let data = match modified {
+            Some(_) => ron::de::from_str(&fs::read_to_string(&path)?)?,
+            None => T::default(),
+        };
+        self.modified = modified;
+        if data == self.data {
+            return Ok(false);
+        }
+        self.data = data;
+        Ok(true)
+    }
+
+    pub fn save(&mut self, paths: &GameFolderPaths) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.path(paths);
+        let serialized = ron::ser::to_string_pretty(&self.data, PrettyConfig::new())?;
+        fs::write(&path, serialized)?;
+        self.modified = Some(fs::metadata(&path)?.modified()?);
+        Ok(())
+    }
+}
+
+/// Who is allowed to join the server, and who can manage it
+#[derive(Resource, Debug)]
+pub struct AccessLists {
+    pub bans: AccessFile<BanList>,
+    pub whitelist: AccessFile<Whitelist>,
+    pub ops: AccessFile<BTreeSet<String>>,
+}
+
+impl Default for AccessLists {
+    fn default() -> Self {
+        Self {
+            bans: AccessFile::new(BANS_FILE),
+            whitelist: AccessFile::new(WHITELIST_FILE),
+            ops: AccessFile::new(OPS_FILE),
+        }
+    }
+}
+
+impl AccessLists {
+    /// Reloads every file modified on disk
+    pub fn reload(&mut self, paths: &GameFolderPaths) {
+        for (name, result) in [
+            (BANS_FILE, self.bans.reload(paths)),
+            (WHITELIST_FILE, self.whitelist.reload(paths)),
+            (OPS_FILE, self.ops.reload(paths)),
+        ] {
+            match result {
+                Ok(true) => info!("Reloaded {}", name),
+                Ok(false) => {}
+                Err(e) => error!("Failed to load {}: {}", name, e),
+            }
+        }
+    }
+
+    /// Returns why the player cannot join, if they cannot
+    pub fn check(&self, username: &str, ip: Option<IpAddr>) -> Option<DisconnectReason> {
+        let now = now_ms();
+        let bans = &self.bans.data;
+
+        if let Some(ban) = bans.players.get(username).filter(|b| !b.is_expired(now)) {
+            return Some(ban.to_disconnect_reason());
+        }
+
+        if let Some(ban) = ip
+            .and_then(|ip| bans.ips.get(&ip))
+            .filter(|b| !b.is_expired(now))
+        {
+            return Some(ban.to_disconnect_reason());
+        }
+
+        let whitelist = &self.whitelist.data;
+        if whitelist.enabled && !whitelist.players.contains(username) && !self.is_op(username) {
+            return Some(DisconnectReason::NotWhitelisted);
+        }
+
+        None
+    }
+
+    pub fn is_op(&self, username: &str) -> bool {
+        self.ops.data.contains(username)
+    }
+}
+
+#[derive(Resource)]
+struct AccessReloadTimer(Timer);
+
+pub fn setup_access_resources(app: &mut App) {
+    let mut access = AccessLists::default();
+    access.reload(app.world().resource::<GameFolderPaths>());
+    app.insert_resource(access);
+    app.insert_resource(AccessReloadTimer(Timer::new(
+        RELOAD_INTERVAL,
+        TimerMode::Repeating,
+    )));
+    app.add_systems(Update, reload_access_lists_system);
+}
+
+fn reload_access_lists_system(
+    mut access: ResMut<AccessLists>,
+    mut timer: ResMut<AccessReloadTimer>,
+    paths: Res<GameFolderPaths>,
+    time: Res<Time>,
+) {
+    timer.0.tick(time.delta());
+    if !timer.0.finished() {
+        return;
+    }
+
+    access.reload(&paths);
+
+    if access.bans.data.prune(now_ms()) {
+        info!("Removed expired bans");
+        if let Err(e) = access.bans.save(&paths) {
+            error!("Failed to save {}: {}", BANS_FILE, e);
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use crate::access::{now_ms, AccessLists, Ban};
+use crate::init::ServerLobby;
+use crate::network::disconnect::{disconnect_with_reason, PendingDisconnections};
+use crate::network::transport::ServerTransport;
+use crate::world::backup::{list_backups, PendingRestore};
+use crate::world::pregen::{PregenJob, Pregeneration, MAX_PREGEN_RADIUS};
+use crate::world::storage::WorldStorage;
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
+use bincode::Options;
+use shared::messages::{DisconnectReason, PlayerId, ServerToClientMessage};
+use shared::world::WorldMetadata;
+use shared::{GameFolderPaths, CHUNK_SIZE};
+use std::net::IpAddr;
+
+const HELP: &str = "Commands: kick <player> [message], ban <player> [duration] [reason], \
+ban-ip <ip|player> [duration] [reason], pardon <player>, pardon-ip <ip>, banlist, \
+whitelist <on|off|add|remove|list> [player], op <player>, deop <player>, reload, \
+backups, restore <backup>, pregen <x> <z> <radius>, pregen <status|cancel>";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandSender {
+    /// Terminal of a dedicated server
+    Console,
+    Player(ClientId),
+}
+
+/// A command typed in the server console, or in the chat with a leading `/`
+#[derive(Event, Debug)]
+pub struct CommandEvent {
+    pub sender: CommandSender,
+    pub command: String,
+}
+
+pub fn setup_command_resources(app: &mut App) {
+    app.add_event::<CommandEvent>();
+}
+
+/// Everything a command may need to act on
+struct CommandContext<'a> {
+    server: &'a mut RenetServer,
+    pending: &'a mut PendingDisconnections,
+    transport: &'a ServerTransport,
+    lobby: &'a ServerLobby,
+    access: &'a mut AccessLists,
+    paths: &'a GameFolderPaths,
+    storage: &'a WorldStorage,
+    pending_restore: &'a mut PendingRestore,
+    pregen: &'a mut Pregeneration,
+    metadata: &'a WorldMetadata,
+}
+
+pub fn handle_commands_system(
+    mut events: EventReader<CommandEvent>,
+    (mut server, mut pending, transport): (
+        ResMut<RenetServer>,
+        ResMut<PendingDisconnections>,
+        Res<ServerTransport>,
+    ),
+    (lobby, mut access, paths): (Res<ServerLobby>, ResMut<AccessLists>, Res<GameFolderPaths>),
+    (storage, mut pending_restore, mut pregen, metadata): (
+        Res<WorldStorage>,
+        ResMut<PendingRestore>,
+        ResMut<Pregeneration>,
+        Res<WorldMetadata>,
+    ),
+) {
+    for event in events.read() {
+        info!("{:?} issued command: {}", event.sender, event.command);
+
+        let mut ctx = CommandContext {
+            server: &mut server,
+            pending: &mut pending,
+            transport: &transport,
+            lobby: &lobby,
+            access: &mut access,
+            paths: &paths,
+            storage: &storage,
+            pending_restore: &mut pending_restore,
+            pregen: &mut pregen,
+            metadata: &metadata,
+        };
+
+        let args: Vec<&str> = event
+            .command
+            .trim_start_matches('/')
+            .split_whitespace()
+            .collect();
+        if args.is_empty() {
+            continue;
+        }
+
+        let result = if is_allowed(event.sender, &ctx) {
+            execute(&args, &mut ctx)
+        } else {
+            Err("You are not allowed to use commands".to_string())
+        };
+
+        match (event.sender, result) {
+            (CommandSender::Console, Ok(reply)) => info!("{}", reply),
+            (CommandSender::Console, Err(reply)) => warn!("{}", reply),
+            (CommandSender::Player(client_id), Ok(reply) | Err(reply)) => {
+                let payload = bincode::options()
+                    .serialize(&ServerToClientMessage::CommandReply { content: reply })
+                    .unwrap();
+                if server.is_connected(client_id) {
+                    server.send_message(client_id, DefaultChannel::ReliableOrdered, payload);
+                }
+            }
+        }
+    }
+}
+
+/// The console, operators and the host of a solo world can manage the server
+fn is_allowed(sender: CommandSender, ctx: &CommandContext) -> bool {
+    match sender {
+        CommandSender::Console => true,
+        CommandSender::Player(client_id) => {
+            ctx.lobby.host == Some(client_id.raw())
+                || ctx
+                    .lobby
+                    .players
+                    .get(&client_id.raw())
+                    .is_some_and(|name| ctx.access.is_op(name))
+        }
+    }
+}
+
+fn execute(args: &[&str], ctx: &mut CommandContext) -> Result<String, String> {
+    match args {
+        ["help"] => Ok(HELP.to_string()),
+        ["kick", name, message @ ..] => {
+            let id = find_player(ctx.lobby, name)?;
+            let message = (!message.is_empty()).then(|| message.join(" "));
+            disconnect(ctx, id, DisconnectReason::Kicked { message });
+            Ok(format!("{} was kicked", name))
+        }
+        ["ban", name, rest @ ..] => {
+            let ban = parse_ban(rest)?;
+            if let Ok(id) = find_player(ctx.lobby, name) {
+                disconnect(ctx, id, ban.to_disconnect_reason());
+            }
+            ctx.access.bans.data.players.insert(name.to_string(), ban);
+            save_bans(ctx)?;
+            Ok(format!("{} was banned", name))
+        }
+        ["ban-ip", target, rest @ ..] => {
+            let ip = match target.parse::<IpAddr>() {
+                Ok(ip) => ip,
+                Err(_) => {
+                    let id = find_player(ctx.lobby, target)?;
+                    player_ip(ctx, id).ok_or(format!("{} has no known address", target))?
+                }
+            };
+            let ban = parse_ban(rest)?;
+            let banned_players: Vec<PlayerId> = ctx
+                .lobby
+                .players
+                .keys()
+                .filter(|id| player_ip(ctx, **id) == Some(ip))
+                .copied()
+                .collect();
+            for id in banned_players {
+                disconnect(ctx, id, ban.to_disconnect_reason());
+            }
+            ctx.access.bans.data.ips.insert(ip, ban);
+            save_bans(ctx)?;
+            Ok(format!("{} was banned", ip))
+        }
+        ["pardon", name] => {
+            if ctx.access.bans.data.players.remove(*name).is_none() {
+                return Err(format!("{} is not banned", name));
+            }
+            save_bans(ctx)?;
+            Ok(format!("{} was unbanned", name))
+        }
+        ["pardon-ip", ip] => {
+            let ip = ip.parse::<IpAddr>().map_err(|e| e.to_string())?;
+            if ctx.access.bans.data.ips.remove(&ip).is_none() {
+                return Err(format!("{} is not banned", ip));
+            }
+            save_bans(ctx)?;
+            Ok(format!("{} was unbanned", ip))
+        }
+        ["banlist"] => {
+            let bans = &ctx.access.bans.data;
+            let entries: Vec<String> = bans
+                .players
+                .keys()
+                .cloned()
+                .chain(bans.ips.keys().map(|ip| ip.to_string()))
+                .collect();
+            Ok(format!("Banned: {}", entries.join(", ")))
+        }
+        ["whitelist", toggle @ ("on" | "off")] => {
+            let enabled = *toggle == "on";
+            ctx.access.whitelist.data.enabled = enabled;
+            save_whitelist(ctx)?;
+            Ok(format!(
+                "Whitelist {}",
+                if enabled { "enabled" } else { "disabled" }
+            ))
+        }
+        ["whitelist", "add", name] => {
+            ctx.access.whitelist.data.players.insert(name.to_string());
+            save_whitelist(ctx)?;
+            Ok(format!("{} was added to the whitelist", name))
+        }
+        ["whitelist", "remove", name] => {
+            if !ctx.access.whitelist.data.players.remove(*name) {
+                return Err(format!("{} is not whitelisted", name));
+            }
+            save_whitelist(ctx)?;
+            Ok(format!("{} was removed from the whitelist", name))
+        }
+        ["whitelist", "list"] => {
+            let whitelist = &ctx.access.whitelist.data;
+            let players: Vec<&str> = whitelist.players.iter().map(|p| p.as_str()).collect();
+            Ok(format!(
+                "Whitelist ({}): {}",
+                if whitelist.enabled { "on" } else { "off" },
+                players.join(", ")
+            ))
+        }
+        ["op", name] => {
+            ctx.access.ops.data.insert(name.to_string());
+            save_ops(ctx)?;
+            Ok(format!("{} is now an operator", name))
+        }
+        ["deop", name] => {
+            if !ctx.access.ops.data.remove(*name) {
+                return Err(format!("{} is not an operator", name));
+            }
+            save_ops(ctx)?;
+            Ok(format!("{} is no longer an operator", name))
+        }
+        ["reload"] => {
+            ctx.access.reload(ctx.paths);
+            Ok("Access lists reloaded".to_string())
+        }
+        ["backups"] => {
+            let backups = list_backups(ctx.storage).map_err(|e| e.to_string())?;
+            Ok(format!("Backups: {}", backups.join(", ")))
+        }
+        ["restore", name] => {
+            let backups = list_backups(ctx.storage).map_err(|e| e.to_string())?;
+            if !backups.iter().any(|backup| backup == name) {
+                return Err(format!("Unknown backup: {}", name));
+            }
+            ctx.pending_restore.0 = Some(name.to_string());
+            Ok(format!("Restoring backup {}", name))
+        }
+        ["pregen", "status"] => ctx
+            .pregen
+            .status()
+            .ok_or("No pre-generation is running".to_string()),
+        ["pregen", "cancel"] => {
+            ctx.pregen.cancel(ctx.storage)?;
+            Ok("Pre-generation cancelled".to_string())
+        }
+        ["pregen", x, z, radius] => {
+            let x = parse_number(x)?;
+            let z = parse_number(z)?;
+            let radius = parse_number(radius)?;
+            if !(0..=MAX_PREGEN_RADIUS).contains(&radius) {
+                return Err(format!(
+                    "The radius must be between 0 and {} blocks",
+                    MAX_PREGEN_RADIUS
+                ));
+            }
+            // The area covers every chunk within `radius` blocks of the center
+            let center = IVec2::new(x, z).div_euclid(IVec2::splat(CHUNK_SIZE));
+            let job = PregenJob::new(center, (radius + CHUNK_SIZE - 1) / CHUNK_SIZE);
+            let total = job.total_columns();
+            ctx.pregen
+                .start(job, ctx.metadata.height.chunk_layers(), ctx.storage)?;
+            Ok(format!(
+                "Pre-generating {} chunk columns around {} {}",
+                total, x, z
+            ))
+        }
+        [command, ..] => Err(format!("Unknown command or wrong arguments: {}", command)),
+        [] => Err(HELP.to_string()),
+    }
+}
+
+fn find_player(lobby: &ServerLobby, name: &str) -> Result<PlayerId, String> {
+    lobby
+        .players
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(id, _)| *id)
+        .ok_or(format!("Unknown player: {}", name))
+}
+
+fn player_ip(ctx: &CommandContext, id: PlayerId) -> Option<IpAddr> {
+    ctx.transport
+        .client_addr(ClientId::from_raw(id))
+        .map(|addr| addr.ip())
+}
+
+fn disconnect(ctx: &mut CommandContext, id: PlayerId, reason: DisconnectReason) {
+    disconnect_with_reason(ctx.server, ctx.pending, ClientId::from_raw(id), reason);
+}
+
+fn parse_number(value: &str) -> Result<i32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Not a number: {}", value))
+}
+
+/// Parses `[duration] [reason...]`, the ban is permanent without a duration
+fn parse_ban(args: &[&str]) -> Result<Ban, String> {
+    let (expires_at, reason) = match args.split_first() {
+        Some((first, rest)) => match parse_duration(first) {
+            Some(duration) => {
+                let expires_at = now_ms()
+                    .checked_add(duration)
+                    .ok_or(format!("Ban duration too long: {}", first))?;
+                (Some(expires_at), rest)
+            }
+            None => (None, args),
+        },
+        None => (None, args),
+    };
+
+    Ok(Ban {
+        reason: (!reason.is_empty()).then(|| reason.join(" ")),
+        expires_at,
+    })
+}
+
+/// Parses durations such as `30s`, `10m`, `2h`, `7d` or `1w` into milliseconds
+fn parse_duration(value: &str) -> Option<u64> {
+    let unit_index = value.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = value.split_at(unit_index);
+    if amount.is_empty() {
+        return None;
+    }
+    // Too long durations saturate, and are rejected by `parse_ban`
+    let amount: u64 = amount.parse().unwrap_or(u64::MAX);
+    let unit_ms = match unit {
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 604_800_000,
+        _ => return None,
+    };
+    Some(amount.saturating_mul(unit_ms))
+}
+
+fn save_bans(ctx: &mut CommandContext) -> Result<(), String> {
+    ctx.access
+        .bans
+        .save(ctx.paths)
+        .map_err(|e| format!("Failed to save bans: {}", e))
+}
+
+fn save_whitelist(ctx: &mut CommandContext) -> Result<(), String> {
+    ctx.access
+        .whitelist
+        .save(ctx.paths)
+        .map_err(|e| format!("Failed to save whitelist: {}", e))
+}
+
+fn save_ops(ctx: &mut CommandContext) -> Result<(), String> {
+    ctx.access
+        .ops
+        .save(ctx.paths)
+        .map_err(|e| format!("Failed to save operators: {}", e))
+}
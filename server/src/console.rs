@@ -0,0 +1,39 @@
+use crate::commands::{CommandEvent, CommandSender};
+use bevy::prelude::*;
+use crossbeam_channel::{unbounded, Receiver};
+use std::io::BufRead;
+use std::thread;
+
+/// Lines typed in the terminal running a dedicated server
+#[derive(Resource)]
+pub struct ConsoleInput {
+    receiver: Receiver<String>,
+}
+
+pub fn setup_console(app: &mut App) {
+    let (sender, receiver) = unbounded();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    app.insert_resource(ConsoleInput { receiver });
+    app.add_systems(Update, read_console_system);
+}
+
+fn read_console_system(console: Res<ConsoleInput>, mut ev_command: EventWriter<CommandEvent>) {
+    for line in console.receiver.try_iter() {
+        let command = line.trim();
+        if command.is_empty() {
+            continue;
+        }
+        ev_command.send(CommandEvent {
+            sender: CommandSender::Console,
+            command: command.to_string(),
+        });
+    }
+}
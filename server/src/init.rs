@@ -1,22 +1,33 @@
+use crate::access::setup_access_resources;
+use crate::console::setup_console;
 use crate::network::dispatcher::{self, setup_resources_and_events};
+use crate::network::memory::MemoryChannel;
+use crate::network::transport::{ServerTransport, ServerTransportPlugin};
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
 };
 use bevy_app::ScheduleRunnerPlugin;
-use bevy_renet::renet::transport::NetcodeServerTransport;
-use bevy_renet::renet::RenetServer;
+use bevy_renet::renet::{ClientId, RenetServer};
 use bevy_renet::RenetServerPlugin;
 use serde::{Deserialize, Serialize};
+use shared::world::ServerWorldMap;
 use shared::{get_shared_renet_config, messages::PlayerId, GameFolderPaths, GameServerConfig};
 use std::fmt::Debug;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 use std::{collections::HashMap, net::IpAddr};
 
-use crate::world::load_from_file::{load_world_map, load_world_seed, load_world_time};
+use crate::world::autosave::{setup_autosave, setup_shutdown_signals};
+use crate::world::backup::backup_due;
+use crate::world::generation_queue::setup_generation_queue;
+use crate::world::generator::{ActiveGenerator, GeneratorRegistry};
+use crate::world::load_from_file::{load_world, WorldLoadError};
+use crate::world::metadata::{load_metadata, setup_playtime};
+use crate::world::pregen::setup_pregeneration;
+use crate::world::save::SaveRequestEvent;
+use crate::world::storage::WorldStorage;
+use crate::world::unload::setup_chunk_unloading;
 
-use bevy_renet::renet::transport::{ServerAuthentication, ServerConfig};
-use bevy_renet::transport::NetcodeServerPlugin;
 use std::net::{SocketAddr, UdpSocket};
 
 #[derive(Resource, Serialize, Deserialize, Debug, Clone)]
@@ -25,9 +36,10 @@ pub struct ServerTime(pub u64);
 #[derive(Debug, Default, Resource)]
 pub struct ServerLobby {
     pub players: HashMap<PlayerId, String>,
+    /// Player hosting a solo world, the server stops when they leave
+    pub host: Option<PlayerId>,
 }
 
-#[allow(dead_code)]
 pub fn acquire_local_ephemeral_udp_socket(ip: IpAddr) -> UdpSocket {
     acquire_socket_by_port(ip, 0)
 }
@@ -37,30 +49,58 @@ pub fn acquire_socket_by_port(ip: IpAddr, port: u16) -> UdpSocket {
     UdpSocket::bind(addr).unwrap()
 }
 
-pub fn add_netcode_network(app: &mut App, socket: UdpSocket) {
-    app.add_plugins(NetcodeServerPlugin);
+/// How the first client reaches the server
+pub enum ServerEndpoint {
+    /// Dedicated server, listening on a UDP socket
+    Udp(UdpSocket),
+    /// Solo world, played by a client running in the same process
+    Memory {
+        client_id: PlayerId,
+        channel: MemoryChannel,
+    },
+}
 
-    let server = RenetServer::new(get_shared_renet_config());
+pub fn add_network(app: &mut App, endpoint: ServerEndpoint) {
+    app.add_plugins(ServerTransportPlugin);
 
-    let granted_addr = &socket.local_addr().unwrap();
+    let mut server = RenetServer::new(get_shared_renet_config());
+    let mut transport = ServerTransport::default();
 
-    let current_time: Duration = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap();
-    let server_config = ServerConfig {
-        current_time,
-        max_clients: 64,
-        protocol_id: shared::PROTOCOL_ID,
-        public_addresses: vec![*granted_addr],
-        authentication: ServerAuthentication::Unsecure,
-    };
+    match endpoint {
+        ServerEndpoint::Udp(socket) => {
+            let granted_addr = socket.local_addr().unwrap();
+            transport.add_listener(socket, vec![granted_addr]).unwrap();
+        }
+        ServerEndpoint::Memory { client_id, channel } => {
+            transport.add_memory_client(&mut server, ClientId::from_raw(client_id), channel);
+        }
+    }
 
-    let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
     app.insert_resource(server);
     app.insert_resource(transport);
 }
 
-pub fn init(socket: UdpSocket, config: GameServerConfig, game_folder_path: String) {
+/// Runs the server until it stops, fails early if the world cannot be loaded
+pub fn init(
+    endpoint: ServerEndpoint,
+    config: GameServerConfig,
+    game_folder_path: String,
+) -> Result<(), WorldLoadError> {
+    init_with_generators(
+        endpoint,
+        config,
+        game_folder_path,
+        GeneratorRegistry::default(),
+    )
+}
+
+/// Same as `init`, with the generators worlds can use, for code adding its own
+pub fn init_with_generators(
+    endpoint: ServerEndpoint,
+    config: GameServerConfig,
+    game_folder_path: String,
+    generators: GeneratorRegistry,
+) -> Result<(), WorldLoadError> {
     let mut app = App::new();
     app.add_plugins(
         MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
@@ -80,53 +120,99 @@ pub fn init(socket: UdpSocket, config: GameServerConfig, game_folder_path: Strin
     });
 
     let world_name = &config.world_name.clone();
+    let is_solo = config.is_solo;
+    let chunk_memory_budget_mb = config.chunk_memory_budget_mb;
+    let creation = config.creation.clone();
 
     app.insert_resource(config);
 
-    info!("Starting server on {}", socket.local_addr().unwrap());
+    match &endpoint {
+        ServerEndpoint::Udp(socket) => info!("Starting server on {}", socket.local_addr().unwrap()),
+        ServerEndpoint::Memory { .. } => info!("Starting local server"),
+    }
 
-    add_netcode_network(&mut app, socket);
+    add_network(&mut app, endpoint);
 
     setup_resources_and_events(&mut app);
+    setup_access_resources(&mut app);
+    // Solo worlds run inside the client, which has no terminal to read from
+    if !is_solo {
+        setup_console(&mut app);
+        setup_shutdown_signals(&mut app);
+    }
 
-    // Load world from files
-    let world_map = match load_world_map(world_name, &app) {
-        Ok(world) => world,
+    // Load world from files, chunks are loaded when players need them
+    let storage = WorldStorage::new(app.world().resource::<GameFolderPaths>(), world_name);
+    let level = match load_world(&storage, &creation) {
+        Ok(level) => {
+            info!("World seed loaded successfully: {}", level.seed.0);
+            level
+        }
         Err(e) => {
-            error!("Error loading world: {}. Generating a new one.", e);
-            panic!();
+            error!("Error loading world {}: {}", world_name, e);
+            return Err(e);
         }
     };
 
-    let world_seed = match load_world_seed(world_name, &app) {
-        Ok(seed) => {
-            info!("World seed loaded successfully: {}", seed.0); // Affiche la seed chargée
-            seed
-        }
+    let mut metadata = match load_metadata(&storage, world_name, &level, &creation) {
+        Ok(metadata) => metadata,
         Err(e) => {
-            error!("Error loading seed: {}. Generating a new one.", e);
-            panic!();
+            error!("Error loading world {}: {}", world_name, e);
+            return Err(e);
         }
     };
-
-    let server_time = match load_world_time(world_name, &app) {
-        Ok(time) => time,
+    let generator = match generators.create(&metadata.generator) {
+        Ok(generator) => generator,
         Err(e) => {
-            error!("Error loading time: {}. Defaulting to 0.", e);
-            0
+            let e = WorldLoadError::Generator(e);
+            error!("Error loading world {}: {}", world_name, e);
+            return Err(e);
         }
     };
+    if !storage.exists() {
+        metadata.spawn = generator.spawn_point(level.seed.0, metadata.height);
+    }
+    info!("World generator: {}", metadata.generator.name);
+    app.insert_resource(metadata);
 
     // Insert world_map and seed into ressources
-    app.insert_resource(world_map);
-    app.insert_resource(world_seed);
-    app.insert_resource(ServerTime(server_time));
+    app.insert_resource(ServerWorldMap {
+        name: world_name.clone(),
+        time: level.time,
+        ..Default::default()
+    });
+    app.insert_resource(level.seed);
+    app.insert_resource(ActiveGenerator(generator));
+    app.insert_resource(ServerTime(level.time));
+
+    // Keeps the world as it was before this session, unless a recent backup already does. The
+    // backup is made by a save right away, on the IO task pool.
+    if storage.exists() {
+        match backup_due(&storage) {
+            Ok(true) => {
+                app.world_mut().send_event(SaveRequestEvent {
+                    requester: None,
+                    backup: true,
+                });
+            }
+            Ok(false) => debug!("Skipping the startup backup, the last one is recent"),
+            Err(e) => error!("Failed to list backups: {}", e),
+        }
+    }
+    app.insert_resource(storage);
+
+    setup_chunk_unloading(&mut app, chunk_memory_budget_mb);
+    setup_generation_queue(&mut app);
+    setup_pregeneration(&mut app);
+    setup_autosave(&mut app);
+    setup_playtime(&mut app);
 
     dispatcher::register_systems(&mut app);
 
     setup_heartbeat(&mut app);
 
     app.run();
+    Ok(())
 }
 
 #[derive(Resource)]
@@ -1,7 +1,26 @@
+mod access;
+mod commands;
+mod console;
 mod init;
 mod network;
 mod player;
 pub mod time;
 mod world;
 
-pub use init::{acquire_local_ephemeral_udp_socket, init};
+pub use init::{
+    acquire_local_ephemeral_udp_socket, acquire_socket_by_port, init, init_with_generators,
+    ServerEndpoint,
+};
+pub use network::memory::{memory_channel_pair, MemoryChannel};
+pub use world::backup::{backup_due, copy_dir, create_backup, list_backups, restore_backup};
+pub use world::generation::{NoiseGenerator, GENERATOR_VERSION, SEA_LEVEL};
+pub use world::generator::{
+    generate_chunk, DebugGenerator, FlatGenerator, FlatLayer, GeneratorError, GeneratorRegistry,
+    VoidGenerator, WorldGenerator,
+};
+pub use world::load_from_file::{load_world, WorldLoadError};
+pub use world::metadata::load_metadata;
+pub use world::migrations::SAVE_VERSION;
+pub use world::pregen::{PregenJob, MAX_PREGEN_RADIUS, PREGEN_FILE};
+pub use world::preset::{BiomeRule, Bounds, TerrainPreset};
+pub use world::storage::{LevelData, WorldStorage};
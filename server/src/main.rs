@@ -1,15 +1,10 @@
 use std::net::Ipv4Addr;
 
-use crate::init::acquire_socket_by_port;
 use clap::Parser;
+use server::{acquire_socket_by_port, ServerEndpoint};
+use shared::world::{seed_from_text, GeneratorSettings, WorldCreationSettings, WorldHeight};
 use shared::GameServerConfig;
 
-mod init;
-mod network;
-mod player;
-mod time;
-mod world;
-
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
@@ -21,6 +16,34 @@ struct Args {
 
     #[arg(short, long, default_value = "../")]
     game_folder_path: String,
+
+    /// Memory the server may use for loaded chunks, in megabytes
+    #[arg(long)]
+    chunk_memory_mb: Option<usize>,
+
+    /// Seed of the world if it does not exist yet, numbers are used as is and any other text is hashed
+    #[arg(long)]
+    seed: Option<String>,
+
+    /// Generator of the world if it does not exist yet
+    #[arg(long, default_value = "default")]
+    generator: String,
+
+    /// Options of the generator in RON, for instance the layers of a flat world
+    #[arg(long, default_value = "", conflicts_with = "generator_preset")]
+    generator_options: String,
+
+    /// File holding the options of the generator, such as `data/generator_presets/default.ron`
+    #[arg(long)]
+    generator_preset: Option<String>,
+
+    /// Lowest height blocks can be placed at, if the world does not exist yet
+    #[arg(long, default_value_t = WorldHeight::default().min, allow_negative_numbers = true)]
+    min_height: i32,
+
+    /// Height blocks can be placed below, if the world does not exist yet
+    #[arg(long, default_value_t = WorldHeight::default().max, allow_negative_numbers = true)]
+    max_height: i32,
 }
 
 fn main() {
@@ -29,12 +52,46 @@ fn main() {
 
     let game_folder_path = args.game_folder_path.clone();
 
-    init::init(
-        socket,
+    let generator_options = match &args.generator_preset {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("Failed to read generator preset {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => args.generator_options,
+    };
+
+    let height = match WorldHeight::new(args.min_height, args.max_height) {
+        Ok(height) => height,
+        Err(e) => {
+            eprintln!("Invalid world height: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let result = server::init(
+        ServerEndpoint::Udp(socket),
         GameServerConfig {
             world_name: args.world,
             is_solo: false,
+            chunk_memory_budget_mb: args.chunk_memory_mb,
+            creation: WorldCreationSettings {
+                seed: args.seed.as_deref().and_then(seed_from_text),
+                generator: GeneratorSettings {
+                    name: args.generator,
+                    options: generator_options,
+                },
+                height,
+                ..Default::default()
+            },
         },
         game_folder_path,
     );
+
+    // The error was already logged by the server
+    if result.is_err() {
+        std::process::exit(1);
+    }
 }
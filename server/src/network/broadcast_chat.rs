@@ -2,7 +2,7 @@ use crate::network::dispatcher::BroadcastTimer;
 use bevy::prelude::*;
 use bevy_renet::renet::{DefaultChannel, RenetServer};
 use bincode::Options;
-use shared::messages::ChatConversation;
+use shared::messages::{ChatConversation, ChatMessage, ServerToClientMessage};
 
 #[derive(Event)]
 pub struct ChatMessageEvent;
@@ -12,6 +12,18 @@ pub fn setup_chat_resources(app: &mut App) {
     app.add_event::<ChatMessageEvent>();
 }
 
+/// Builds a chat message authored by the server itself
+pub fn server_chat_message(content: &str) -> ChatMessage {
+    ChatMessage {
+        author_name: "Server".into(),
+        date: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+        content: content.into(),
+    }
+}
+
 pub fn broadcast_chat_messages(
     mut server: ResMut<RenetServer>,
     chat_messages: Res<ChatConversation>,
@@ -25,7 +37,7 @@ pub fn broadcast_chat_messages(
             "Broadcasting chat history, {} messages",
             chat_messages.messages.len()
         );
-        let cm: ChatConversation = chat_messages.into_inner().clone();
+        let cm = ServerToClientMessage::ChatConversation(chat_messages.into_inner().clone());
         let serialized = bincode::options().serialize(&cm).unwrap();
         trace!("world {:?}", cm);
         trace!("serialized: {:?}", serialized);
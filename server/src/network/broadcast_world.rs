@@ -1,75 +1,89 @@
 use crate::init::ServerTime;
 use crate::init::TickCounter;
 use crate::network::utils::format_bytes;
-use crate::world::generation::generate_chunk;
+use crate::world::generation_queue::GenerationQueue;
+use crate::world::generator::ActiveGenerator;
+use crate::world::storage::{load_chunk, WorldStorage};
 use bevy::math::IVec3;
 use bevy::prelude::*;
 use bevy_ecs::system::ResMut;
 use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
 use bincode::Options;
 use shared::messages::{ServerToClientMessage, WorldUpdate};
-use shared::world::{chunk_in_radius, ServerChunk, ServerWorldMap};
+use shared::world::{chunk_in_radius, ServerChunk, ServerWorldMap, WorldMetadata};
 use std::collections::HashMap;
 
-use shared::world::data::WorldSeed;
-
 #[derive(Event, Debug)]
 pub struct WorldUpdateRequestEvent {
     pub client: ClientId,
     pub chunks: Vec<IVec3>,
     pub render_distance: u32,
+    pub vertical_render_distance: u32,
     pub player_chunk_position: IVec3,
 }
 
+/// Answers with the requested chunks which are in memory or on disk, the others are generated
+/// on a task pool and sent by `generate_chunks_system` once ready
 pub fn send_world_update(
     mut server: ResMut<RenetServer>,
     ticker: Res<TickCounter>,
-    seed: Res<WorldSeed>,
+    storage: Res<WorldStorage>,
+    metadata: Res<WorldMetadata>,
+    generator: Res<ActiveGenerator>,
+    mut queue: ResMut<GenerationQueue>,
     mut world_map: ResMut<ServerWorldMap>,
     mut ev_update: EventReader<WorldUpdateRequestEvent>,
 ) {
-    let mut chunks_to_update_count = 0;
     for event in ev_update.read() {
-        let payload = bincode::options()
-            .serialize(&ServerToClientMessage::WorldUpdate(WorldUpdate {
-                tick: ticker.tick,
-                player_positions: world_map.player_positions.clone(),
-                new_map: {
-                    let mut map: HashMap<IVec3, ServerChunk> = HashMap::new();
-                    for c in event.chunks.iter() {
-                        if chunk_in_radius(
-                            &event.player_chunk_position,
-                            c,
-                            event.render_distance as i32,
-                        ) {
-                            let chunk = world_map.map.get(c);
-
-                            // If chunk already exists, transmit it to client
-                            if let Some(chunk) = chunk {
-                                if chunk.map.is_empty() {
-                                    continue;
-                                }
+        queue.update_view(
+            event.client,
+            event.player_chunk_position,
+            event.render_distance,
+            event.vertical_render_distance,
+        );
 
-                                chunks_to_update_count += 1;
-                                map.insert(*c, chunk.clone());
-                            } else {
-                                // If chunk does not exists, generate it before transmitting it
-                                let chunk = generate_chunk(*c, seed.0);
+        let mut map: HashMap<IVec3, ServerChunk> = HashMap::new();
+        let mut empty_chunks = Vec::new();
+        for c in event.chunks.iter() {
+            if !chunk_in_radius(
+                &event.player_chunk_position,
+                c,
+                event.render_distance as i32,
+                event.vertical_render_distance as i32,
+            ) {
+                continue;
+            }
 
-                                // If chunk is empty, do not create it to prevent unnecessary data transmission
-                                if chunk.map.is_empty() {
-                                    continue;
-                                }
+            // Nothing can be above or below the world, those chunks are never loaded, nor are
+            // chunks already generated empty
+            if !metadata.height.chunk_layers().contains(&c.y) || world_map.empty_chunks.contains(c)
+            {
+                empty_chunks.push(*c);
+                continue;
+            }
 
-                                chunks_to_update_count += 1;
-                                map.insert(*c, chunk.clone());
-                                world_map.map.insert(*c, chunk);
-                            }
-                        }
+            // Chunks are read from disk, or generated, the first time they are needed
+            match load_chunk(&mut world_map, &storage, generator.0.version(), *c) {
+                // Only the positions of empty chunks are sent, to prevent unnecessary data transmission
+                Ok(Some(chunk)) => {
+                    if chunk.map.is_empty() {
+                        empty_chunks.push(*c);
+                    } else {
+                        map.insert(*c, chunk.clone());
                     }
-                    trace!("Update event yippeee :D    len={}", map.len());
-                    map
-                },
+                }
+                Ok(None) => queue.request(*c, event.client),
+                Err(e) => error!("Failed to load chunk {:?}: {}", c, e),
+            }
+        }
+
+        let chunks_to_update_count = map.len();
+        let payload = bincode::options()
+            .serialize(&ServerToClientMessage::WorldUpdate(WorldUpdate {
+                tick: ticker.tick,
+                player_positions: world_map.player_positions.clone(),
+                new_map: map,
+                empty_chunks,
                 time: world_map.time,
             }))
             .unwrap();
@@ -89,7 +103,7 @@ pub fn broadcast_world_state(
     mut world_map: ResMut<ServerWorldMap>,
     time: Res<ServerTime>,
 ) {
-    if ticker.tick % 10 != 0 {
+    if !ticker.tick.is_multiple_of(10) {
         return;
     }
 
@@ -114,12 +128,16 @@ fn to_network(world_map: &mut ServerWorldMap, tick: u64) -> WorldUpdate {
             let mut m: HashMap<IVec3, ServerChunk> = HashMap::new();
             // Only send chunks that must be updated
             for v in world_map.chunks_to_update.iter() {
-                m.insert(*v, world_map.map.get(v).unwrap().clone());
+                // The chunk may have been unloaded since it changed
+                if let Some(chunk) = world_map.map.get(v) {
+                    m.insert(*v, chunk.clone());
+                }
             }
             // Chunks are up do date, clear the vector
             world_map.chunks_to_update.clear();
             m
         },
+        empty_chunks: Vec::new(),
         time: world_map.time,
     }
 }
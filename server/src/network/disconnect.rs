@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
+use bevy_renet::RenetSend;
+use bincode::Options;
+use shared::messages::{DisconnectReason, ServerToClientMessage};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Time left for the disconnect message to reach the client before the connection is closed
+pub const DISCONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Clients which were told why they are disconnected, and will be once the delay is over
+#[derive(Resource, Debug, Default)]
+pub struct PendingDisconnections {
+    clients: HashMap<ClientId, Timer>,
+}
+
+impl PendingDisconnections {
+    pub fn contains(&self, client_id: ClientId) -> bool {
+        self.clients.contains_key(&client_id)
+    }
+}
+
+fn disconnect_payload(reason: DisconnectReason) -> Vec<u8> {
+    bincode::options()
+        .serialize(&ServerToClientMessage::Disconnect { reason })
+        .unwrap()
+}
+
+/// Tells the client why it is being disconnected, then closes the connection after a short delay
+pub fn disconnect_with_reason(
+    server: &mut RenetServer,
+    pending: &mut PendingDisconnections,
+    client_id: ClientId,
+    reason: DisconnectReason,
+) {
+    if pending.contains(client_id) {
+        return;
+    }
+
+    info!("Disconnecting player {}: {:?}", client_id, reason);
+    server.send_message(
+        client_id,
+        DefaultChannel::ReliableOrdered,
+        disconnect_payload(reason),
+    );
+    pending
+        .clients
+        .insert(client_id, Timer::new(DISCONNECT_DELAY, TimerMode::Once));
+}
+
+pub fn setup_disconnect_resources(app: &mut App) {
+    app.insert_resource(PendingDisconnections::default());
+}
+
+pub fn pending_disconnections_system(
+    mut server: ResMut<RenetServer>,
+    mut pending: ResMut<PendingDisconnections>,
+    time: Res<Time>,
+) {
+    pending.clients.retain(|client_id, timer| {
+        timer.tick(time.delta());
+        if !timer.finished() {
+            return true;
+        }
+        if server.is_connected(*client_id) {
+            server.disconnect(*client_id);
+        }
+        false
+    });
+}
+
+/// Lets every client know the server is going down, before the transport closes the connections
+pub fn notify_shutdown_system(exit: EventReader<AppExit>, mut server: ResMut<RenetServer>) {
+    if exit.is_empty() {
+        return;
+    }
+
+    server.broadcast_message(
+        DefaultChannel::ReliableOrdered,
+        disconnect_payload(DisconnectReason::Shutdown),
+    );
+}
+
+pub fn register_disconnect_systems(app: &mut App) {
+    app.add_systems(Update, pending_disconnections_system);
+    app.add_systems(PostUpdate, notify_shutdown_system.before(RenetSend));
+}
@@ -1,20 +1,31 @@
+use crate::access::AccessLists;
+use crate::commands::{
+    handle_commands_system, setup_command_resources, CommandEvent, CommandSender,
+};
 use crate::init::{ServerLobby, TickCounter};
 use crate::network::broadcast_chat::*;
 use crate::network::broadcast_world::WorldUpdateRequestEvent;
 use crate::network::broadcast_world::*;
+use crate::network::disconnect::{
+    disconnect_with_reason, register_disconnect_systems, setup_disconnect_resources,
+    PendingDisconnections,
+};
+use crate::network::lan::{open_to_lan_system, setup_lan_resources, OpenToLanEvent};
+use crate::network::transport::{ServerTransport, MAX_CLIENTS};
 use crate::player::handle_player_inputs;
 use crate::time::update_server_time;
 use crate::world;
-use crate::world::save::SaveRequestEvent;
+use crate::world::backup::PendingRestore;
+use crate::world::save::{SaveRequestEvent, WorldSaver};
 use crate::world::BlockInteractionEvent;
 use bevy::prelude::*;
 use bevy_renet::renet::{DefaultChannel, RenetServer, ServerEvent};
 use bincode::Options;
 use shared::messages::{
-    AuthRegisterResponse, ChatConversation, ClientToServerMessage, PlayerSpawnEvent,
-    ServerToClientMessage,
+    AuthRegisterResponse, ChatConversation, ClientToServerMessage, DisconnectReason,
+    PlayerSpawnEvent, ServerToClientMessage,
 };
-use shared::world::ServerWorldMap;
+use shared::world::{ServerWorldMap, WorldMetadata};
 use shared::GameServerConfig;
 
 #[derive(Resource)]
@@ -26,11 +37,16 @@ pub fn setup_resources_and_events(app: &mut App) {
     app.insert_resource(BroadcastTimer {
         timer: Timer::from_seconds(2.0, TimerMode::Repeating),
     })
+    .insert_resource(WorldSaver::default())
+    .insert_resource(PendingRestore::default())
     .add_event::<WorldUpdateRequestEvent>()
     .add_event::<SaveRequestEvent>()
     .add_event::<BlockInteractionEvent>();
 
     setup_chat_resources(app);
+    setup_lan_resources(app);
+    setup_disconnect_resources(app);
+    setup_command_resources(app);
 }
 
 pub fn register_systems(app: &mut App) {
@@ -38,20 +54,43 @@ pub fn register_systems(app: &mut App) {
 
     app.add_systems(Update, broadcast_chat_messages);
 
-    app.add_systems(Update, (broadcast_world_state, send_world_update));
+    app.add_systems(
+        Update,
+        (
+            broadcast_world_state,
+            send_world_update,
+            world::generation_queue::generate_chunks_system,
+        )
+            .chain(),
+    );
 
-    app.add_systems(Update, world::save::save_world_system);
+    app.add_systems(
+        Update,
+        (
+            world::backup::restore_backup_system,
+            world::save::save_world_system,
+        )
+            .chain(),
+    );
+    app.add_systems(Last, world::save::save_on_exit_system);
     app.add_systems(Update, world::handle_block_interactions);
 
     app.add_systems(Update, update_server_time);
+
+    app.add_systems(Update, open_to_lan_system);
+
+    app.add_systems(Update, handle_commands_system);
+
+    register_disconnect_systems(app);
 }
 
 fn server_update_system(
     mut server_events: EventReader<ServerEvent>,
-    (mut server, mut chat_conversation, mut lobby, tick): (
+    (mut server, mut chat_conversation, mut lobby, mut pending, tick): (
         ResMut<RenetServer>,
         ResMut<ChatConversation>,
         ResMut<ServerLobby>,
+        ResMut<PendingDisconnections>,
         Res<TickCounter>,
     ),
     (
@@ -60,14 +99,23 @@ fn server_update_system(
         mut ev_world_update_request,
         mut ev_save_request,
         mut ev_block_interaction,
+        mut ev_open_to_lan,
+        mut ev_command,
     ): (
         EventWriter<ChatMessageEvent>,
         EventWriter<AppExit>,
         EventWriter<WorldUpdateRequestEvent>,
         EventWriter<SaveRequestEvent>,
         EventWriter<BlockInteractionEvent>,
+        EventWriter<OpenToLanEvent>,
+        EventWriter<CommandEvent>,
+    ),
+    (config, access, transport, metadata): (
+        Res<GameServerConfig>,
+        Res<AccessLists>,
+        Res<ServerTransport>,
+        Res<WorldMetadata>,
     ),
-    config: Res<GameServerConfig>,
     mut world_map: ResMut<ServerWorldMap>,
 ) {
     for event in server_events.read() {
@@ -78,11 +126,24 @@ fn server_update_system(
             }
             ServerEvent::ClientDisconnected { client_id, reason } => {
                 info!("Player {} disconnected: {}", client_id, reason);
+                lobby.players.remove(&client_id.raw());
+                world_map.player_positions.remove(&client_id.raw());
+
+                // The host may have left without sending an exit order (e.g. connection lost)
+                if config.is_solo && lobby.host == Some(client_id.raw()) {
+                    info!("Host left, server is going down...");
+                    ev_app_exit.send(AppExit::Success);
+                }
             }
         }
     }
 
     for client_id in server.clients_id() {
+        // Ignore players who are about to be disconnected
+        if pending.contains(client_id) {
+            continue;
+        }
+
         while let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered)
         {
             let msg = bincode::options().deserialize::<ClientToServerMessage>(&message);
@@ -100,7 +161,36 @@ fn server_update_system(
 
                     if lobby.players.values().any(|v| *v == auth_req.username) {
                         debug!("Username already in map: {}", &auth_req.username);
-                        return;
+                        disconnect_with_reason(
+                            &mut server,
+                            &mut pending,
+                            client_id,
+                            DisconnectReason::DuplicateUsername,
+                        );
+                        break;
+                    }
+
+                    if lobby.players.len() >= MAX_CLIENTS {
+                        disconnect_with_reason(
+                            &mut server,
+                            &mut pending,
+                            client_id,
+                            DisconnectReason::ServerFull,
+                        );
+                        break;
+                    }
+
+                    if config.is_solo && lobby.host.is_none() {
+                        lobby.host = Some(client_id.raw());
+                    }
+
+                    // The host of a solo world can always play in it
+                    if lobby.host != Some(client_id.raw()) {
+                        let ip = transport.client_addr(client_id).map(|addr| addr.ip());
+                        if let Some(reason) = access.check(&auth_req.username, ip) {
+                            disconnect_with_reason(&mut server, &mut pending, client_id, reason);
+                            break;
+                        }
                     }
 
                     // let new_session_token = generate_session_token();
@@ -112,14 +202,16 @@ fn server_update_system(
                     let spawn_message = PlayerSpawnEvent {
                         id: client_id.raw(),
                         name: auth_req.username,
-                        position: Vec3::new(0.0, 80.0, 0.0),
+                        position: metadata.spawn,
                     };
 
                     // TODO: add cleanup system if no heartbeat
-                    let msg = &(AuthRegisterResponse {
+                    let msg = &ServerToClientMessage::AuthRegisterResponse(AuthRegisterResponse {
                         username: spawn_message.name.clone(),
                         session_token: client_id.raw() as u128,
                         spawn_event: spawn_message.clone(),
+                        server_version: shared::GAME_VERSION.to_string(),
+                        world_height: metadata.height,
                     });
                     let auth_response_payload = bincode::options().serialize(msg).unwrap();
 
@@ -133,7 +225,7 @@ fn server_update_system(
                         let spawn_message = PlayerSpawnEvent {
                             id: *id,
                             name: name.into(),
-                            position: Vec3::new(0.0, 80.0, 0.0),
+                            position: metadata.spawn,
                         };
 
                         let spawn_message_wrapped =
@@ -147,6 +239,14 @@ fn server_update_system(
                         info!("Sending spawn order {:?}", spawn_message_wrapped);
                     }
                 }
+                ClientToServerMessage::ChatMessage(chat_msg)
+                    if chat_msg.content.starts_with('/') =>
+                {
+                    ev_command.send(CommandEvent {
+                        sender: CommandSender::Player(client_id),
+                        command: chat_msg.content,
+                    });
+                }
                 ClientToServerMessage::ChatMessage(chat_msg) => {
                     info!("Chat message received: {:?}", &chat_msg);
                     chat_conversation.messages.push(chat_msg);
@@ -155,7 +255,8 @@ fn server_update_system(
                 ClientToServerMessage::Exit(order) => {
                     debug!("Received shutdown order... {:?}", order);
                     // TODO: add permission checks
-                    if config.is_solo {
+                    // Other players of a world opened to LAN leave without stopping it
+                    if config.is_solo && lobby.host == Some(client_id.raw()) {
                         info!("Server is going down...");
                         ev_app_exit.send(AppExit::Success);
                     } else {
@@ -173,22 +274,28 @@ fn server_update_system(
                         save_req.session_token
                     );
 
-                    ev_save_request.send(SaveRequestEvent);
+                    ev_save_request.send(SaveRequestEvent {
+                        requester: Some(client_id),
+                        backup: false,
+                    });
                 }
                 ClientToServerMessage::WorldUpdateRequest {
                     player_chunk_position,
                     requested_chunks,
                     render_distance,
+                    vertical_render_distance,
                 } => {
                     debug!(
-                        "Received WorldUpdateRequest: client_id = {}, player_chunk_position = {:?}, render_distance = {}, requested_chunks = {}",
+                        "Received WorldUpdateRequest: client_id = {}, player_chunk_position = {:?}, render_distance = {}, vertical_render_distance = {}, requested_chunks = {}",
                         client_id,
                         player_chunk_position,
                         render_distance,
+                        vertical_render_distance,
                         requested_chunks.len(),
                     );
                     ev_world_update_request.send(WorldUpdateRequestEvent {
                         render_distance,
+                        vertical_render_distance,
                         client: client_id,
                         chunks: requested_chunks,
                         player_chunk_position,
@@ -211,6 +318,16 @@ fn server_update_system(
                 ClientToServerMessage::SetPlayerPosition { position } => {
                     world_map.player_positions.insert(client_id.raw(), position);
                 }
+                ClientToServerMessage::OpenToLan => {
+                    if lobby.host == Some(client_id.raw()) {
+                        ev_open_to_lan.send(OpenToLanEvent);
+                    } else {
+                        warn!(
+                            "Player {} tried to open the world to LAN without hosting it",
+                            client_id
+                        );
+                    }
+                }
             }
         }
     }
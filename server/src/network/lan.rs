@@ -0,0 +1,76 @@
+use crate::network::broadcast_chat::{server_chat_message, ChatMessageEvent};
+use crate::network::transport::{local_lan_ip, ServerTransport};
+use bevy::prelude::*;
+use shared::messages::ChatConversation;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+
+/// Port tried first when opening a world to LAN, falls back to a random one if already in use
+pub const LAN_PORT: u16 = 8000;
+
+#[derive(Event, Debug)]
+pub struct OpenToLanEvent;
+
+#[derive(Resource, Debug, Default)]
+pub struct LanState {
+    /// Address other players on the local network can join, if the world was opened to LAN
+    pub address: Option<SocketAddr>,
+}
+
+pub fn setup_lan_resources(app: &mut App) {
+    app.insert_resource(LanState::default());
+    app.add_event::<OpenToLanEvent>();
+}
+
+fn bind_lan_socket() -> std::io::Result<UdpSocket> {
+    let any = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+    UdpSocket::bind(SocketAddr::new(any, LAN_PORT))
+        .or_else(|_| UdpSocket::bind(SocketAddr::new(any, 0)))
+}
+
+pub fn open_to_lan_system(
+    mut events: EventReader<OpenToLanEvent>,
+    mut transport: ResMut<ServerTransport>,
+    mut lan: ResMut<LanState>,
+    mut chat: ResMut<ChatConversation>,
+    mut ev_chat: EventWriter<ChatMessageEvent>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    if lan.address.is_some() {
+        debug!("World is already opened to LAN");
+        return;
+    }
+
+    let Some(lan_ip) = local_lan_ip() else {
+        error!("Could not find a LAN address for this machine");
+        chat.messages.push(server_chat_message(
+            "Could not open the world to LAN: no network found",
+        ));
+        ev_chat.send(ChatMessageEvent);
+        return;
+    };
+
+    let result = bind_lan_socket().and_then(|socket| {
+        let address = SocketAddr::new(lan_ip, socket.local_addr()?.port());
+        transport.add_listener(socket, vec![address])?;
+        Ok(address)
+    });
+
+    let content = match result {
+        Ok(address) => {
+            info!("World opened to LAN on {}", address);
+            lan.address = Some(address);
+            format!("World opened to LAN on {}", address)
+        }
+        Err(e) => {
+            error!("Failed to open world to LAN: {}", e);
+            format!("Could not open the world to LAN: {}", e)
+        }
+    };
+
+    chat.messages.push(server_chat_message(&content));
+    ev_chat.send(ChatMessageEvent);
+}
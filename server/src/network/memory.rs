@@ -0,0 +1,44 @@
+use crossbeam_channel::{unbounded, Receiver, Sender, TryRecvError};
+
+/// One end of an in-memory connection between a client and a server running in the same process.
+/// Raw renet packets go through it, without any socket nor netcode encryption.
+#[derive(Debug)]
+pub struct MemoryChannel {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+}
+
+/// Returned once the other end of a `MemoryChannel` has been dropped
+#[derive(Debug)]
+pub struct MemoryChannelClosed;
+
+/// Creates both ends of an in-memory connection
+pub fn memory_channel_pair() -> (MemoryChannel, MemoryChannel) {
+    let (client_sender, server_receiver) = unbounded();
+    let (server_sender, client_receiver) = unbounded();
+    (
+        MemoryChannel {
+            sender: client_sender,
+            receiver: client_receiver,
+        },
+        MemoryChannel {
+            sender: server_sender,
+            receiver: server_receiver,
+        },
+    )
+}
+
+impl MemoryChannel {
+    pub fn send(&self, packet: Vec<u8>) -> Result<(), MemoryChannelClosed> {
+        self.sender.send(packet).map_err(|_| MemoryChannelClosed)
+    }
+
+    /// Returns the next packet sent by the other end, if any
+    pub fn try_receive(&self) -> Result<Option<Vec<u8>>, MemoryChannelClosed> {
+        match self.receiver.try_recv() {
+            Ok(packet) => Ok(Some(packet)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(MemoryChannelClosed),
+        }
+    }
+}
@@ -1,4 +1,8 @@
 pub mod broadcast_chat;
 pub mod broadcast_world;
+pub mod disconnect;
 pub mod dispatcher;
+pub mod lan;
+pub mod memory;
+pub mod transport;
 pub mod utils;
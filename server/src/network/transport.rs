@@ -0,0 +1,341 @@
+use crate::network::memory::MemoryChannel;
+use bevy::{app::AppExit, prelude::*};
+use bevy_renet::renet::transport::{NetcodeTransportError, ServerAuthentication, ServerConfig};
+use bevy_renet::renet::{ClientId, RenetServer};
+use bevy_renet::{RenetReceive, RenetSend, RenetServerPlugin};
+use renetcode::{NetcodeServer, ServerResult, NETCODE_MAX_PACKET_BYTES};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime};
+
+pub const MAX_CLIENTS: usize = 64;
+
+/// A netcode server bound to a single UDP socket
+#[derive(Debug)]
+struct NetcodeListener {
+    socket: UdpSocket,
+    netcode_server: NetcodeServer,
+}
+
+/// A client running in the same process as the server
+#[derive(Debug)]
+struct MemoryClient {
+    client_id: ClientId,
+    channel: MemoryChannel,
+}
+
+/// Server-side transport able to serve clients through several UDP sockets at once,
+/// as well as clients connected through in-memory channels.
+/// Works like renet's `NetcodeServerTransport`, but listeners can be added while the
+/// server is running (e.g. when a solo world is opened to LAN).
+#[derive(Resource, Debug)]
+pub struct ServerTransport {
+    listeners: Vec<NetcodeListener>,
+    memory_clients: Vec<MemoryClient>,
+    buffer: [u8; NETCODE_MAX_PACKET_BYTES],
+}
+
+impl Default for ServerTransport {
+    fn default() -> Self {
+        Self {
+            listeners: Vec::new(),
+            memory_clients: Vec::new(),
+            buffer: [0; NETCODE_MAX_PACKET_BYTES],
+        }
+    }
+}
+
+impl ServerTransport {
+    /// Starts serving clients on `socket`. Clients must connect using one of `public_addresses`.
+    pub fn add_listener(
+        &mut self,
+        socket: UdpSocket,
+        public_addresses: Vec<SocketAddr>,
+    ) -> io::Result<()> {
+        socket.set_nonblocking(true)?;
+
+        let current_time: Duration = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let server_config = ServerConfig {
+            current_time,
+            max_clients: MAX_CLIENTS,
+            protocol_id: shared::PROTOCOL_ID,
+            public_addresses,
+            authentication: ServerAuthentication::Unsecure,
+        };
+
+        self.listeners.push(NetcodeListener {
+            socket,
+            netcode_server: NetcodeServer::new(server_config),
+        });
+        Ok(())
+    }
+
+    /// Connects a client living in the same process, bypassing netcode entirely
+    pub fn add_memory_client(
+        &mut self,
+        server: &mut RenetServer,
+        client_id: ClientId,
+        channel: MemoryChannel,
+    ) {
+        server.add_connection(client_id);
+        self.memory_clients
+            .push(MemoryClient { client_id, channel });
+    }
+
+    /// Address of a client connected through one of the listeners
+    pub fn client_addr(&self, client_id: ClientId) -> Option<SocketAddr> {
+        self.listeners
+            .iter()
+            .find_map(|l| l.netcode_server.client_addr(client_id.raw()))
+    }
+
+    /// Advances every listener by the duration, and receive packets from the network.
+    pub fn update(
+        &mut self,
+        duration: Duration,
+        server: &mut RenetServer,
+    ) -> Result<(), NetcodeTransportError> {
+        for listener in self.listeners.iter_mut() {
+            listener.netcode_server.update(duration);
+
+            loop {
+                match listener.socket.recv_from(&mut self.buffer) {
+                    Ok((len, addr)) => {
+                        let server_result = listener
+                            .netcode_server
+                            .process_packet(addr, &mut self.buffer[..len]);
+                        handle_server_result(server_result, &listener.socket, server);
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => break,
+                    Err(ref e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+                    Err(e) => return Err(e.into()),
+                };
+            }
+
+            for client_id in listener.netcode_server.clients_id() {
+                let server_result = listener.netcode_server.update_client(client_id);
+                handle_server_result(server_result, &listener.socket, server);
+            }
+
+            for disconnection_id in server.disconnections_id() {
+                if !listener
+                    .netcode_server
+                    .is_client_connected(disconnection_id.raw())
+                {
+                    continue;
+                }
+                let server_result = listener.netcode_server.disconnect(disconnection_id.raw());
+                handle_server_result(server_result, &listener.socket, server);
+            }
+        }
+
+        self.memory_clients.retain(|client| loop {
+            match client.channel.try_receive() {
+                Ok(Some(payload)) => {
+                    if let Err(e) = server.process_packet_from(&payload, client.client_id) {
+                        error!(
+                            "Error while processing payload for {}: {}",
+                            client.client_id, e
+                        );
+                    }
+                }
+                Ok(None) => break true,
+                // The client has been dropped
+                Err(_) => {
+                    server.remove_connection(client.client_id);
+                    break false;
+                }
+            }
+        });
+
+        for disconnection_id in server.disconnections_id() {
+            if let Some(index) = self
+                .memory_clients
+                .iter()
+                .position(|c| c.client_id == disconnection_id)
+            {
+                // Dropping the channel lets the client know it has been disconnected
+                self.memory_clients.remove(index);
+                server.remove_connection(disconnection_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send packets to connected clients, through the listener they are connected to.
+    pub fn send_packets(&mut self, server: &mut RenetServer) {
+        'clients: for client_id in server.clients_id() {
+            let Some(listener) = self
+                .listeners
+                .iter_mut()
+                .find(|l| l.netcode_server.is_client_connected(client_id.raw()))
+            else {
+                if let Some(client) = self
+                    .memory_clients
+                    .iter()
+                    .find(|c| c.client_id == client_id)
+                {
+                    for packet in server.get_packets_to_send(client_id).unwrap() {
+                        if client.channel.send(packet).is_err() {
+                            continue 'clients;
+                        }
+                    }
+                }
+                continue;
+            };
+
+            let packets = server.get_packets_to_send(client_id).unwrap();
+            for packet in packets {
+                match listener
+                    .netcode_server
+                    .generate_payload_packet(client_id.raw(), &packet)
+                {
+                    Ok((addr, payload)) => {
+                        if let Err(e) = listener.socket.send_to(payload, addr) {
+                            error!("Failed to send packet to client {client_id} ({addr}): {e}");
+                            continue 'clients;
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to encrypt payload packet for client {client_id}: {e}");
+                        continue 'clients;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Disconnects all connected clients, sending the disconnect packets instantly.
+    pub fn disconnect_all(&mut self, server: &mut RenetServer) {
+        for listener in self.listeners.iter_mut() {
+            for client_id in listener.netcode_server.clients_id() {
+                let server_result = listener.netcode_server.disconnect(client_id);
+                handle_server_result(server_result, &listener.socket, server);
+            }
+        }
+
+        for client in self.memory_clients.drain(..) {
+            server.remove_connection(client.client_id);
+        }
+    }
+}
+
+fn handle_server_result(
+    server_result: ServerResult,
+    socket: &UdpSocket,
+    reliable_server: &mut RenetServer,
+) {
+    let send_packet = |packet: &[u8], addr: SocketAddr| {
+        if let Err(err) = socket.send_to(packet, addr) {
+            error!("Failed to send packet to {addr}: {err}");
+        }
+    };
+
+    match server_result {
+        ServerResult::None => {}
+        ServerResult::PacketToSend { payload, addr } => {
+            send_packet(payload, addr);
+        }
+        ServerResult::Payload { client_id, payload } => {
+            let client_id = ClientId::from_raw(client_id);
+            if let Err(e) = reliable_server.process_packet_from(payload, client_id) {
+                error!("Error while processing payload for {}: {}", client_id, e);
+            }
+        }
+        ServerResult::ClientConnected {
+            client_id,
+            user_data: _,
+            addr,
+            payload,
+        } => {
+            reliable_server.add_connection(ClientId::from_raw(client_id));
+            send_packet(payload, addr);
+        }
+        ServerResult::ClientDisconnected {
+            client_id,
+            addr,
+            payload,
+        } => {
+            reliable_server.remove_connection(ClientId::from_raw(client_id));
+            if let Some(payload) = payload {
+                send_packet(payload, addr);
+            }
+        }
+    }
+}
+
+/// Finds the address of this machine on the local network.
+/// No packet is sent: connecting a UDP socket only selects the outgoing interface.
+pub fn local_lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0)).ok()?;
+    socket
+        .connect(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(10, 255, 255, 255)),
+            1,
+        ))
+        .ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    if ip.is_unspecified() {
+        None
+    } else {
+        Some(ip)
+    }
+}
+
+pub struct ServerTransportPlugin;
+
+impl Plugin for ServerTransportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NetcodeTransportError>();
+
+        app.add_systems(
+            PreUpdate,
+            update_system
+                .in_set(RenetReceive)
+                .run_if(resource_exists::<ServerTransport>)
+                .run_if(resource_exists::<RenetServer>)
+                .after(RenetServerPlugin::update_system)
+                .before(RenetServerPlugin::emit_server_events_system),
+        );
+
+        app.add_systems(
+            PostUpdate,
+            (
+                send_packets.in_set(RenetSend),
+                // Lets messages sent on exit go through first
+                disconnect_on_exit.after(RenetSend),
+            )
+                .run_if(resource_exists::<ServerTransport>)
+                .run_if(resource_exists::<RenetServer>),
+        );
+    }
+}
+
+fn update_system(
+    mut transport: ResMut<ServerTransport>,
+    mut server: ResMut<RenetServer>,
+    time: Res<Time>,
+    mut transport_errors: EventWriter<NetcodeTransportError>,
+) {
+    if let Err(e) = transport.update(time.delta(), &mut server) {
+        transport_errors.send(e);
+    }
+}
+
+fn send_packets(mut transport: ResMut<ServerTransport>, mut server: ResMut<RenetServer>) {
+    transport.send_packets(&mut server);
+}
+
+fn disconnect_on_exit(
+    exit: EventReader<AppExit>,
+    mut transport: ResMut<ServerTransport>,
+    mut server: ResMut<RenetServer>,
+) {
+    if !exit.is_empty() {
+        transport.disconnect_all(&mut server);
+    }
+}
@@ -4,7 +4,7 @@ use bevy_ecs::prelude::Res;
 use shared::messages::PlayerInputs;
 
 pub fn handle_player_inputs(player_inputs: PlayerInputs, ticker: &Res<TickCounter>) {
-    if ticker.tick % 60 == 0 {
+    if ticker.tick.is_multiple_of(60) {
         trace!("Received inputs: {:?}", player_inputs);
     }
 }
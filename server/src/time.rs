@@ -3,7 +3,7 @@ use crate::init::TickCounter;
 use bevy::prelude::*;
 
 pub fn update_server_time(mut time: ResMut<ServerTime>, tick_counter: Res<TickCounter>) {
-    if tick_counter.tick % 60 == 0 {
+    if tick_counter.tick.is_multiple_of(60) {
         time.0 += 1;
         debug!("Server time updated: {}", time.0);
     }
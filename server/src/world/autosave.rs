@@ -0,0 +1,73 @@
+use crate::world::backup::BACKUP_INTERVAL;
+use crate::world::save::SaveRequestEvent;
+use bevy::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Resource)]
+struct AutosaveTimers {
+    save: Timer,
+    backup: Timer,
+}
+
+/// Set by SIGINT or SIGTERM, the server then stops as if it was asked to
+#[derive(Resource)]
+struct ShutdownSignal(Arc<AtomicBool>);
+
+pub fn setup_autosave(app: &mut App) {
+    app.insert_resource(AutosaveTimers {
+        save: Timer::new(AUTOSAVE_INTERVAL, TimerMode::Repeating),
+        backup: Timer::new(BACKUP_INTERVAL, TimerMode::Once),
+    });
+    app.add_systems(Update, autosave_system);
+}
+
+/// Stops the server gracefully on SIGINT or SIGTERM, so that the world is saved.
+/// A second signal terminates it right away.
+pub fn setup_shutdown_signals(app: &mut App) {
+    let flag = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        let registered = signal_hook::flag::register_conditional_shutdown(signal, 1, flag.clone())
+            .and_then(|_| signal_hook::flag::register(signal, flag.clone()));
+        if let Err(e) = registered {
+            error!("Failed to handle signal {}: {}", signal, e);
+        }
+    }
+
+    app.insert_resource(ShutdownSignal(flag));
+    app.add_systems(Update, shutdown_signal_system);
+}
+
+fn autosave_system(
+    mut timers: ResMut<AutosaveTimers>,
+    time: Res<Time>,
+    mut ev_save: EventWriter<SaveRequestEvent>,
+) {
+    timers.save.tick(time.delta());
+    timers.backup.tick(time.delta());
+    if !timers.save.finished() {
+        return;
+    }
+
+    // Backups are made by the first autosave once they are due, so they hold everything up to it
+    let backup = timers.backup.finished();
+    if backup {
+        timers.backup.reset();
+    }
+
+    debug!("Autosave");
+    ev_save.send(SaveRequestEvent {
+        requester: None,
+        backup,
+    });
+}
+
+fn shutdown_signal_system(signal: Res<ShutdownSignal>, mut ev_exit: EventWriter<AppExit>) {
+    if signal.0.load(Ordering::Relaxed) {
+        info!("Shutdown signal received, stopping the server");
+        ev_exit.send(AppExit::Success);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::init::{ServerLobby, ServerTime};
+use crate::network::disconnect::{disconnect_with_reason, PendingDisconnections};
+use crate::world::generation_queue::GenerationQueue;
+use crate::world::pregen::Pregeneration;
+use crate::world::save::WorldSaver;
+use crate::world::storage::{WorldStorage, LEVEL_FILE, REGION_DIR};
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, RenetServer};
+use shared::messages::DisconnectReason;
+use shared::world::{
+    unix_timestamp, DateTime, ServerWorldMap, WorldMetadata, WorldSeed, METADATA_FILE,
+};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+pub const BACKUP_DIR: &str = "backups";
+/// Where a backup is copied before replacing the world, so that a failed copy leaves it intact
+const RESTORE_DIR: &str = "restore.tmp";
+/// Where the replaced region files are moved while the restored ones take their place
+const REPLACED_REGION_DIR: &str = "region.old";
+/// Older backups are deleted once a world has more than this
+pub const MAX_BACKUPS: usize = 5;
+/// Time between two backups made by autosaves
+pub const BACKUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Formats a unix timestamp as `YYYY-MM-DD_HH-MM-SS` (UTC), which also sorts chronologically
+fn format_timestamp(secs: u64) -> String {
+    let date = DateTime::from_unix(secs);
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        date.year, date.month, date.day, date.hour, date.minute, date.second
+    )
+}
+
+/// Copies a folder of a world recursively, without the files a save is still writing
+pub fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        // Region files being written are not part of the world yet
+        if entry.path().extension().is_some_and(|ext| ext == "tmp") {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the level file, the metadata and the region files of a world folder
+fn copy_world(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    fs::copy(from.join(LEVEL_FILE), to.join(LEVEL_FILE))?;
+    if from.join(METADATA_FILE).is_file() {
+        fs::copy(from.join(METADATA_FILE), to.join(METADATA_FILE))?;
+    }
+    if from.join(REGION_DIR).exists() {
+        copy_dir(&from.join(REGION_DIR), &to.join(REGION_DIR))?;
+    }
+    Ok(())
+}
+
+/// Names of the backups of a world, oldest first
+pub fn list_backups(storage: &WorldStorage) -> io::Result<Vec<String>> {
+    let backup_dir = storage.world_dir.join(BACKUP_DIR);
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backup_dir)? {
+        let entry = entry?;
+        if entry.path().join(LEVEL_FILE).is_file() {
+            backups.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Whether the last backup of a world is older than `BACKUP_INTERVAL`, or it has none
+pub fn backup_due(storage: &WorldStorage) -> io::Result<bool> {
+    let Some(latest) = list_backups(storage)?.pop() else {
+        return Ok(true);
+    };
+    let created = fs::metadata(storage.world_dir.join(BACKUP_DIR).join(latest))?.modified()?;
+    Ok(SystemTime::now()
+        .duration_since(created)
+        .is_ok_and(|age| age >= BACKUP_INTERVAL))
+}
+
+/// Copies the saved world into a new timestamped backup, then deletes the oldest ones.
+/// Returns the name of the new backup.
+pub fn create_backup(storage: &WorldStorage) -> io::Result<String> {
+    let name = format_timestamp(unix_timestamp());
+    let backup_path = storage.world_dir.join(BACKUP_DIR).join(&name);
+    copy_world(&storage.world_dir, &backup_path)?;
+
+    let backups = list_backups(storage)?;
+    for old in &backups[..backups.len().saturating_sub(MAX_BACKUPS)] {
+        fs::remove_dir_all(storage.world_dir.join(BACKUP_DIR).join(old))?;
+    }
+
+    Ok(name)
+}
+
+/// Replaces the saved world with one of its backups. The backup is copied next to the world
+/// first, then the current world is backed up and swapped with the copy, so that it is never
+/// left half restored. Returns the name of the backup of the replaced world.
+pub fn restore_backup(storage: &WorldStorage, name: &str) -> io::Result<String> {
+    let backup_path = storage.world_dir.join(BACKUP_DIR).join(name);
+    if name.contains(['/', '\\']) || !backup_path.join(LEVEL_FILE).is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no backup named {}", name),
+        ));
+    }
+
+    let restore_dir = storage.world_dir.join(RESTORE_DIR);
+    if restore_dir.exists() {
+        fs::remove_dir_all(&restore_dir)?;
+    }
+    copy_world(&backup_path, &restore_dir)?;
+
+    // Copied before, the backup may be the oldest one and rotated out by this one
+    let replaced = create_backup(storage)?;
+
+    let region_dir = storage.world_dir.join(REGION_DIR);
+    let replaced_region_dir = storage.world_dir.join(REPLACED_REGION_DIR);
+    if replaced_region_dir.exists() {
+        fs::remove_dir_all(&replaced_region_dir)?;
+    }
+    if region_dir.exists() {
+        fs::rename(&region_dir, &replaced_region_dir)?;
+    }
+    if restore_dir.join(REGION_DIR).exists() {
+        fs::rename(restore_dir.join(REGION_DIR), &region_dir)?;
+    }
+    if restore_dir.join(METADATA_FILE).is_file() {
+        fs::rename(restore_dir.join(METADATA_FILE), storage.metadata_path())?;
+    }
+    fs::rename(restore_dir.join(LEVEL_FILE), storage.level_path())?;
+
+    fs::remove_dir_all(&restore_dir)?;
+    if replaced_region_dir.exists() {
+        fs::remove_dir_all(&replaced_region_dir)?;
+    }
+    Ok(replaced)
+}
+
+/// Backup a command asked to restore, applied once no save is running
+#[derive(Resource, Debug, Default)]
+pub struct PendingRestore(pub Option<String>);
+
+/// Restores a backup while the server runs. Players are disconnected since the chunks
+/// they hold no longer match the world, and unsaved changes are dropped.
+pub fn restore_backup_system(
+    mut pending_restore: ResMut<PendingRestore>,
+    saver: Res<WorldSaver>,
+    storage: Res<WorldStorage>,
+    (mut world_map, mut seed, mut time, mut metadata, mut queue, mut pregen): (
+        ResMut<ServerWorldMap>,
+        ResMut<WorldSeed>,
+        ResMut<ServerTime>,
+        ResMut<WorldMetadata>,
+        ResMut<GenerationQueue>,
+        ResMut<Pregeneration>,
+    ),
+    (mut server, mut pending, lobby): (
+        ResMut<RenetServer>,
+        ResMut<PendingDisconnections>,
+        Res<ServerLobby>,
+    ),
+) {
+    if pending_restore.0.is_none() || saver.is_saving() {
+        return;
+    }
+    let name = pending_restore.0.take().unwrap();
+
+    match restore_backup(&storage, &name) {
+        Ok(replaced) => info!("Backed up the replaced world as {}", replaced),
+        Err(e) => {
+            error!("Failed to restore backup {}: {}", name, e);
+            return;
+        }
+    }
+    let level = match storage.load_level() {
+        Ok(level) => level,
+        Err(e) => {
+            error!("Failed to load restored backup {}: {}", name, e);
+            return;
+        }
+    };
+
+    // Backups made before metadata were backed up leave the current ones in place
+    if storage.metadata_path().is_file() {
+        match storage.load_metadata() {
+            Ok(restored) => *metadata = restored,
+            Err(e) => error!("Failed to load the metadata of backup {}: {}", name, e),
+        }
+    }
+
+    world_map.map.clear();
+    world_map.dirty_chunks.clear();
+    world_map.empty_chunks.clear();
+    world_map.chunks_to_update.clear();
+    queue.clear();
+    pregen.restart_batch();
+    world_map.time = level.time;
+    *seed = level.seed;
+    time.0 = level.time;
+
+    for id in lobby.players.keys() {
+        disconnect_with_reason(
+            &mut server,
+            &mut pending,
+            ClientId::from_raw(*id),
+            DisconnectReason::Kicked {
+                message: Some("The world was restored from a backup".into()),
+            },
+        );
+    }
+
+    info!("Restored backup {}", name);
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use noise::{NoiseFn, Perlin};
+use shared::world::WorldHeight;
+
+/// Caves never go lower than this above the bottom of the world, so that they do not open onto
+/// the bedrock
+const CAVE_FLOOR: i32 = 5;
+/// Depth under the surface where tunnels only open at the rare cave entrances
+const SURFACE_CRUST: i32 = 8;
+/// Depth under the surface where caverns start
+const CAVERN_DEPTH: i32 = 16;
+
+/// Noises carving the caves of the default generator.
+/// Tunnels follow the intersection of two noise surfaces, which draws long winding worms,
+/// while caverns are the places where a third noise is high.
+pub struct CaveNoise {
+    tunnel_perlin: Perlin,
+    tunnel_offset_perlin: Perlin,
+    cavern_perlin: Perlin,
+    entrance_perlin: Perlin,
+}
+
+impl CaveNoise {
+    const TUNNEL_SCALE: f64 = 0.03;
+    const TUNNEL_WIDTH: f64 = 0.06;
+    const CAVERN_SCALE: f64 = 0.02;
+    const CAVERN_THRESHOLD: f64 = 0.6;
+    const ENTRANCE_SCALE: f64 = 0.05;
+    const ENTRANCE_THRESHOLD: f64 = 0.6;
+
+    pub fn new(seed: u32) -> Self {
+        CaveNoise {
+            tunnel_perlin: Perlin::new(seed.wrapping_add(3)),
+            tunnel_offset_perlin: Perlin::new(seed.wrapping_add(4)),
+            cavern_perlin: Perlin::new(seed.wrapping_add(5)),
+            entrance_perlin: Perlin::new(seed.wrapping_add(6)),
+        }
+    }
+
+    /// Whether the block at `pos` is carved out, in a column whose surface is at `surface`
+    /// and whose biome has the given `cave_density`
+    pub fn is_cave(
+        &self,
+        pos: IVec3,
+        surface: i32,
+        cave_density: f64,
+        height: WorldHeight,
+    ) -> bool {
+        if pos.y < height.min + CAVE_FLOOR || pos.y > surface || cave_density <= 0. {
+            return false;
+        }
+        let depth = surface - pos.y;
+
+        // the crust is only crossed where there is a cave entrance
+        if depth < SURFACE_CRUST {
+            let entrance = self.entrance_perlin.get([
+                pos.x as f64 * Self::ENTRANCE_SCALE,
+                pos.z as f64 * Self::ENTRANCE_SCALE,
+            ]);
+            if entrance < Self::ENTRANCE_THRESHOLD {
+                return false;
+            }
+        }
+
+        let tunnel_point = [
+            pos.x as f64 * Self::TUNNEL_SCALE,
+            pos.y as f64 * Self::TUNNEL_SCALE * 2.,
+            pos.z as f64 * Self::TUNNEL_SCALE,
+        ];
+        let tunnel_width = Self::TUNNEL_WIDTH * cave_density;
+        if self.tunnel_perlin.get(tunnel_point).abs() < tunnel_width
+            && self.tunnel_offset_perlin.get(tunnel_point).abs() < tunnel_width
+        {
+            return true;
+        }
+
+        depth >= CAVERN_DEPTH
+            && self.cavern_perlin.get([
+                pos.x as f64 * Self::CAVERN_SCALE,
+                pos.y as f64 * Self::CAVERN_SCALE * 1.5,
+                pos.z as f64 * Self::CAVERN_SCALE,
+            ]) > Self::CAVERN_THRESHOLD / cave_density
+    }
+}
@@ -0,0 +1,200 @@
+//! Second phase of the default generator: plants and trees are placed once the terrain exists.
+//! The features growing on a column are planned from the world seed and the column position
+//! only, so a chunk can place the parts of the trees of its neighbours which overhang it. Chunks
+//! are then the same whatever order they are generated in, and trees are not cut at borders.
+
+use crate::world::generation::{seeded_rng, TerrainNoise};
+use bevy::prelude::*;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use shared::{world::*, CHUNK_SIZE};
+use std::collections::HashMap;
+
+/// Farthest a feature reaches horizontally from the column it grows on
+const FEATURE_RADIUS: i32 = 2;
+/// Farthest a feature reaches above the surface of the column it grows on
+const FEATURE_HEIGHT: i32 = 9;
+
+/// Radius of the layers of leaves of a spruce, from its top down
+const SPRUCE_LAYERS: [i32; 6] = [1, 1, 2, 1, 2, 2];
+
+fn tree_blocks(
+    blocks: &mut Vec<(IVec3, BlockId)>,
+    rng: &mut ChaCha8Rng,
+    base: IVec3,
+    trunk: BlockId,
+    leaves: BlockId,
+) {
+    // create trunk
+    let trunk_height = rng.gen_range(3..=5);
+    for dy in 0..trunk_height {
+        blocks.push((base.with_y(base.y + dy), trunk));
+    }
+
+    // place the leaves
+    let leaf_start_y = base.y + trunk_height - 1;
+    for offset_x in -1..=1 {
+        for offset_z in -1..=1 {
+            if (offset_x != 0 || offset_z != 0) && (offset_x == 0 || offset_z == 0) {
+                blocks.push((
+                    IVec3::new(base.x + offset_x, leaf_start_y, base.z + offset_z),
+                    leaves,
+                ));
+            }
+        }
+    }
+    // add one leaf block at the top of the trunk
+    blocks.push((base.with_y(leaf_start_y + 1), leaves));
+}
+
+/// A conical spruce: layers of leaves around the trunk, wider and wider towards the bottom,
+/// above a bare part of the trunk
+fn spruce_blocks(
+    blocks: &mut Vec<(IVec3, BlockId)>,
+    rng: &mut ChaCha8Rng,
+    base: IVec3,
+    trunk: BlockId,
+    leaves: BlockId,
+) {
+    let trunk_height = rng.gen_range(6..=8);
+    for dy in 0..trunk_height {
+        blocks.push((base.with_y(base.y + dy), trunk));
+    }
+
+    let top_y = base.y + trunk_height - 1;
+    blocks.push((base.with_y(top_y + 1), leaves));
+    for (layer, y) in (base.y + 2..=top_y).rev().enumerate() {
+        let radius = SPRUCE_LAYERS[layer];
+        for offset_x in -radius..=radius {
+            for offset_z in -radius..=radius {
+                // round the corners of the wide layers
+                let rounded = offset_x.abs() + offset_z.abs() <= radius.max(radius * 2 - 1);
+                if (offset_x != 0 || offset_z != 0) && rounded {
+                    blocks.push((IVec3::new(base.x + offset_x, y, base.z + offset_z), leaves));
+                }
+            }
+        }
+    }
+}
+
+fn cactus_blocks(
+    blocks: &mut Vec<(IVec3, BlockId)>,
+    rng: &mut ChaCha8Rng,
+    base: IVec3,
+    cactus: BlockId,
+) {
+    let cactus_height = rng.gen_range(2..=3);
+    for dy in 0..cactus_height {
+        blocks.push((base.with_y(base.y + dy), cactus));
+    }
+}
+
+/// Blocks of the features growing on the column at `(x, z)`, in world coordinates
+fn column_features(
+    seed: u32,
+    x: i32,
+    z: i32,
+    surface: i32,
+    flora: &Flora,
+) -> Vec<(IVec3, BlockId)> {
+    let mut rng = seeded_rng(seed, &[x, z]);
+    let mut blocks = Vec::new();
+    let above_surface_pos = IVec3::new(x, surface + 1, z);
+
+    // Add flowers
+    let flower_chance = rng.gen::<f32>();
+    if flower_chance < flora.flower_chance && !flora.flowers.is_empty() {
+        let index = (rng.gen::<f32>() * flora.flowers.len() as f32) as usize;
+        blocks.push((above_surface_pos, flora.flowers[index]));
+    }
+
+    // Add tall grass
+    let tall_grass_chance = rng.gen::<f32>();
+    if tall_grass_chance < flora.tall_grass_chance && blocks.is_empty() {
+        blocks.push((above_surface_pos, BlockId::TallGrass));
+    }
+
+    // Plants already use the block above the surface
+    if !blocks.is_empty() {
+        return blocks;
+    }
+
+    // Add trees
+    let tree_chance = rng.gen::<f32>();
+    if let Some(tree) = flora.tree.filter(|_| tree_chance < flora.tree_chance) {
+        match tree.shape {
+            TreeShape::Round => tree_blocks(
+                &mut blocks,
+                &mut rng,
+                above_surface_pos,
+                tree.trunk,
+                tree.leaves,
+            ),
+            TreeShape::Conical => spruce_blocks(
+                &mut blocks,
+                &mut rng,
+                above_surface_pos,
+                tree.trunk,
+                tree.leaves,
+            ),
+        }
+    }
+
+    // Add cactus
+    let cactus_chance = rng.gen::<f32>();
+    if cactus_chance < flora.cactus_chance {
+        cactus_blocks(&mut blocks, &mut rng, above_surface_pos, BlockId::Cactus);
+    }
+
+    blocks
+}
+
+/// Places the blocks of every feature reaching the chunk, including those growing on the
+/// columns of neighbouring chunks. Features never replace terrain, nor each other.
+pub fn decorate(
+    blocks: &mut HashMap<IVec3, BlockData>,
+    chunk_pos: IVec3,
+    seed: u32,
+    height: WorldHeight,
+    noise: &TerrainNoise,
+) {
+    let origin = chunk_pos * CHUNK_SIZE;
+
+    for x in origin.x - FEATURE_RADIUS..origin.x + CHUNK_SIZE + FEATURE_RADIUS {
+        for z in origin.z - FEATURE_RADIUS..origin.z + CHUNK_SIZE + FEATURE_RADIUS {
+            let surface = noise.height(x, z);
+            if surface + FEATURE_HEIGHT < origin.y || surface + 1 >= origin.y + CHUNK_SIZE {
+                continue;
+            }
+            // nothing grows under water, nor on the sand of its shores
+            if surface <= noise.preset.sea_level {
+                continue;
+            }
+
+            // nothing grows over a cave entrance
+            let biome = noise.biome(x, z);
+            if noise.caves.is_cave(
+                IVec3::new(x, surface, z),
+                surface,
+                biome.cave_density,
+                height,
+            ) {
+                continue;
+            }
+
+            for (pos, block) in column_features(seed, x, z, surface, &biome.flora) {
+                let local_pos = pos - origin;
+                if local_pos.cmplt(IVec3::ZERO).any()
+                    || local_pos.cmpge(IVec3::splat(CHUNK_SIZE)).any()
+                {
+                    continue;
+                }
+                blocks.entry(local_pos).or_insert(BlockData::new(
+                    block,
+                    false,
+                    BlockDirection::Front,
+                ));
+            }
+        }
+    }
+}
@@ -1,317 +1,235 @@
+use crate::world::caves::CaveNoise;
+use crate::world::features::decorate;
+use crate::world::generator::WorldGenerator;
+use crate::world::ores::place_ores;
+use crate::world::preset::TerrainPreset;
 use bevy::prelude::*;
 use noise::{NoiseFn, Perlin};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use shared::{world::*, CHUNK_SIZE};
 use std::collections::HashMap;
 
-fn generate_tree(chunk: &mut ServerChunk, x: i32, y: i32, z: i32, trunk: BlockId, leaves: BlockId) {
-    // create trunk
-    let trunk_height = 3 + rand::random::<u8>() % 3; // random height between 3 and 5
-    for dy in 0..trunk_height {
-        chunk.map.insert(
-            IVec3::new(x, y + dy as i32, z),
-            BlockData::new(trunk, false, BlockDirection::Front),
-        );
-    }
+/// Version of `NoiseGenerator`, bumped whenever the terrain it generates for a given seed changes
+pub const GENERATOR_VERSION: u32 = 7;
 
-    // place the leaves
-    let leaf_start_y = y + trunk_height as i32 - 1;
-    for offset_x in -1..=1 {
-        for offset_z in -1..=1 {
-            if (offset_x != 0 || offset_z != 0) && (offset_x == 0 || offset_z == 0) {
-                chunk.map.insert(
-                    IVec3::new(x + offset_x, leaf_start_y, z + offset_z),
-                    BlockData::new(leaves, false, BlockDirection::Front),
-                );
-            }
-        }
+/// Height up to which low terrain is filled with water, unless the preset says otherwise
+pub const SEA_LEVEL: i32 = 62;
+
+/// Random numbers for something placed at the given coordinates, the same for a given seed.
+/// ChaCha8 is used rather than `StdRng`, whose algorithm may change between `rand` versions.
+pub fn seeded_rng(seed: u32, coords: &[i32]) -> ChaCha8Rng {
+    // mix the coordinates so that neighbouring positions get unrelated streams
+    let mut state = u64::from(seed);
+    for coord in coords {
+        state = (state ^ u64::from(*coord as u32)).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        state ^= state >> 31;
     }
-    // add one leaf block at the top of the trunk
-    chunk.map.insert(
-        IVec3::new(x, leaf_start_y + 1, z),
-        BlockData::new(leaves, false, BlockDirection::Front),
-    );
+    ChaCha8Rng::seed_from_u64(state)
 }
 
-fn generate_cactus(chunk: &mut ServerChunk, x: i32, y: i32, z: i32, cactus: BlockId) {
-    let cactus_height = 2 + rand::random::<u8>() % 2;
-    for dy in 0..cactus_height {
-        chunk.map.insert(
-            IVec3::new(x, y + dy as i32, z),
-            BlockData::new(cactus, false, BlockDirection::Front),
-        );
-    }
+/// Noises shaping the terrain of the default generator, with the parameters of its preset
+pub struct TerrainNoise<'a> {
+    pub preset: &'a TerrainPreset,
+    perlin: Perlin,
+    temp_perlin: Perlin,
+    humidity_perlin: Perlin,
+    continent_perlin: Perlin,
+    river_perlin: Perlin,
+    pub caves: CaveNoise,
 }
 
-pub fn determine_biome(temperature: f64, humidity: f64) -> BiomeType {
-    if temperature > 0.6 {
-        if humidity > 0.5 {
-            BiomeType::Forest
-        } else {
-            BiomeType::Desert
+impl<'a> TerrainNoise<'a> {
+    pub fn new(seed: u32, preset: &'a TerrainPreset) -> Self {
+        TerrainNoise {
+            preset,
+            perlin: Perlin::new(seed),
+            temp_perlin: Perlin::new(seed.wrapping_add(1)),
+            humidity_perlin: Perlin::new(seed.wrapping_add(2)),
+            continent_perlin: Perlin::new(seed.wrapping_add(7)),
+            river_perlin: Perlin::new(seed.wrapping_add(8)),
+            caves: CaveNoise::new(seed),
         }
-    } else if temperature > 0.3 {
-        if humidity > 0.7 {
-            BiomeType::FlowerPlains
-        } else if humidity > 0.5 {
-            BiomeType::Plains
-        } else {
-            BiomeType::MediumMountain
-        }
-    } else if temperature >= 0.0 {
-        if humidity > 0.5 {
-            BiomeType::IcePlain
-        } else {
-            BiomeType::HighMountainGrass
-        }
-    } else {
-        panic!();
     }
-}
 
-fn interpolated_height(
-    x: i32,
-    z: i32,
-    biome_scale: f64,
-    perlin: &Perlin,
-    temp_perlin: &Perlin,
-    humidity_perlin: &Perlin,
-    scale: f64,
-) -> i32 {
-    // get the properties of the main biome at (x, z)
-    let temperature =
-        (temp_perlin.get([x as f64 * biome_scale, z as f64 * biome_scale]) + 1.0) / 2.0;
-    let humidity =
-        (humidity_perlin.get([x as f64 * biome_scale, z as f64 * biome_scale]) + 1.0) / 2.0;
-    let biome_type = determine_biome(temperature, humidity);
-    let biome = get_biome_data(biome_type);
+    /// Biome of the column at `(x, z)`, from its temperature, humidity, distance to the sea
+    /// and to rivers
+    pub fn biome(&self, x: i32, z: i32) -> &'a Biome {
+        let biome_scale = self.preset.biome_scale;
+        let biome_point = [x as f64 * biome_scale, z as f64 * biome_scale];
+        let temperature = (self.temp_perlin.get(biome_point) + 1.0) / 2.0;
+        let humidity = (self.humidity_perlin.get(biome_point) + 1.0) / 2.0;
+        let continent_scale = self.preset.continent_scale;
+        let continentalness = (self
+            .continent_perlin
+            .get([x as f64 * continent_scale, z as f64 * continent_scale])
+            + 1.0)
+            / 2.0;
+        let river_scale = self.preset.river_scale;
+        let river = self
+            .river_perlin
+            .get([x as f64 * river_scale, z as f64 * river_scale])
+            .abs();
+        self.preset
+            .biome_at(temperature, humidity, continentalness, river)
+    }
 
-    // initialize weighted values
-    let mut weighted_base_height = biome.base_height as f64;
-    let mut weighted_variation = biome.height_variation as f64;
-    let mut total_weight = 1.0;
+    /// Height of the surface block of the column at `(x, z)`, blending the neighbouring biomes
+    pub fn height(&self, x: i32, z: i32) -> i32 {
+        // get the properties of the main biome at (x, z)
+        let biome = self.biome(x, z);
+
+        // initialize weighted values
+        let mut weighted_base_height = biome.base_height as f64;
+        let mut weighted_variation = biome.height_variation as f64;
+        let mut total_weight = 1.0;
+
+        // loop through neighboring blocks to get influences
+        for &offset_x in &[-4, 0, 4] {
+            for &offset_z in &[-4, 0, 4] {
+                if offset_x == 0 && offset_z == 0 {
+                    continue; // ignore the central position
+                }
 
-    // loop through neighboring blocks to get influences
-    for &offset_x in &[-4, 0, 4] {
-        for &offset_z in &[-4, 0, 4] {
-            if offset_x == 0 && offset_z == 0 {
-                continue; // ignore the central position
-            }
+                let neighbor_x = x + offset_x;
+                let neighbor_z = z + offset_z;
 
-            let neighbor_x = x + offset_x;
-            let neighbor_z = z + offset_z;
+                // determine the biome of the neighboring block
+                let neighbor_biome = self.biome(neighbor_x, neighbor_z);
 
-            // calculate the temperature and humidity of the neighboring block
-            let neighbor_temp = (temp_perlin.get([
-                neighbor_x as f64 * biome_scale,
-                neighbor_z as f64 * biome_scale,
-            ]) + 1.0)
-                / 2.0;
-            let neighbor_humidity = (humidity_perlin.get([
-                neighbor_x as f64 * biome_scale,
-                neighbor_z as f64 * biome_scale,
-            ]) + 1.0)
-                / 2.0;
+                // weight by distance (the farther a neighbor is, the less influence it has)
+                let distance = ((offset_x.pow(2) + offset_z.pow(2)) as f64).sqrt();
+                let weight = 1.0 / (distance + 1.0); // distance +1 to avoid division by zero
 
-            // determine the biome of the neighboring block
-            let neighbor_biome_type = determine_biome(neighbor_temp, neighbor_humidity);
-            let neighbor_biome = get_biome_data(neighbor_biome_type);
+                // update weighted values
+                weighted_base_height += neighbor_biome.base_height as f64 * weight;
+                weighted_variation += neighbor_biome.height_variation as f64 * weight;
+                total_weight += weight;
+            }
+        }
 
-            // weight by distance (the farther a neighbor is, the less influence it has)
-            let distance = ((offset_x.pow(2) + offset_z.pow(2)) as f64).sqrt();
-            let weight = 1.0 / (distance + 1.0); // distance +1 to avoid division by zero
+        // normalize weighted values
+        weighted_base_height /= total_weight;
+        weighted_variation /= total_weight;
 
-            // update weighted values
-            weighted_base_height += neighbor_biome.base_height as f64 * weight;
-            weighted_variation += neighbor_biome.height_variation as f64 * weight;
-            total_weight += weight;
-        }
-    }
+        // final calculation of height with perlin noise
+        let terrain_scale = self.preset.terrain_scale;
+        let terrain_noise = self
+            .perlin
+            .get([x as f64 * terrain_scale, z as f64 * terrain_scale]);
+        let interpolated_height = weighted_base_height + (weighted_variation * terrain_noise);
 
-    // normalize weighted values
-    weighted_base_height /= total_weight;
-    weighted_variation /= total_weight;
+        interpolated_height.round() as i32
+    }
+}
 
-    // final calculation of height with perlin noise
-    let terrain_noise = perlin.get([x as f64 * scale, z as f64 * scale]);
-    let interpolated_height = weighted_base_height + (weighted_variation * terrain_noise);
+/// Terrain shaped by noises, with biomes, caves, ores, water and plants
+#[derive(Default)]
+pub struct NoiseGenerator {
+    pub preset: TerrainPreset,
+}
 
-    interpolated_height.round() as i32
+impl NoiseGenerator {
+    /// Options are a `TerrainPreset`
+    pub fn from_options(options: &str) -> Result<Self, String> {
+        Ok(Self {
+            preset: TerrainPreset::from_options(options)?,
+        })
+    }
 }
 
-pub fn generate_chunk(chunk_pos: IVec3, seed: u32) -> ServerChunk {
-    let perlin = Perlin::new(seed);
-    let temp_perlin = Perlin::new(seed + 1);
-    let humidity_perlin = Perlin::new(seed + 2);
+impl WorldGenerator for NoiseGenerator {
+    fn generate_chunk(
+        &self,
+        chunk_pos: IVec3,
+        seed: u32,
+        height: WorldHeight,
+    ) -> HashMap<IVec3, BlockData> {
+        generate_noise_chunk(chunk_pos, seed, height, &self.preset)
+    }
 
-    let scale = 0.1;
-    let biome_scale = 0.01;
-    let cx = chunk_pos.x;
-    let cy = chunk_pos.y;
-    let cz = chunk_pos.z;
+    fn version(&self) -> u32 {
+        GENERATOR_VERSION
+    }
+}
 
-    let mut chunk = ServerChunk {
-        map: HashMap::new(),
-        ts: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64,
-    };
+/// Generates the terrain of the chunk and carves its caves, then adds ores, plants and trees.
+/// Bedrock lines the bottom of the world.
+fn generate_noise_chunk(
+    chunk_pos: IVec3,
+    seed: u32,
+    height: WorldHeight,
+    preset: &TerrainPreset,
+) -> HashMap<IVec3, BlockData> {
+    let noise = TerrainNoise::new(seed, preset);
+    let mut blocks = HashMap::new();
 
     for dx in 0..CHUNK_SIZE {
         for dz in 0..CHUNK_SIZE {
-            let x = CHUNK_SIZE * cx + dx;
-            let z = CHUNK_SIZE * cz + dz;
-
-            // calculate temperature and humidity
-            let temperature =
-                (temp_perlin.get([x as f64 * biome_scale, z as f64 * biome_scale]) + 1.0) / 2.0;
-            let humidity =
-                (humidity_perlin.get([x as f64 * biome_scale, z as f64 * biome_scale]) + 1.0) / 2.0;
+            let x = CHUNK_SIZE * chunk_pos.x + dx;
+            let z = CHUNK_SIZE * chunk_pos.z + dz;
 
-            // get biome regarding the two values
-            let biome_type = determine_biome(temperature, humidity);
-            let biome = get_biome_data(biome_type);
+            let biome = noise.biome(x, z);
+            let terrain_height = noise.height(x, z);
 
-            // get terrain height
-            let terrain_height = interpolated_height(
-                x,
-                z,
-                biome_scale,
-                &perlin,
-                &temp_perlin,
-                &humidity_perlin,
-                scale,
-            );
+            // shores and the bottom of the water are sandy, and caves would open onto it
+            let submerged = terrain_height <= preset.sea_level;
+            let surface_block = if submerged {
+                BlockId::Sand
+            } else {
+                biome.surface_block
+            };
+            let cave_density = if submerged { 0. } else { biome.cave_density };
 
             // generate blocs
             for dy in 0..CHUNK_SIZE {
-                let y = CHUNK_SIZE * cy + dy;
+                let y = CHUNK_SIZE * chunk_pos.y + dy;
+                if y < height.min {
+                    continue;
+                }
+                // nothing is carved through the floor of the world
+                if y == height.min {
+                    blocks.insert(
+                        IVec3::new(dx, dy, dz),
+                        BlockData::new(BlockId::Bedrock, false, BlockDirection::Front),
+                    );
+                    continue;
+                }
 
                 if y > terrain_height {
-                    break;
+                    if y > preset.sea_level {
+                        break;
+                    }
+                    blocks.insert(
+                        IVec3::new(dx, dy, dz),
+                        BlockData::new(BlockId::Water, false, BlockDirection::Front),
+                    );
+                    continue;
+                }
+                if noise
+                    .caves
+                    .is_cave(IVec3::new(x, y, z), terrain_height, cave_density, height)
+                {
+                    continue;
                 }
 
-                let block = if y == 0 {
-                    BlockId::Bedrock
-                } else if y < terrain_height - 4 {
+                let block = if y < terrain_height - 4 {
                     BlockId::Stone
                 } else if y < terrain_height {
                     biome.sub_surface_block
-                } else if y == terrain_height {
-                    biome.surface_block
                 } else {
-                    panic!();
+                    surface_block
                 };
 
-                let block_pos = IVec3::new(dx, dy, dz);
-
-                chunk.map.insert(
-                    block_pos,
+                blocks.insert(
+                    IVec3::new(dx, dy, dz),
                     BlockData::new(block, false, BlockDirection::Front),
                 );
-
-                // Add flora in biomes
-                if y == terrain_height && terrain_height >= 1 {
-                    let above_surface_pos = IVec3::new(dx, terrain_height + 1, dz);
-
-                    // Add flowers
-                    let flower_chance = rand::random::<f32>();
-                    match biome_type {
-                        BiomeType::FlowerPlains => {
-                            // High probability for flowers in Flower Plains
-                            if flower_chance < 0.1 {
-                                let flower_type = if rand::random::<f32>() < 0.5 {
-                                    BlockId::Dandelion
-                                } else {
-                                    BlockId::Poppy
-                                };
-
-                                chunk.map.insert(
-                                    block_pos.with_y(block_pos.y + 1),
-                                    BlockData::new(flower_type, false, BlockDirection::Front),
-                                );
-                            }
-                        }
-                        BiomeType::Plains | BiomeType::Forest | BiomeType::MediumMountain => {
-                            // Low probability for flowers in Plains, Forest, Medium Mountain
-                            if flower_chance < 0.02 {
-                                let flower_type = if rand::random::<f32>() < 0.5 {
-                                    BlockId::Dandelion
-                                } else {
-                                    BlockId::Poppy
-                                };
-
-                                chunk.map.insert(
-                                    block_pos.with_y(block_pos.y + 1),
-                                    BlockData::new(flower_type, false, BlockDirection::Front),
-                                );
-                            }
-                        }
-                        _ => {}
-                    }
-
-                    // Add tall grass
-                    if biome_type != BiomeType::HighMountainGrass
-                        && biome_type != BiomeType::Desert
-                        && biome_type != BiomeType::IcePlain
-                    {
-                        let tall_grass_chance = rand::random::<f32>();
-                        if tall_grass_chance < 0.10 {
-                            chunk.map.insert(
-                                block_pos.with_y(block_pos.y + 1),
-                                BlockData::new(BlockId::TallGrass, false, BlockDirection::Front),
-                            );
-                        }
-                    }
-
-                    // Add trees
-                    let tree_chance = rand::random::<f32>();
-                    match biome_type {
-                        BiomeType::Forest => {
-                            // High probability for trees in Forest
-                            if tree_chance < 0.06 {
-                                if !chunk.map.contains_key(&above_surface_pos) {
-                                    generate_tree(
-                                        &mut chunk,
-                                        dx,
-                                        dy + 1,
-                                        dz,
-                                        BlockId::OakLog,
-                                        BlockId::OakLeaves,
-                                    );
-                                }
-                            }
-                        }
-                        BiomeType::FlowerPlains | BiomeType::MediumMountain => {
-                            // Medium probability for trees in Flower Plains and Medium Mountain
-                            if tree_chance < 0.02 {
-                                if !chunk.map.contains_key(&above_surface_pos) {
-                                    generate_tree(
-                                        &mut chunk,
-                                        dx,
-                                        dy + 1,
-                                        dz,
-                                        BlockId::OakLog,
-                                        BlockId::OakLeaves,
-                                    );
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-
-                    // Add cactus in Desert
-                    if biome_type == BiomeType::Desert {
-                        let cactus_chance = rand::random::<f32>();
-                        if cactus_chance < 0.01 {
-                            if !chunk.map.contains_key(&above_surface_pos) {
-                                generate_cactus(&mut chunk, dx, dy + 1, dz, BlockId::Cactus);
-                            }
-                        }
-                    }
-                }
             }
         }
     }
-    chunk
+
+    place_ores(&mut blocks, chunk_pos, seed, height);
+    decorate(&mut blocks, chunk_pos, seed, height, &noise);
+    blocks
 }
@@ -0,0 +1,427 @@
+//! Chunks which were never saved are generated on the async compute task pool rather than in the
+//! game loop, so that players joining or moving fast do not stall the server. Each chunk is
+//! generated once however many players asked for it, and sent to them as soon as it is ready.
+
+use crate::init::TickCounter;
+use crate::world::generator::{generate_chunk, ActiveGenerator};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
+use bincode::Options;
+use shared::messages::{ServerToClientMessage, WorldUpdate};
+use shared::world::{
+    chunk_in_radius, ServerChunk, ServerWorldMap, WorldHeight, WorldMetadata, WorldSeed,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Chunks generated at the same time, more wait in the queue
+const MAX_GENERATING_CHUNKS: usize = 32;
+/// Chunks waiting for generation, further requests wait with the player who made them
+const MAX_QUEUED_CHUNKS: usize = 4096;
+
+pub const GENERATING_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("chunks/generating");
+pub const QUEUED_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("chunks/queued");
+
+/// Chunks a player sees, requests for chunks out of it are dropped when it moves away
+#[derive(Debug, Clone, Copy)]
+struct ClientView {
+    chunk_position: IVec3,
+    render_distance: i32,
+    vertical_render_distance: i32,
+}
+
+#[derive(Resource, Default)]
+pub struct GenerationQueue {
+    queued: VecDeque<IVec3>,
+    generating: HashMap<IVec3, Task<ServerChunk>>,
+    /// Players waiting for each queued or generating chunk
+    requesters: HashMap<IVec3, HashSet<ClientId>>,
+    /// Requests made while the queue was full, queued in turn for each player once it has room
+    overflow: HashMap<ClientId, VecDeque<IVec3>>,
+    views: HashMap<ClientId, ClientView>,
+}
+
+impl GenerationQueue {
+    /// Records the latest view of a player, sent with each of its requests
+    pub fn update_view(
+        &mut self,
+        client: ClientId,
+        chunk_position: IVec3,
+        render_distance: u32,
+        vertical_render_distance: u32,
+    ) {
+        self.views.insert(
+            client,
+            ClientView {
+                chunk_position,
+                render_distance: render_distance as i32,
+                vertical_render_distance: vertical_render_distance as i32,
+            },
+        );
+    }
+
+    /// Asks for a chunk to be generated and sent to `client`. While the queue is full, the
+    /// request waits with the other requests of the player until there is room.
+    pub fn request(&mut self, chunk_pos: IVec3, client: ClientId) {
+        if let Some(requesters) = self.requesters.get_mut(&chunk_pos) {
+            requesters.insert(client);
+            return;
+        }
+        if self.queued.len() >= MAX_QUEUED_CHUNKS {
+            let overflow = self.overflow.entry(client).or_default();
+            if !overflow.contains(&chunk_pos) {
+                overflow.push_back(chunk_pos);
+            }
+            return;
+        }
+        self.queued.push_back(chunk_pos);
+        self.requesters.insert(chunk_pos, HashSet::from([client]));
+    }
+
+    /// Stops generating a chunk, for instance because it was loaded some other way
+    pub fn cancel(&mut self, chunk_pos: IVec3) {
+        // dropping the task cancels it, a queued chunk is skipped once it has no requester
+        self.generating.remove(&chunk_pos);
+        self.requesters.remove(&chunk_pos);
+        for overflow in self.overflow.values_mut() {
+            overflow.retain(|pos| *pos != chunk_pos);
+        }
+    }
+
+    /// Whether no player waits for a chunk, so that background work can use the cores
+    pub fn is_idle(&self) -> bool {
+        self.queued.is_empty() && self.generating.is_empty() && self.overflow.is_empty()
+    }
+
+    /// Drops every request, their chunks no longer match the world
+    pub fn clear(&mut self) {
+        self.queued.clear();
+        self.generating.clear();
+        self.requesters.clear();
+        self.overflow.clear();
+    }
+
+    /// Forgets the requests and the view of players who left
+    fn retain_clients(&mut self, mut is_connected: impl FnMut(&ClientId) -> bool) {
+        self.views.retain(|client, _| is_connected(client));
+        self.overflow.retain(|client, _| is_connected(client));
+    }
+
+    /// Queues the requests which did not fit, one player after the other, dropping those out of
+    /// the view of their player
+    fn queue_overflow(&mut self) {
+        while self.queued.len() < MAX_QUEUED_CHUNKS && !self.overflow.is_empty() {
+            let clients: Vec<ClientId> = self.overflow.keys().copied().collect();
+            for client in clients {
+                let overflow = self.overflow.get_mut(&client).unwrap();
+                let next = overflow.pop_front();
+                if overflow.is_empty() {
+                    self.overflow.remove(&client);
+                }
+                match next {
+                    Some(chunk_pos) if self.is_in_view(&client, &chunk_pos) => {
+                        self.request(chunk_pos, client);
+                    }
+                    _ => {}
+                }
+                if self.queued.len() >= MAX_QUEUED_CHUNKS {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Whether a player still sees the chunk it asked for
+    fn is_in_view(&self, client: &ClientId, chunk_pos: &IVec3) -> bool {
+        self.views.get(client).is_some_and(|view| {
+            chunk_in_radius(
+                &view.chunk_position,
+                chunk_pos,
+                view.render_distance,
+                view.vertical_render_distance,
+            )
+        })
+    }
+
+    /// Moves the generated chunks to the world, and lists them for the players who asked for
+    /// them. Empty chunks are only remembered as such.
+    fn finish_tasks(&mut self, world_map: &mut ServerWorldMap) -> HashMap<ClientId, WorldUpdate> {
+        let mut finished = Vec::new();
+        self.generating
+            .retain(|chunk_pos, task| match block_on(future::poll_once(task)) {
+                Some(chunk) => {
+                    finished.push((*chunk_pos, chunk));
+                    false
+                }
+                None => true,
+            });
+
+        let mut updates: HashMap<ClientId, WorldUpdate> = HashMap::new();
+        for (chunk_pos, chunk) in finished {
+            let requesters = self.requesters.remove(&chunk_pos).unwrap_or_default();
+
+            if chunk.map.is_empty() {
+                world_map.empty_chunks.insert(chunk_pos);
+                for client in requesters {
+                    updates
+                        .entry(client)
+                        .or_default()
+                        .empty_chunks
+                        .push(chunk_pos);
+                }
+                continue;
+            }
+            let chunk = world_map.map.entry(chunk_pos).or_insert(chunk);
+            for client in requesters {
+                updates
+                    .entry(client)
+                    .or_default()
+                    .new_map
+                    .insert(chunk_pos, chunk.clone());
+            }
+        }
+        updates
+    }
+
+    /// Starts generating queued chunks while there is room
+    fn start_tasks(&mut self, generator: &ActiveGenerator, seed: &WorldSeed, height: WorldHeight) {
+        self.queue_overflow();
+        let pool = AsyncComputeTaskPool::get();
+        while self.generating.len() < MAX_GENERATING_CHUNKS {
+            let Some(chunk_pos) = self.queued.pop_front() else {
+                break;
+            };
+            // a chunk cancelled then requested again may be queued twice
+            if self.generating.contains_key(&chunk_pos) {
+                continue;
+            }
+
+            // players who moved away no longer need the chunk
+            let Some(mut requesters) = self.requesters.remove(&chunk_pos) else {
+                continue;
+            };
+            requesters.retain(|client| self.is_in_view(client, &chunk_pos));
+            if requesters.is_empty() {
+                continue;
+            }
+            self.requesters.insert(chunk_pos, requesters);
+
+            let generator = generator.0.clone();
+            let seed = seed.0;
+            let task =
+                pool.spawn(async move { generate_chunk(&*generator, chunk_pos, seed, height) });
+            self.generating.insert(chunk_pos, task);
+        }
+    }
+}
+
+pub fn setup_generation_queue(app: &mut App) {
+    app.insert_resource(GenerationQueue::default());
+    app.register_diagnostic(Diagnostic::new(GENERATING_CHUNKS))
+        .register_diagnostic(Diagnostic::new(QUEUED_CHUNKS));
+}
+
+/// Starts the queued generations, then sends the generated chunks to the players who asked
+/// for them, one update per player holding every chunk finished since the last frame.
+/// Empty chunks are neither kept nor sent, players are only told they are empty.
+pub fn generate_chunks_system(
+    mut queue: ResMut<GenerationQueue>,
+    mut world_map: ResMut<ServerWorldMap>,
+    mut server: ResMut<RenetServer>,
+    generator: Res<ActiveGenerator>,
+    seed: Res<WorldSeed>,
+    metadata: Res<WorldMetadata>,
+    ticker: Res<TickCounter>,
+    mut diagnostics: Diagnostics,
+) {
+    let queue = &mut *queue;
+    queue.retain_clients(|client| server.is_connected(*client));
+    queue.start_tasks(&generator, &seed, metadata.height);
+    let updates = queue.finish_tasks(&mut world_map);
+
+    for (client, update) in updates {
+        if !server.is_connected(client) {
+            continue;
+        }
+        let payload = bincode::options()
+            .serialize(&ServerToClientMessage::WorldUpdate(WorldUpdate {
+                tick: ticker.tick,
+                player_positions: world_map.player_positions.clone(),
+                time: world_map.time,
+                ..update
+            }))
+            .unwrap();
+        server.send_message(client, DefaultChannel::ReliableUnordered, payload);
+    }
+
+    diagnostics.add_measurement(&GENERATING_CHUNKS, || queue.generating.len() as f64);
+    diagnostics.add_measurement(&QUEUED_CHUNKS, || queue.queued.len() as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::generator::VoidGenerator;
+    use bevy::tasks::TaskPool;
+    use shared::world::{BlockData, BlockDirection, BlockId};
+    use shared::CHUNK_SIZE;
+    use std::sync::Arc;
+
+    const ALICE: ClientId = ClientId::from_raw(1);
+    const BOB: ClientId = ClientId::from_raw(2);
+
+    fn start_tasks(queue: &mut GenerationQueue) {
+        AsyncComputeTaskPool::get_or_init(TaskPool::default);
+        queue.start_tasks(
+            &ActiveGenerator(Arc::new(VoidGenerator)),
+            &WorldSeed(0),
+            WorldHeight::default(),
+        );
+    }
+
+    /// A queue where both players stand at the origin and see 4 chunks around them
+    fn queue() -> GenerationQueue {
+        let mut queue = GenerationQueue::default();
+        queue.update_view(ALICE, IVec3::ZERO, 4, 4);
+        queue.update_view(BOB, IVec3::ZERO, 4, 4);
+        queue
+    }
+
+    #[test]
+    fn chunks_requested_twice_are_generated_once() {
+        let mut queue = queue();
+        let chunk_pos = IVec3::new(1, 0, 1);
+        queue.request(chunk_pos, ALICE);
+        queue.request(chunk_pos, BOB);
+        assert_eq!(queue.queued.len(), 1);
+
+        start_tasks(&mut queue);
+        queue.request(chunk_pos, ALICE);
+        assert!(queue.queued.is_empty());
+        assert_eq!(queue.generating.len(), 1);
+        assert_eq!(queue.requesters[&chunk_pos], HashSet::from([ALICE, BOB]));
+    }
+
+    #[test]
+    fn chunks_out_of_view_are_not_generated() {
+        let mut queue = queue();
+        let chunk_pos = IVec3::new(3, 0, 0);
+        queue.request(chunk_pos, ALICE);
+        queue.request(chunk_pos, BOB);
+
+        // only Bob still sees the chunk
+        queue.update_view(ALICE, IVec3::new(-10, 0, 0), 4, 4);
+        start_tasks(&mut queue);
+        assert_eq!(queue.requesters[&chunk_pos], HashSet::from([BOB]));
+
+        // then Bob leaves before his next chunk is generated
+        let left = IVec3::new(-3, 0, 0);
+        queue.request(left, BOB);
+        queue.update_view(BOB, IVec3::new(10, 0, 0), 4, 4);
+        start_tasks(&mut queue);
+        assert!(!queue.generating.contains_key(&left));
+        assert!(!queue.requesters.contains_key(&left));
+    }
+
+    #[test]
+    fn cancelled_chunks_can_be_requested_again() {
+        let mut queue = queue();
+        let generating = IVec3::new(0, 0, 1);
+        let queued = IVec3::new(0, 0, 2);
+        queue.request(generating, ALICE);
+        start_tasks(&mut queue);
+        queue.request(queued, ALICE);
+
+        // a block interaction generates the chunks in the game loop
+        queue.cancel(generating);
+        queue.cancel(queued);
+        assert!(queue.generating.is_empty());
+        assert!(queue.requesters.is_empty());
+        start_tasks(&mut queue);
+        assert!(queue.is_idle());
+
+        // the same chunk queued twice is only generated once
+        queue.request(queued, ALICE);
+        queue.cancel(queued);
+        queue.request(queued, BOB);
+        assert_eq!(queue.queued.len(), 2);
+        start_tasks(&mut queue);
+        assert_eq!(queue.generating.len(), 1);
+        assert_eq!(queue.requesters[&queued], HashSet::from([BOB]));
+    }
+
+    #[test]
+    fn empty_chunks_are_remembered() {
+        let mut queue = queue();
+        let mut world_map = ServerWorldMap::default();
+        let empty = IVec3::new(1, 0, 1);
+        let platform = IVec3::new(0, 4, 0);
+        queue.request(empty, ALICE);
+        queue.request(platform, BOB);
+        start_tasks(&mut queue);
+
+        let mut updates = HashMap::new();
+        while !queue.generating.is_empty() {
+            updates.extend(queue.finish_tasks(&mut world_map));
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(updates[&ALICE].empty_chunks, vec![empty]);
+        assert!(updates[&BOB].new_map.contains_key(&platform));
+        assert_eq!(world_map.empty_chunks, HashSet::from([empty]));
+        assert!(world_map.map.contains_key(&platform));
+        assert!(!world_map.map.contains_key(&empty));
+
+        // a block placed in the chunk makes it a chunk like the others
+        world_map.set_block(
+            &(empty * CHUNK_SIZE),
+            BlockData::new(BlockId::Stone, false, BlockDirection::Front),
+        );
+        assert!(world_map.empty_chunks.is_empty());
+    }
+
+    #[test]
+    fn restoring_a_backup_drops_every_request() {
+        let mut queue = queue();
+        queue.request(IVec3::ZERO, ALICE);
+        start_tasks(&mut queue);
+        queue.request(IVec3::X, BOB);
+        queue.overflow.insert(BOB, VecDeque::from([IVec3::Y]));
+
+        queue.clear();
+        assert!(queue.is_idle());
+        assert!(queue.requesters.is_empty());
+        start_tasks(&mut queue);
+        assert!(queue.is_idle());
+    }
+
+    #[test]
+    fn requests_made_while_the_queue_is_full_wait_for_room() {
+        let mut queue = GenerationQueue::default();
+        queue.update_view(ALICE, IVec3::ZERO, 64, 4);
+        queue.update_view(BOB, IVec3::ZERO, 64, 4);
+        let area: Vec<IVec3> = (-40..40)
+            .flat_map(|x| (-40..40).map(move |z| IVec3::new(x, 0, z)))
+            .collect();
+        for chunk_pos in &area {
+            queue.request(*chunk_pos, ALICE);
+        }
+        queue.request(area[area.len() - 1], BOB);
+        queue.request(IVec3::new(50, 0, 50), BOB);
+        assert_eq!(queue.queued.len(), MAX_QUEUED_CHUNKS);
+        assert_eq!(queue.overflow[&ALICE].len(), area.len() - MAX_QUEUED_CHUNKS);
+
+        // Bob moved away from the second chunk he asked for
+        queue.update_view(BOB, area[area.len() - 1], 4, 4);
+        queue.queued.clear();
+        queue.requesters.clear();
+        start_tasks(&mut queue);
+        assert!(queue.overflow.is_empty());
+        assert_eq!(queue.requesters.len(), area.len() - MAX_QUEUED_CHUNKS);
+        assert_eq!(
+            queue.requesters[&area[area.len() - 1]],
+            HashSet::from([ALICE, BOB])
+        );
+        assert!(!queue.requesters.contains_key(&IVec3::new(50, 0, 50)));
+    }
+}
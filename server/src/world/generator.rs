@@ -0,0 +1,304 @@
+//! Generators produce the terrain of a world. A world is created with one of the generators of
+//! the `GeneratorRegistry`, whose name and options are then kept in the world metadata.
+//! Code embedding the server can register its own generators next to the built-in ones.
+
+use crate::world::generation::NoiseGenerator;
+use bevy::prelude::*;
+use serde::Deserialize;
+use shared::world::*;
+use shared::CHUNK_SIZE;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Produces the blocks of a world. Chunks nobody modified are not saved and are generated again
+/// when needed, so a given seed and position must always give the same blocks.
+pub trait WorldGenerator: Send + Sync {
+    /// Blocks of the chunk at `chunk_pos`, by position within the chunk. Blocks outside of
+    /// `height` are dropped.
+    fn generate_chunk(
+        &self,
+        chunk_pos: IVec3,
+        seed: u32,
+        height: WorldHeight,
+    ) -> HashMap<IVec3, BlockData>;
+
+    /// Bumped whenever the blocks generated for a given seed change
+    fn version(&self) -> u32 {
+        1
+    }
+
+    /// Where players appear in a new world
+    fn spawn_point(&self, _seed: u32, _height: WorldHeight) -> Vec3 {
+        DEFAULT_SPAWN
+    }
+}
+
+/// Generator of the loaded world
+#[derive(Resource, Clone)]
+pub struct ActiveGenerator(pub Arc<dyn WorldGenerator>);
+
+/// Generates a chunk which was never saved, without the blocks out of the world
+pub fn generate_chunk(
+    generator: &dyn WorldGenerator,
+    chunk_pos: IVec3,
+    seed: u32,
+    height: WorldHeight,
+) -> ServerChunk {
+    let mut map = generator.generate_chunk(chunk_pos, seed, height);
+    map.retain(|local_pos, _| height.contains(chunk_pos.y * CHUNK_SIZE + local_pos.y));
+    ServerChunk {
+        map,
+        ts: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+        modified: false,
+        generator_version: generator.version(),
+    }
+}
+
+/// Why the generator of a world could not be created
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeneratorError {
+    /// No generator is registered with this name
+    Unknown(String),
+    /// The generator rejected the options of the world
+    InvalidOptions { name: String, error: String },
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorError::Unknown(name) => write!(f, "no generator is named {}", name),
+            GeneratorError::InvalidOptions { name, error } => {
+                write!(f, "invalid options for generator {}: {}", name, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeneratorError {}
+
+/// Builds a generator from the options of a world
+type GeneratorFactory = Box<dyn Fn(&str) -> Result<Arc<dyn WorldGenerator>, String> + Send + Sync>;
+
+/// Generators worlds can be created with, by name
+pub struct GeneratorRegistry {
+    factories: HashMap<String, GeneratorFactory>,
+}
+
+impl Default for GeneratorRegistry {
+    /// The generators shipped with the game
+    fn default() -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+        };
+        registry.register(DEFAULT_GENERATOR, NoiseGenerator::from_options);
+        registry.register(FLAT_GENERATOR, FlatGenerator::from_options);
+        registry.register(VOID_GENERATOR, |_| Ok(VoidGenerator));
+        registry.register(DEBUG_GENERATOR, |_| Ok(DebugGenerator));
+        registry
+    }
+}
+
+impl GeneratorRegistry {
+    /// Adds a generator, replacing the one registered with the same name if any.
+    /// `factory` receives the options of the world in RON, empty for the defaults.
+    pub fn register<G, F>(&mut self, name: &str, factory: F)
+    where
+        G: WorldGenerator + 'static,
+        F: Fn(&str) -> Result<G, String> + Send + Sync + 'static,
+    {
+        self.factories.insert(
+            name.to_string(),
+            Box::new(move |options| {
+                factory(options).map(|generator| Arc::new(generator) as Arc<dyn WorldGenerator>)
+            }),
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    pub fn create(
+        &self,
+        settings: &GeneratorSettings,
+    ) -> Result<Arc<dyn WorldGenerator>, GeneratorError> {
+        let factory = self
+            .factories
+            .get(&settings.name)
+            .ok_or_else(|| GeneratorError::Unknown(settings.name.clone()))?;
+        factory(&settings.options).map_err(|error| GeneratorError::InvalidOptions {
+            name: settings.name.clone(),
+            error,
+        })
+    }
+}
+
+fn fill_layer(blocks: &mut HashMap<IVec3, BlockData>, dy: i32, block: BlockId) {
+    for dx in 0..CHUNK_SIZE {
+        for dz in 0..CHUNK_SIZE {
+            blocks.insert(
+                IVec3::new(dx, dy, dz),
+                BlockData::new(block, false, BlockDirection::Front),
+            );
+        }
+    }
+}
+
+/// Layer of a flat world
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlatLayer {
+    pub block: BlockId,
+    pub thickness: u32,
+}
+
+/// The same layers everywhere, stacked from the bottom of the world up, for instance
+/// `(layers: [(block: Bedrock, thickness: 1), (block: Sand, thickness: 10)])`
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlatGenerator {
+    pub layers: Vec<FlatLayer>,
+}
+
+impl Default for FlatGenerator {
+    /// Grass on a few layers of dirt and a thick layer of stone, the surface at height 64
+    fn default() -> Self {
+        let layer = |block, thickness| FlatLayer { block, thickness };
+        Self {
+            layers: vec![
+                layer(BlockId::Bedrock, 1),
+                layer(BlockId::Stone, 60),
+                layer(BlockId::Dirt, 3),
+                layer(BlockId::Grass, 1),
+            ],
+        }
+    }
+}
+
+impl FlatGenerator {
+    pub fn from_options(options: &str) -> Result<Self, String> {
+        if options.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        ron::de::from_str(options).map_err(|e| e.to_string())
+    }
+
+    /// Block of the layer `offset` blocks above the bottom of the world
+    fn block_at(&self, offset: i64) -> Option<BlockId> {
+        if offset < 0 {
+            return None;
+        }
+        let mut top = 0;
+        for layer in &self.layers {
+            top += i64::from(layer.thickness);
+            if offset < top {
+                return Some(layer.block);
+            }
+        }
+        None
+    }
+}
+
+impl WorldGenerator for FlatGenerator {
+    fn generate_chunk(
+        &self,
+        chunk_pos: IVec3,
+        _seed: u32,
+        height: WorldHeight,
+    ) -> HashMap<IVec3, BlockData> {
+        let mut blocks = HashMap::new();
+        for dy in 0..CHUNK_SIZE {
+            let y = CHUNK_SIZE * chunk_pos.y + dy;
+            if let Some(block) = self.block_at(i64::from(y) - i64::from(height.min)) {
+                fill_layer(&mut blocks, dy, block);
+            }
+        }
+        blocks
+    }
+
+    fn spawn_point(&self, _seed: u32, height: WorldHeight) -> Vec3 {
+        let thickness: u32 = self.layers.iter().map(|layer| layer.thickness).sum();
+        let surface = height.min as f32 + thickness as f32;
+        DEFAULT_SPAWN.with_y(DEFAULT_SPAWN.y.max(surface + 2.))
+    }
+}
+
+/// Height of the platform of void worlds, moved within the world when it is not as high
+const VOID_PLATFORM_HEIGHT: i32 = 64;
+/// Half the width of the platform of void worlds
+const VOID_PLATFORM_RADIUS: i32 = 2;
+
+/// Nothing but a small stone platform under the spawn point
+pub struct VoidGenerator;
+
+impl WorldGenerator for VoidGenerator {
+    fn generate_chunk(
+        &self,
+        chunk_pos: IVec3,
+        _seed: u32,
+        height: WorldHeight,
+    ) -> HashMap<IVec3, BlockData> {
+        let mut blocks = HashMap::new();
+        let origin = chunk_pos * CHUNK_SIZE;
+        let platform = VOID_PLATFORM_HEIGHT.clamp(height.min, height.max - 1);
+        for x in -VOID_PLATFORM_RADIUS..=VOID_PLATFORM_RADIUS {
+            for z in -VOID_PLATFORM_RADIUS..=VOID_PLATFORM_RADIUS {
+                let local_pos = IVec3::new(x, platform, z) - origin;
+                if local_pos.cmpge(IVec3::ZERO).all()
+                    && local_pos.cmplt(IVec3::splat(CHUNK_SIZE)).all()
+                {
+                    blocks.insert(
+                        local_pos,
+                        BlockData::new(BlockId::Stone, false, BlockDirection::Front),
+                    );
+                }
+            }
+        }
+        blocks
+    }
+}
+
+/// Height of the blocks of debug worlds, moved within the world when it is not as high
+const DEBUG_HEIGHT: i32 = 64;
+/// Distance between two blocks of debug worlds, so that each can be looked at on its own
+const DEBUG_SPACING: i32 = 2;
+
+/// Every block laid out in a square grid, in the order of `BlockId::ALL`, to check how they look
+pub struct DebugGenerator;
+
+impl DebugGenerator {
+    /// Position of the block at `index` in the grid
+    pub fn block_position(index: usize, height: WorldHeight) -> IVec3 {
+        let width = (BlockId::ALL.len() as f64).sqrt().ceil() as usize;
+        IVec3::new(
+            (index % width) as i32 * DEBUG_SPACING,
+            DEBUG_HEIGHT.clamp(height.min, height.max - 1),
+            (index / width) as i32 * DEBUG_SPACING,
+        )
+    }
+}
+
+impl WorldGenerator for DebugGenerator {
+    fn generate_chunk(
+        &self,
+        chunk_pos: IVec3,
+        _seed: u32,
+        height: WorldHeight,
+    ) -> HashMap<IVec3, BlockData> {
+        let mut blocks = HashMap::new();
+        let origin = chunk_pos * CHUNK_SIZE;
+        for (index, block) in BlockId::ALL.iter().enumerate() {
+            let local_pos = Self::block_position(index, height) - origin;
+            if local_pos.cmpge(IVec3::ZERO).all() && local_pos.cmplt(IVec3::splat(CHUNK_SIZE)).all()
+            {
+                blocks.insert(
+                    local_pos,
+                    BlockData::new(*block, false, BlockDirection::Front),
+                );
+            }
+        }
+        blocks
+    }
+}
@@ -1,74 +1,80 @@
 use bevy::prelude::*;
-use ron::de::from_str;
-use shared::world::data::{ServerWorldMap, WorldSeed};
-use shared::world::get_game_folder;
-use shared::GameFolderPaths;
-use std::fs;
-use std::path::Path;
+use shared::world::{WorldCreationSettings, WorldSeed};
+use std::fmt;
 
-use crate::world::data::SAVE_PATH;
-use std::path::PathBuf;
+use crate::world::generator::GeneratorError;
+use crate::world::migrations::{migrate, saved_version, SAVE_VERSION};
+use crate::world::storage::{LevelData, WorldStorage};
 
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct WorldData {
-    pub seed: WorldSeed,
-    pub map: ServerWorldMap,
-    pub time: u64,
+/// Why a world could not be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldLoadError {
+    /// The files of the world could not be read or parsed
+    Read(String),
+    /// The world was saved by a newer version of the game
+    UnsupportedVersion { version: u32 },
+    /// Upgrading the world from an older version failed
+    Migration { from: u32, error: String },
+    /// The generator of the world is unknown or its options are wrong
+    Generator(GeneratorError),
+    /// The heights of the world are out of order or out of bounds
+    InvalidHeight(String),
 }
 
-/// Charge les données combinées (carte et graine) d'un fichier
-pub fn load_world_data(
-    file_name: &str,
-    app: &App,
-) -> Result<WorldData, Box<dyn std::error::Error>> {
-    // Obtenir le chemin du dossier de jeu
-    let game_folder_path = app.world().get_resource::<GameFolderPaths>().unwrap();
+impl WorldLoadError {
+    pub(crate) fn read(error: &dyn fmt::Display) -> Self {
+        WorldLoadError::Read(error.to_string())
+    }
+}
+
+impl fmt::Display for WorldLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldLoadError::Read(error) => write!(f, "failed to read the world: {}", error),
+            WorldLoadError::UnsupportedVersion { version } => write!(
+                f,
+                "the world was saved with format version {}, this version of the game supports up to {}",
+                version, SAVE_VERSION
+            ),
+            WorldLoadError::Migration { from, error } => write!(
+                f,
+                "failed to upgrade the world from format version {}: {}",
+                from, error
+            ),
+            WorldLoadError::Generator(error) => {
+                write!(f, "failed to create the world generator: {}", error)
+            }
+            WorldLoadError::InvalidHeight(error) => {
+                write!(f, "invalid world height: {}", error)
+            }
+        }
+    }
+}
 
-    // Construire le chemin complet du fichier
-    let file_path: PathBuf = get_game_folder(Some(game_folder_path))
-        .join(SAVE_PATH)
-        .join(format!("{file_name}.ron"));
-    let path: &Path = file_path.as_path();
+impl std::error::Error for WorldLoadError {}
 
-    // Vérifier si le fichier existe
-    if !path.exists() {
+/// Loads the level data of a world, upgrading it first if it was saved by an older version.
+/// Chunks are then read from the region files when needed.
+/// A world which does not exist yet is created with the given settings.
+pub fn load_world(
+    storage: &WorldStorage,
+    creation: &WorldCreationSettings,
+) -> Result<LevelData, WorldLoadError> {
+    let Some(version) = saved_version(storage)? else {
         info!(
-            "World data file not found: {}. Generating default world and seed.",
-            file_path.display()
+            "World data not found: {}. Creating a new world.",
+            storage.world_dir.display()
         );
-        return Ok(WorldData {
-            map: ServerWorldMap {
-                name: file_name.to_string(),
-                ..Default::default()
-            },
-            seed: WorldSeed(rand::random::<u32>()),
-            time: 0,
+        return Ok(LevelData {
+            version: SAVE_VERSION,
+            seed: WorldSeed(creation.seed.unwrap_or_else(rand::random::<u32>)),
+            time: creation.time_of_day.start_time(),
         });
-    }
+    };
 
-    // Lire le contenu du fichier
-    let contents: String = fs::read_to_string(path)?;
-    let world_data: WorldData = from_str(&contents)?; // Désérialiser les données combinées
-    Ok(world_data)
-}
-
-pub fn load_world_map(
-    file_name: &str,
-    app: &App,
-) -> Result<ServerWorldMap, Box<dyn std::error::Error>> {
-    let world_data = load_world_data(file_name, app)?;
-    Ok(world_data.map)
-}
-
-pub fn load_world_time(file_name: &str, app: &App) -> Result<u64, Box<dyn std::error::Error>> {
-    let world_data = load_world_data(file_name, app)?;
-    Ok(world_data.time)
-}
+    if version != SAVE_VERSION {
+        migrate(storage, version)?;
+    }
 
-pub fn load_world_seed(
-    file_name: &str,
-    app: &App,
-) -> Result<WorldSeed, Box<dyn std::error::Error>> {
-    let world_data = load_world_data(file_name, app)?;
-    Ok(world_data.seed)
+    storage.load_level().map_err(|e| WorldLoadError::read(&e))
 }
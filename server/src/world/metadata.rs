@@ -0,0 +1,60 @@
+use crate::init::ServerLobby;
+use crate::world::load_from_file::WorldLoadError;
+use crate::world::migrations::SAVE_VERSION;
+use crate::world::storage::{LevelData, WorldStorage};
+use bevy::prelude::*;
+use shared::world::{WorldCreationSettings, WorldMetadata, METADATA_FILE};
+use std::time::Duration;
+
+/// Metadata of the world from its file, or new ones for a world which has none yet.
+/// The generator and height of a world are only kept there, so a file which cannot be read
+/// is an error rather than replaced.
+pub fn load_metadata(
+    storage: &WorldStorage,
+    world_name: &str,
+    level: &LevelData,
+    creation: &WorldCreationSettings,
+) -> Result<WorldMetadata, WorldLoadError> {
+    if storage.metadata_path().exists() {
+        let metadata = storage
+            .load_metadata()
+            .map_err(|e| WorldLoadError::Read(format!("{}: {}", METADATA_FILE, e)))?;
+        // Metadata files can be edited by hand, their height is not checked when they are read
+        metadata
+            .height
+            .validate()
+            .map_err(WorldLoadError::InvalidHeight)?;
+        return Ok(metadata);
+    }
+    let mut metadata = WorldMetadata::new(world_name.to_string(), level.seed.0, SAVE_VERSION);
+    // Worlds saved before metadata files existed were created in survival
+    if !storage.exists() {
+        metadata.game_mode = creation.game_mode;
+        metadata.generator = creation.generator.clone();
+        metadata.height = creation.height;
+    }
+    Ok(metadata)
+}
+
+pub fn setup_playtime(app: &mut App) {
+    app.add_systems(Update, playtime_system);
+}
+
+/// Counts the time at least one player is in the world
+fn playtime_system(
+    time: Res<Time>,
+    lobby: Res<ServerLobby>,
+    mut metadata: ResMut<WorldMetadata>,
+    mut elapsed: Local<Duration>,
+) {
+    if lobby.players.is_empty() {
+        return;
+    }
+
+    *elapsed += time.delta();
+    let secs = elapsed.as_secs();
+    if secs > 0 {
+        metadata.playtime += secs;
+        *elapsed -= Duration::from_secs(secs);
+    }
+}
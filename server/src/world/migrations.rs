@@ -0,0 +1,157 @@
+//! Saves are only ever read in the current format: an older world is first upgraded on disk,
+//! one version at a time. Changing the level file, the region files or anything serialized in
+//! a chunk (`ServerChunk`, `BlockData`, `BlockId`...) requires bumping `SAVE_VERSION` and adding
+//! the migration which converts the previous version.
+
+use crate::world::backup::create_backup;
+use crate::world::load_from_file::WorldLoadError;
+use crate::world::storage::{LevelData, WorldStorage};
+use bevy::prelude::*;
+use serde::Deserialize;
+use shared::world::{GeneratorPreset, ServerWorldMap, WorldMetadata, WorldSeed};
+use std::fs;
+
+/// Version of the save format written by this build
+pub const SAVE_VERSION: u32 = 3;
+
+/// File name of a world saved before region files, kept next to the migrated world
+pub const LEGACY_BACKUP_FILE: &str = "legacy.ron";
+
+type Migration = fn(&WorldStorage) -> Result<(), Box<dyn std::error::Error>>;
+
+/// `MIGRATIONS[v]` upgrades a world from version `v` to version `v + 1`
+const MIGRATIONS: [Migration; SAVE_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3];
+
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(default = "unversioned")]
+    version: u32,
+}
+
+/// Level files written before the version field are version 1
+fn unversioned() -> u32 {
+    1
+}
+
+/// Version of the world on disk, `None` if it was never saved
+pub fn saved_version(storage: &WorldStorage) -> Result<Option<u32>, WorldLoadError> {
+    if storage.exists() {
+        let contents =
+            fs::read_to_string(storage.level_path()).map_err(|e| WorldLoadError::read(&e))?;
+        let probe: VersionProbe =
+            ron::de::from_str(&contents).map_err(|e| WorldLoadError::read(&e))?;
+        Ok(Some(probe.version))
+    } else if storage.legacy_path().exists() {
+        Ok(Some(0))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Upgrades a world saved with version `from` to the current version
+pub fn migrate(storage: &WorldStorage, from: u32) -> Result<(), WorldLoadError> {
+    if from > SAVE_VERSION {
+        return Err(WorldLoadError::UnsupportedVersion { version: from });
+    }
+
+    // Version 0 is moved aside by its own migration, later ones are backed up first
+    if (1..SAVE_VERSION).contains(&from) {
+        create_backup(storage).map_err(|e| WorldLoadError::Migration {
+            from,
+            error: format!("failed to back up the world: {}", e),
+        })?;
+    }
+
+    for version in from..SAVE_VERSION {
+        info!(
+            "Migrating world {} from save version {} to {}",
+            storage.world_dir.display(),
+            version,
+            version + 1
+        );
+        MIGRATIONS[version as usize](storage).map_err(|e| WorldLoadError::Migration {
+            from: version,
+            error: e.to_string(),
+        })?;
+    }
+    Ok(())
+}
+
+/// Version 0: the whole world in a single `saves/<name>.ron` file
+#[derive(Deserialize)]
+struct WorldDataV0 {
+    seed: WorldSeed,
+    map: ServerWorldMap,
+    time: u64,
+}
+
+/// Version 1: level file without a version
+#[derive(Deserialize)]
+struct LevelDataV1 {
+    seed: WorldSeed,
+    time: u64,
+}
+
+/// Version 2: level file without a generator
+#[derive(Deserialize)]
+struct LevelDataV2 {
+    seed: WorldSeed,
+    time: u64,
+}
+
+/// Splits the single-file save into region files.
+/// The original file is moved into the world folder rather than deleted.
+fn migrate_v0_to_v1(storage: &WorldStorage) -> Result<(), Box<dyn std::error::Error>> {
+    let legacy_path = storage.legacy_path();
+    let contents = fs::read_to_string(&legacy_path)?;
+    let legacy: WorldDataV0 = ron::de::from_str(&contents)?;
+
+    let count = storage.save_chunks(legacy.map.map.iter())?;
+    // Written as version 1, the next migration adds the version
+    fs::create_dir_all(&storage.world_dir)?;
+    fs::write(
+        storage.level_path(),
+        format!("(seed: ({}), time: {})", legacy.seed.0, legacy.time),
+    )?;
+    fs::rename(&legacy_path, storage.world_dir.join(LEGACY_BACKUP_FILE))?;
+
+    info!("Migrated {} chunks", count);
+    Ok(())
+}
+
+/// Written by hand since `LevelData` is always the latest version
+fn migrate_v1_to_v2(storage: &WorldStorage) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(storage.level_path())?;
+    let level: LevelDataV1 = ron::de::from_str(&contents)?;
+    fs::write(
+        storage.level_path(),
+        format!(
+            "(version: 2, seed: ({}), time: {})",
+            level.seed.0, level.time
+        ),
+    )?;
+    Ok(())
+}
+
+/// Worlds from before generators were chosen all use the default one, which is written to the
+/// metadata, created for worlds which have none yet
+fn migrate_v2_to_v3(storage: &WorldStorage) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(storage.level_path())?;
+    let level: LevelDataV2 = ron::de::from_str(&contents)?;
+
+    let mut metadata = if storage.metadata_path().exists() {
+        storage.load_metadata()?
+    } else {
+        let name = storage.world_dir.file_name().unwrap_or_default();
+        WorldMetadata::new(name.to_string_lossy().into_owned(), level.seed.0, 3)
+    };
+    metadata.generator = GeneratorPreset::Default.settings();
+    storage.save_metadata(&metadata)?;
+
+    storage.save_level(&LevelData {
+        version: 3,
+        seed: level.seed,
+        time: level.time,
+    })
+}
@@ -1,15 +1,35 @@
+pub mod autosave;
+pub mod backup;
+mod caves;
 mod data;
+mod features;
 pub mod generation;
+pub mod generation_queue;
+pub mod generator;
 pub mod load_from_file;
+pub mod metadata;
+pub mod migrations;
+mod ores;
+pub mod pregen;
+pub mod preset;
+mod region;
 pub mod save;
+pub mod storage;
+pub mod unload;
 
 use bevy::prelude::Event;
 use bevy::prelude::EventReader;
 use bevy::prelude::IVec3;
 use bevy::prelude::ResMut;
 use bevy::prelude::*;
+use generation_queue::GenerationQueue;
+use generator::ActiveGenerator;
+use shared::world::global_block_to_chunk_pos;
 use shared::world::BlockData;
 use shared::world::ServerWorldMap;
+use shared::world::WorldMetadata;
+use shared::world::WorldSeed;
+use storage::{load_or_generate_chunk, WorldStorage};
 
 #[derive(Event, Debug)]
 pub struct BlockInteractionEvent {
@@ -19,9 +39,36 @@ pub struct BlockInteractionEvent {
 
 pub fn handle_block_interactions(
     mut world_map: ResMut<ServerWorldMap>,
+    storage: Res<WorldStorage>,
+    seed: Res<WorldSeed>,
+    generator: Res<ActiveGenerator>,
+    metadata: Res<WorldMetadata>,
+    mut queue: ResMut<GenerationQueue>,
     mut events: EventReader<BlockInteractionEvent>,
 ) {
     for event in events.read() {
+        // The chunk may only exist on disk, it has to be loaded before being modified
+        if !metadata.height.contains(event.position.y) {
+            warn!("Block interaction out of the world at {:?}", event.position);
+            continue;
+        }
+
+        let chunk_pos = global_block_to_chunk_pos(&event.position);
+        if let Err(e) = load_or_generate_chunk(
+            &mut world_map,
+            &storage,
+            &seed,
+            &*generator.0,
+            metadata.height,
+            chunk_pos,
+        ) {
+            error!("Failed to load chunk {:?}: {}", chunk_pos, e);
+            continue;
+        }
+        // A generation still running would overwrite the change once done, players waiting
+        // for the chunk get it with the change instead
+        queue.cancel(chunk_pos);
+
         match &event.block_type {
             Some(block) => {
                 // Ajouter un bloc
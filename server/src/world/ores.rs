@@ -0,0 +1,122 @@
+use crate::world::generation::seeded_rng;
+use bevy::prelude::*;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use shared::{world::*, CHUNK_SIZE};
+use std::collections::HashMap;
+
+/// How an ore is spread in the stone
+struct OreDistribution {
+    block: BlockId,
+    /// Veins started in each column of chunks
+    veins_per_column: u32,
+    /// Heights above the bottom of the world veins start at, most of them around the middle
+    /// of the range. Taller worlds have their ores spread from their bottom the same way.
+    min_y: i32,
+    max_y: i32,
+    /// Most blocks in a vein
+    vein_size: u32,
+}
+
+const ORES: [OreDistribution; 4] = [
+    OreDistribution {
+        block: BlockId::CoalOre,
+        veins_per_column: 20,
+        min_y: 5,
+        max_y: 96,
+        vein_size: 10,
+    },
+    OreDistribution {
+        block: BlockId::IronOre,
+        veins_per_column: 10,
+        min_y: 5,
+        max_y: 64,
+        vein_size: 6,
+    },
+    OreDistribution {
+        block: BlockId::GoldOre,
+        veins_per_column: 3,
+        min_y: 5,
+        max_y: 32,
+        vein_size: 6,
+    },
+    OreDistribution {
+        block: BlockId::DiamondOre,
+        veins_per_column: 1,
+        min_y: 5,
+        max_y: 16,
+        vein_size: 4,
+    },
+];
+
+/// Farthest a vein reaches from where it starts
+const VEIN_RADIUS: i32 = 3;
+
+/// Blocks of a vein starting at `origin`, grown by a random walk around it
+fn vein_blocks(rng: &mut ChaCha8Rng, origin: IVec3, size: u32) -> Vec<IVec3> {
+    let directions = [
+        IVec3::X,
+        IVec3::NEG_X,
+        IVec3::Y,
+        IVec3::NEG_Y,
+        IVec3::Z,
+        IVec3::NEG_Z,
+    ];
+
+    let mut blocks = vec![origin];
+    let mut pos = origin;
+    for _ in 1..rng.gen_range(size / 2..=size) {
+        let next = pos + directions[rng.gen_range(0..directions.len())];
+        if (next - origin).abs().max_element() <= VEIN_RADIUS {
+            pos = next;
+            blocks.push(pos);
+        }
+    }
+    blocks
+}
+
+/// Replaces stone with the ores of the veins reaching the chunk. Veins are planned for whole
+/// columns of chunks from the seed, so that those crossing a chunk border are not cut.
+pub fn place_ores(
+    blocks: &mut HashMap<IVec3, BlockData>,
+    chunk_pos: IVec3,
+    seed: u32,
+    height: WorldHeight,
+) {
+    let origin = chunk_pos * CHUNK_SIZE;
+    // heights relative to the bottom of the world
+    let bottom = origin.y - VEIN_RADIUS - height.min;
+    let top = origin.y + CHUNK_SIZE - 1 + VEIN_RADIUS - height.min;
+
+    for cx in chunk_pos.x - 1..=chunk_pos.x + 1 {
+        for cz in chunk_pos.z - 1..=chunk_pos.z + 1 {
+            for (index, ore) in ORES.iter().enumerate() {
+                if ore.max_y < bottom || ore.min_y > top {
+                    continue;
+                }
+
+                let mut rng = seeded_rng(seed, &[cx, cz, index as i32]);
+                for _ in 0..ore.veins_per_column {
+                    let start = IVec3::new(
+                        cx * CHUNK_SIZE + rng.gen_range(0..CHUNK_SIZE),
+                        height.min
+                            + (rng.gen_range(ore.min_y..=ore.max_y)
+                                + rng.gen_range(ore.min_y..=ore.max_y))
+                                / 2,
+                        cz * CHUNK_SIZE + rng.gen_range(0..CHUNK_SIZE),
+                    );
+                    // the walk is drawn even for far veins, so that the next ones stay the same
+                    let vein = vein_blocks(&mut rng, start, ore.vein_size);
+                    for pos in vein {
+                        let local_pos = pos - origin;
+                        if let Some(block) = blocks.get_mut(&local_pos) {
+                            if block.id == BlockId::Stone {
+                                block.id = ore.block;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
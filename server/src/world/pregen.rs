@@ -0,0 +1,405 @@
+//! Pre-generation fills an area of the world ahead of time, so that players exploring it load
+//! saved chunks instead of waiting for their generation. It only runs while no player is waiting
+//! for a chunk, and writes its progress to the world folder so that it resumes after a restart.
+
+use crate::world::generation_queue::GenerationQueue;
+use crate::world::generator::{generate_chunk, ActiveGenerator};
+use crate::world::region::REGION_SIZE;
+use crate::world::save::WorldSaver;
+use crate::world::storage::{write_ron, WorldStorage};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use shared::world::{WorldMetadata, WorldSeed};
+use shared::CHUNK_SIZE;
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub const PREGEN_FILE: &str = "pregen.ron";
+/// Largest radius of a pre-generation, in blocks
+pub const MAX_PREGEN_RADIUS: i32 = 16384;
+/// Tasks generating chunks at the same time, few enough to leave the cores to the game loop
+const MAX_PREGEN_TASKS: usize = 4;
+/// Chunks generated by each task
+const TASK_CHUNKS: usize = 16;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Square of chunk columns to generate, and how far it went
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PregenJob {
+    /// Column in the middle of the area, in chunks
+    pub center: IVec2,
+    /// Columns on each side of the center, in chunks
+    pub radius: i32,
+    /// Region files of `batches` already generated and saved
+    pub done: usize,
+}
+
+impl PregenJob {
+    pub fn new(center: IVec2, radius: i32) -> Self {
+        Self {
+            center,
+            radius,
+            done: 0,
+        }
+    }
+
+    /// Regions the area overlaps, in rings around the region of the center
+    pub fn regions(&self) -> impl Iterator<Item = IVec2> {
+        let min = (self.center - IVec2::splat(self.radius)).div_euclid(IVec2::splat(REGION_SIZE));
+        let max = (self.center + IVec2::splat(self.radius)).div_euclid(IVec2::splat(REGION_SIZE));
+        let center = self.center.div_euclid(IVec2::splat(REGION_SIZE));
+        let rings = (center - min).max(max - center).max_element();
+
+        (0..=rings)
+            .flat_map(move |ring| {
+                (center.x - ring..=center.x + ring).flat_map(move |x| {
+                    // the first and last columns of a ring are full, the others only hold its ends
+                    let step = if (x - center.x).abs() == ring {
+                        1
+                    } else {
+                        2 * ring as usize
+                    };
+                    (center.y - ring..=center.y + ring)
+                        .step_by(step)
+                        .map(move |y| IVec2::new(x, y))
+                })
+            })
+            .filter(move |region| region.cmpge(min).all() && region.cmple(max).all())
+    }
+
+    /// First and last columns of the area within a region
+    fn region_bounds(&self, region: IVec2) -> (IVec2, IVec2) {
+        let min = (region * REGION_SIZE).max(self.center - IVec2::splat(self.radius));
+        let max = (region * REGION_SIZE + IVec2::splat(REGION_SIZE - 1))
+            .min(self.center + IVec2::splat(self.radius));
+        (min, max)
+    }
+
+    /// Columns of the area within a region
+    pub fn region_columns(&self, region: IVec2) -> impl Iterator<Item = IVec2> {
+        let (min, max) = self.region_bounds(region);
+        (min.y..=max.y).flat_map(move |z| (min.x..=max.x).map(move |x| IVec2::new(x, z)))
+    }
+
+    fn region_column_count(&self, region: IVec2) -> u64 {
+        let (min, max) = self.region_bounds(region);
+        let size = (max - min + IVec2::ONE).max(IVec2::ZERO);
+        size.x as u64 * size.y as u64
+    }
+
+    /// Region files written by the job, one per region and chunk layer, in the order they are
+    /// generated: a whole region file is saved at once so that it is only rewritten once
+    pub fn batches(&self, layers: RangeInclusive<i32>) -> impl Iterator<Item = IVec3> {
+        self.regions().flat_map(move |region| {
+            layers
+                .clone()
+                .map(move |y| IVec3::new(region.x, y, region.y))
+        })
+    }
+
+    pub fn total_columns(&self) -> u64 {
+        let side = 2 * self.radius as u64 + 1;
+        side * side
+    }
+
+    fn path(storage: &WorldStorage) -> PathBuf {
+        storage.world_dir.join(PREGEN_FILE)
+    }
+
+    /// The pre-generation the world was running when the server stopped, if any
+    pub fn load(storage: &WorldStorage) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let job: Self = match fs::read_to_string(Self::path(storage)) {
+            Ok(contents) => ron::de::from_str(&contents)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if !(0..=MAX_PREGEN_RADIUS / CHUNK_SIZE).contains(&job.radius) {
+            return Err(format!("invalid radius {}", job.radius).into());
+        }
+        Ok(Some(job))
+    }
+
+    pub fn save(&self, storage: &WorldStorage) -> Result<(), Box<dyn std::error::Error>> {
+        write_ron(&Self::path(storage), self)
+    }
+
+    pub fn remove(storage: &WorldStorage) -> io::Result<()> {
+        match fs::remove_file(Self::path(storage)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+struct RunningPregen {
+    job: PregenJob,
+    /// Chunk layers of the world
+    layers: RangeInclusive<i32>,
+    /// Region file being generated, `None` once they are all saved
+    batch: Option<IVec3>,
+    /// Chunks of the batch which are not generating yet
+    pending: Vec<IVec3>,
+    generating: Vec<Task<Vec<(IVec3, Vec<u8>)>>>,
+    /// Serialized chunks of the batch waiting for the rest of it
+    generated: Vec<(IVec3, Vec<u8>)>,
+    /// Whether the `WorldSaver` is writing the batch
+    writing: bool,
+    chunks_done: u64,
+    chunks_total: u64,
+    /// Chunks done when the server started running the job, to measure its speed
+    chunks_at_start: u64,
+    started: Instant,
+    report_timer: Timer,
+}
+
+impl RunningPregen {
+    fn new(job: PregenJob, layers: RangeInclusive<i32>) -> Self {
+        let layer_count = layers.clone().count() as u64;
+        let chunks_done = job
+            .batches(layers.clone())
+            .take(job.done)
+            .map(|batch| job.region_column_count(batch.xz()))
+            .sum();
+        let mut running = Self {
+            chunks_total: job.total_columns() * layer_count,
+            chunks_done,
+            chunks_at_start: chunks_done,
+            job,
+            layers,
+            batch: None,
+            pending: Vec::new(),
+            generating: Vec::new(),
+            generated: Vec::new(),
+            writing: false,
+            started: Instant::now(),
+            report_timer: Timer::new(PROGRESS_INTERVAL, TimerMode::Repeating),
+        };
+        running.start_batch();
+        running
+    }
+
+    /// Starts generating the region file after those done, from scratch
+    fn start_batch(&mut self) {
+        self.batch = self.job.batches(self.layers.clone()).nth(self.job.done);
+        self.pending = match self.batch {
+            Some(batch) => self
+                .job
+                .region_columns(batch.xz())
+                .map(|column| IVec3::new(column.x, batch.y, column.y))
+                .collect(),
+            None => Vec::new(),
+        };
+        self.generating.clear();
+        self.generated.clear();
+        self.writing = false;
+    }
+
+    /// Moves to the next region file once the batch is saved
+    fn finish_batch(&mut self, storage: &WorldStorage) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(batch) = self.batch {
+            self.chunks_done += self.job.region_column_count(batch.xz());
+        }
+        self.job.done += 1;
+        self.job.save(storage)?;
+        self.start_batch();
+        Ok(())
+    }
+
+    fn progress(&self) -> String {
+        let done = self.chunks_done;
+        let total = self.chunks_total;
+        let speed = match done - self.chunks_at_start {
+            0 => 0.,
+            generated => generated as f64 / self.started.elapsed().as_secs_f64(),
+        };
+        let eta = if speed > 0. {
+            format_duration(Duration::from_secs_f64((total - done) as f64 / speed))
+        } else {
+            "unknown".to_string()
+        };
+        format!(
+            "Pre-generation: {}/{} chunks ({:.1}%), {:.1} chunks/s, time left: {}",
+            done,
+            total,
+            100. * done as f64 / total as f64,
+            speed,
+            eta
+        )
+    }
+}
+
+/// Formats a duration as `1h 02m 03s`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+    }
+}
+
+/// Pre-generation of the world, at most one at a time
+#[derive(Resource, Default)]
+pub struct Pregeneration {
+    running: Option<RunningPregen>,
+}
+
+impl Pregeneration {
+    /// Starts generating the area of `job` in a world with `layers`, the job is saved to resume
+    /// it after a restart
+    pub fn start(
+        &mut self,
+        job: PregenJob,
+        layers: RangeInclusive<i32>,
+        storage: &WorldStorage,
+    ) -> Result<(), String> {
+        if self.running.is_some() {
+            return Err("A pre-generation is already running".to_string());
+        }
+        job.save(storage)
+            .map_err(|e| format!("Failed to save the pre-generation: {}", e))?;
+        self.running = Some(RunningPregen::new(job, layers));
+        Ok(())
+    }
+
+    /// Stops the running pre-generation, the chunks it saved are kept
+    pub fn cancel(&mut self, storage: &WorldStorage) -> Result<(), String> {
+        if self.running.take().is_none() {
+            return Err("No pre-generation is running".to_string());
+        }
+        PregenJob::remove(storage).map_err(|e| format!("Failed to remove {}: {}", PREGEN_FILE, e))
+    }
+
+    pub fn status(&self) -> Option<String> {
+        self.running.as_ref().map(RunningPregen::progress)
+    }
+
+    /// Generates the current batch again, its chunks no longer match the world
+    pub fn restart_batch(&mut self) {
+        if let Some(running) = self.running.as_mut() {
+            running.start_batch();
+        }
+    }
+}
+
+pub fn setup_pregeneration(app: &mut App) {
+    let mut pregen = Pregeneration::default();
+    let storage = app.world().resource::<WorldStorage>();
+    let layers = app
+        .world()
+        .resource::<WorldMetadata>()
+        .height
+        .chunk_layers();
+    match PregenJob::load(storage) {
+        Ok(Some(job)) => {
+            let running = RunningPregen::new(job, layers);
+            info!("Resuming pre-generation. {}", running.progress());
+            pregen.running = Some(running);
+        }
+        Ok(None) => {}
+        Err(e) => error!("Failed to load the pre-generation: {}", e),
+    }
+    app.insert_resource(pregen);
+    app.add_systems(Update, pregenerate_chunks_system);
+}
+
+/// Generates the chunks of the current region file while no player waits for a chunk, then has
+/// the `WorldSaver` write them all at once between two saves
+fn pregenerate_chunks_system(
+    mut pregen: ResMut<Pregeneration>,
+    storage: Res<WorldStorage>,
+    queue: Res<GenerationQueue>,
+    mut saver: ResMut<WorldSaver>,
+    generator: Res<ActiveGenerator>,
+    seed: Res<WorldSeed>,
+    metadata: Res<WorldMetadata>,
+    time: Res<Time>,
+) {
+    // polled even without a pre-generation, the write of a cancelled one would block saves
+    let written = saver.poll_write();
+    let Some(running) = pregen.running.as_mut() else {
+        return;
+    };
+
+    if running.writing {
+        match written {
+            Some(Ok(_)) => {
+                if let Err(e) = running.finish_batch(&storage) {
+                    error!("Pre-generation stopped, failed to save its progress: {}", e);
+                    pregen.running = None;
+                    return;
+                }
+            }
+            Some(Err(e)) => {
+                // the saved progress is left as is, the job resumes from it on the next start
+                error!("Pre-generation stopped, failed to save chunks: {}", e);
+                pregen.running = None;
+                return;
+            }
+            None => {}
+        }
+    }
+
+    if running.batch.is_none() {
+        info!(
+            "Pre-generation done: {} chunks in {}",
+            running.chunks_total,
+            format_duration(running.started.elapsed())
+        );
+        if let Err(e) = PregenJob::remove(&storage) {
+            error!("Failed to remove {}: {}", PREGEN_FILE, e);
+        }
+        pregen.running = None;
+        return;
+    }
+
+    running
+        .generating
+        .retain_mut(|task| match block_on(future::poll_once(task)) {
+            Some(chunks) => {
+                running.generated.extend(chunks);
+                false
+            }
+            None => true,
+        });
+
+    if queue.is_idle() {
+        let pool = AsyncComputeTaskPool::get();
+        while !running.pending.is_empty() && running.generating.len() < MAX_PREGEN_TASKS {
+            let split = running.pending.len().saturating_sub(TASK_CHUNKS);
+            let chunks = running.pending.split_off(split);
+            let generator = generator.0.clone();
+            let seed = seed.0;
+            let height = metadata.height;
+            // serialized right away, they are only kept to be written
+            running.generating.push(pool.spawn(async move {
+                chunks
+                    .into_iter()
+                    .map(|chunk_pos| {
+                        let chunk = generate_chunk(&*generator, chunk_pos, seed, height);
+                        (chunk_pos, bincode::options().serialize(&chunk).unwrap())
+                    })
+                    .collect()
+            }));
+        }
+    }
+
+    if !running.writing
+        && running.pending.is_empty()
+        && running.generating.is_empty()
+        && !saver.is_saving()
+    {
+        let chunks = std::mem::take(&mut running.generated);
+        running.writing = saver.start_write(&storage, chunks);
+    }
+
+    running.report_timer.tick(time.delta());
+    if running.report_timer.just_finished() {
+        info!("{}", running.progress());
+    }
+}
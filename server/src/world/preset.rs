@@ -0,0 +1,245 @@
+//! Parameters of the default generator, read from the options of the world so that terrain can
+//! be tuned without recompiling. `data/generator_presets/default.ron` lists all of them with
+//! their built-in values, which generate the same worlds as an empty preset.
+
+use crate::world::generation::SEA_LEVEL;
+use serde::Deserialize;
+use shared::world::{get_biome_data, Biome, BiomeType};
+
+/// Bounds a value has to be within to match a rule, both excluded
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Bounds {
+    pub above: f64,
+    pub below: f64,
+}
+
+impl Default for Bounds {
+    /// Matches everything
+    fn default() -> Self {
+        Self {
+            above: f64::NEG_INFINITY,
+            below: f64::INFINITY,
+        }
+    }
+}
+
+impl Bounds {
+    fn above(value: f64) -> Self {
+        Self {
+            above: value,
+            ..Default::default()
+        }
+    }
+
+    fn below(value: f64) -> Self {
+        Self {
+            below: value,
+            ..Default::default()
+        }
+    }
+
+    fn contains(&self, value: f64) -> bool {
+        value > self.above && value < self.below
+    }
+}
+
+/// Climate a biome appears in. All values are between 0 and 1, `river` being 0 in the middle
+/// of a river and `continentalness` 0 in the middle of oceans.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct BiomeRule {
+    pub biome: BiomeType,
+    #[serde(default)]
+    pub temperature: Bounds,
+    #[serde(default)]
+    pub humidity: Bounds,
+    #[serde(default)]
+    pub continentalness: Bounds,
+    #[serde(default)]
+    pub river: Bounds,
+}
+
+impl BiomeRule {
+    fn new(biome: BiomeType) -> Self {
+        Self {
+            biome,
+            temperature: Bounds::default(),
+            humidity: Bounds::default(),
+            continentalness: Bounds::default(),
+            river: Bounds::default(),
+        }
+    }
+
+    fn matches_everything(&self) -> bool {
+        *self == Self::new(self.biome)
+    }
+
+    fn matches(&self, temperature: f64, humidity: f64, continentalness: f64, river: f64) -> bool {
+        self.temperature.contains(temperature)
+            && self.humidity.contains(humidity)
+            && self.continentalness.contains(continentalness)
+            && self.river.contains(river)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TerrainPreset {
+    /// Height up to which low terrain is filled with water
+    pub sea_level: i32,
+    /// Scales of the noises, the smaller the wider the hills and areas they draw
+    pub terrain_scale: f64,
+    pub biome_scale: f64,
+    pub continent_scale: f64,
+    pub river_scale: f64,
+    /// The biome of a column is the one of the first rule its climate matches,
+    /// so the last rule has to match everything
+    pub biome_rules: Vec<BiomeRule>,
+    pub biomes: Vec<Biome>,
+}
+
+impl Default for TerrainPreset {
+    fn default() -> Self {
+        let rule = BiomeRule::new;
+        let hot = Bounds::above(0.6);
+        let temperate = Bounds::above(0.3);
+        Self {
+            sea_level: SEA_LEVEL,
+            terrain_scale: 0.1,
+            biome_scale: 0.01,
+            continent_scale: 0.004,
+            river_scale: 0.004,
+            biome_rules: vec![
+                BiomeRule {
+                    continentalness: Bounds::below(0.3),
+                    ..rule(BiomeType::Ocean)
+                },
+                BiomeRule {
+                    continentalness: Bounds::below(0.33),
+                    ..rule(BiomeType::Beach)
+                },
+                BiomeRule {
+                    river: Bounds::below(0.02),
+                    ..rule(BiomeType::River)
+                },
+                BiomeRule {
+                    temperature: hot,
+                    humidity: Bounds::above(0.75),
+                    ..rule(BiomeType::Swamp)
+                },
+                BiomeRule {
+                    temperature: hot,
+                    humidity: Bounds::above(0.5),
+                    ..rule(BiomeType::Forest)
+                },
+                BiomeRule {
+                    temperature: hot,
+                    humidity: Bounds::above(0.35),
+                    ..rule(BiomeType::Savanna)
+                },
+                BiomeRule {
+                    temperature: hot,
+                    ..rule(BiomeType::Desert)
+                },
+                BiomeRule {
+                    temperature: temperate,
+                    humidity: Bounds::above(0.7),
+                    ..rule(BiomeType::FlowerPlains)
+                },
+                BiomeRule {
+                    temperature: temperate,
+                    humidity: Bounds::above(0.5),
+                    ..rule(BiomeType::Plains)
+                },
+                BiomeRule {
+                    temperature: temperate,
+                    ..rule(BiomeType::MediumMountain)
+                },
+                // the coldest places are frozen, the others are covered with spruce forests
+                BiomeRule {
+                    temperature: Bounds::above(0.2),
+                    humidity: Bounds::above(0.5),
+                    ..rule(BiomeType::Taiga)
+                },
+                BiomeRule {
+                    humidity: Bounds::above(0.5),
+                    ..rule(BiomeType::IcePlain)
+                },
+                rule(BiomeType::HighMountainGrass),
+            ],
+            biomes: [
+                BiomeType::Plains,
+                BiomeType::Forest,
+                BiomeType::MediumMountain,
+                BiomeType::HighMountainGrass,
+                BiomeType::Desert,
+                BiomeType::IcePlain,
+                BiomeType::FlowerPlains,
+                BiomeType::Ocean,
+                BiomeType::River,
+                BiomeType::Beach,
+                BiomeType::Taiga,
+                BiomeType::Swamp,
+                BiomeType::Savanna,
+            ]
+            .into_iter()
+            .map(get_biome_data)
+            .collect(),
+        }
+    }
+}
+
+impl TerrainPreset {
+    /// Reads a preset in RON, fields it leaves out keep their built-in values
+    pub fn from_options(options: &str) -> Result<Self, String> {
+        if options.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let preset: Self = ron::de::from_str(options).map_err(|e| e.to_string())?;
+        preset.validate()?;
+        Ok(preset)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self
+            .biome_rules
+            .last()
+            .is_some_and(BiomeRule::matches_everything)
+        {
+            return Err("the last biome rule has to match every climate".into());
+        }
+        for rule in &self.biome_rules {
+            if !self
+                .biomes
+                .iter()
+                .any(|biome| biome.biome_type == rule.biome)
+            {
+                return Err(format!(
+                    "biome {:?} has a rule but no parameters",
+                    rule.biome
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parameters of the biome of a column from its climate, see `BiomeRule`
+    pub fn biome_at(
+        &self,
+        temperature: f64,
+        humidity: f64,
+        continentalness: f64,
+        river: f64,
+    ) -> &Biome {
+        // validated presets always end with a rule matching everything
+        let rule = self
+            .biome_rules
+            .iter()
+            .find(|rule| rule.matches(temperature, humidity, continentalness, river))
+            .unwrap();
+        self.biomes
+            .iter()
+            .find(|biome| biome.biome_type == rule.biome)
+            .unwrap()
+    }
+}
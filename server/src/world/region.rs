@@ -0,0 +1,180 @@
+use bevy::math::IVec3;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of chunks along the X and Z axes of a region, a region is one chunk high
+pub const REGION_SIZE: i32 = 32;
+const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+
+const MAGIC: &[u8; 4] = b"RCRG";
+const FORMAT_VERSION: u32 = 1;
+/// Magic, format version, then an (offset, length) pair per chunk
+const HEADER_SIZE: usize = 8 + REGION_CHUNKS * 8;
+
+/// Position of the region containing a chunk
+pub fn region_pos(chunk_pos: IVec3) -> IVec3 {
+    IVec3::new(
+        chunk_pos.x.div_euclid(REGION_SIZE),
+        chunk_pos.y,
+        chunk_pos.z.div_euclid(REGION_SIZE),
+    )
+}
+
+fn chunk_index(chunk_pos: IVec3) -> usize {
+    let x = chunk_pos.x.rem_euclid(REGION_SIZE);
+    let z = chunk_pos.z.rem_euclid(REGION_SIZE);
+    (z * REGION_SIZE + x) as usize
+}
+
+pub fn region_file_name(region: IVec3) -> String {
+    format!("r.{}.{}.{}.region", region.x, region.y, region.z)
+}
+
+/// Offset table of a region file, an offset of 0 means the chunk is absent
+struct Header {
+    entries: Vec<(u32, u32)>,
+}
+
+impl Header {
+    fn empty() -> Self {
+        Self {
+            entries: vec![(0, 0); REGION_CHUNKS],
+        }
+    }
+
+    fn read(file: &mut File) -> io::Result<Self> {
+        let mut bytes = vec![0; HEADER_SIZE];
+        file.read_exact(&mut bytes)?;
+
+        if &bytes[0..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a region file",
+            ));
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported region format version {}", version),
+            ));
+        }
+
+        let entries = bytes[8..]
+            .chunks_exact(8)
+            .map(|entry| {
+                (
+                    u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+                    u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+                )
+            })
+            .collect();
+        Ok(Self { entries })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        for (offset, length) in &self.entries {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// Reads the serialized chunk at `chunk_pos` from a region file, if it was saved
+pub fn read_chunk(path: &Path, chunk_pos: IVec3) -> io::Result<Option<Vec<u8>>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let header = Header::read(&mut file)?;
+    let (offset, length) = header.entries[chunk_index(chunk_pos)];
+    if offset == 0 {
+        return Ok(None);
+    }
+
+    let mut data = vec![0; length as usize];
+    file.seek(SeekFrom::Start(offset as u64))?;
+    file.read_exact(&mut data)?;
+    Ok(Some(data))
+}
+
+/// Whether the chunk at `chunk_pos` was saved in a region file, reading only its header
+pub fn contains_chunk(path: &Path, chunk_pos: IVec3) -> io::Result<bool> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    let header = Header::read(&mut file)?;
+    Ok(header.entries[chunk_index(chunk_pos)].0 != 0)
+}
+
+/// Reads every chunk stored in a region file
+fn read_all_chunks(path: &Path) -> io::Result<Vec<Option<Vec<u8>>>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![None; REGION_CHUNKS]),
+        Err(e) => return Err(e),
+    };
+
+    let header = Header::read(&mut file)?;
+    let mut contents = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut contents)?;
+
+    header
+        .entries
+        .iter()
+        .map(|&(offset, length)| {
+            if offset == 0 {
+                return Ok(None);
+            }
+            let (start, end) = (offset as usize, offset as usize + length as usize);
+            contents
+                .get(start..end)
+                .map(|data| Some(data.to_vec()))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated region"))
+        })
+        .collect()
+}
+
+/// Writes serialized chunks into a region file, keeping the chunks already stored in it.
+/// The region is rewritten to a temporary file then renamed, so a crash never leaves it half-written.
+pub fn write_chunks(path: &Path, chunks: &[(IVec3, Vec<u8>)]) -> io::Result<()> {
+    let mut stored = read_all_chunks(path)?;
+    for (chunk_pos, data) in chunks {
+        stored[chunk_index(*chunk_pos)] = Some(data.clone());
+    }
+
+    let mut header = Header::empty();
+    let mut body = Vec::new();
+    for (index, data) in stored.iter().enumerate() {
+        if let Some(data) = data {
+            header.entries[index] = ((HEADER_SIZE + body.len()) as u32, data.len() as u32);
+            body.extend_from_slice(data);
+        }
+    }
+
+    let tmp_path = tmp_path(path);
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&header.to_bytes())?;
+        file.write_all(&body)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
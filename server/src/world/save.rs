@@ -1,79 +1,248 @@
 use crate::init::ServerTime;
+use crate::world::backup::create_backup;
+use crate::world::migrations::SAVE_VERSION;
+use crate::world::storage::{LevelData, WorldStorage};
 use bevy::prelude::*;
-use ron::ser::PrettyConfig;
-use shared::world::get_game_folder;
+use bevy::tasks::{block_on, futures_lite::future, IoTaskPool, Task};
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
+use bincode::Options;
+use shared::messages::{SaveWorldResponse, ServerToClientMessage};
+use shared::world::ServerChunk;
 use shared::world::ServerWorldMap;
 use shared::world::WorldSeed;
-use shared::GameFolderPaths;
-use std::{fs::File, io::Write, path::Path};
+use shared::world::{unix_timestamp, WorldMetadata};
+use std::collections::HashMap;
 
 #[derive(Event)]
-pub struct SaveRequestEvent;
+pub struct SaveRequestEvent {
+    /// Player told about the outcome of the save, `None` when the server saves on its own
+    pub requester: Option<ClientId>,
+    /// Also copy the saved world into a new backup
+    pub backup: bool,
+}
+
+/// Copy of everything a save writes, so that it can be written away from the game loop
+pub struct WorldSnapshot {
+    level: LevelData,
+    metadata: WorldMetadata,
+    chunks: HashMap<IVec3, ServerChunk>,
+}
+
+impl WorldSnapshot {
+    /// Copies the chunks changed since the last save, which are then no longer dirty.
+    /// Chunks players never modified are left out, they are generated again when needed.
+    pub fn take(
+        world_map: &mut ServerWorldMap,
+        seed: &WorldSeed,
+        time: &ServerTime,
+        metadata: &WorldMetadata,
+    ) -> Self {
+        let dirty_chunks = std::mem::take(&mut world_map.dirty_chunks);
+        let chunks = dirty_chunks
+            .into_iter()
+            .filter_map(|pos| world_map.map.get(&pos).map(|chunk| (pos, chunk)))
+            .filter(|(_, chunk)| chunk.modified)
+            .map(|(pos, chunk)| (pos, chunk.clone()))
+            .collect();
+
+        let mut metadata = metadata.clone();
+        metadata.seed = seed.0;
+        metadata.last_played = unix_timestamp();
+        metadata.version = SAVE_VERSION;
+
+        Self {
+            level: LevelData {
+                version: SAVE_VERSION,
+                seed: seed.clone(),
+                time: time.0,
+            },
+            metadata,
+            chunks,
+        }
+    }
+
+    /// Writes the level data, the chunks and the metadata to the world folder,
+    /// returns the number of chunks written
+    pub fn write(&self, storage: &WorldStorage) -> Result<usize, Box<dyn std::error::Error>> {
+        storage.save_level(&self.level)?;
+        let count = storage.save_chunks(self.chunks.iter())?;
+
+        // Written last, so that its size accounts for everything else
+        let mut metadata = self.metadata.clone();
+        metadata.size_on_disk = storage.size_on_disk()?;
+        storage.save_metadata(&metadata)?;
+        Ok(count)
+    }
+}
 
-use crate::world::data::SAVE_PATH;
+struct RunningSave {
+    task: Task<Result<usize, String>>,
+    chunks: Vec<IVec3>,
+    requesters: Vec<ClientId>,
+}
 
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct WorldData {
-    pub seed: WorldSeed,
-    pub map: ServerWorldMap,
-    pub time: u64,
+/// Saves run one at a time on the IO task pool, requests made during a save start another one after it.
+/// Chunks generated away from the world, by the pre-generation, are written the same way between
+/// two saves.
+#[derive(Resource, Default)]
+pub struct WorldSaver {
+    running: Option<RunningSave>,
+    queued: bool,
+    queued_backup: bool,
+    queued_requesters: Vec<ClientId>,
+    writing: Option<Task<Result<usize, String>>>,
+}
+
+impl WorldSaver {
+    /// Whether region files are being written, by a save or by `start_write`
+    pub fn is_saving(&self) -> bool {
+        self.running.is_some() || self.writing.is_some()
+    }
+
+    /// Writes serialized chunks which are not part of the world map, unless a save or another
+    /// write is running. Chunks saved in the meantime are kept, since players may have modified
+    /// them. Returns false if the write could not start.
+    pub fn start_write(&mut self, storage: &WorldStorage, chunks: Vec<(IVec3, Vec<u8>)>) -> bool {
+        if self.is_saving() {
+            return false;
+        }
+        let storage = storage.clone();
+        self.writing = Some(IoTaskPool::get().spawn(async move {
+            let mut unsaved = Vec::with_capacity(chunks.len());
+            for (chunk_pos, data) in chunks {
+                if !storage.has_chunk(chunk_pos).map_err(|e| e.to_string())? {
+                    unsaved.push((chunk_pos, data));
+                }
+            }
+            storage
+                .save_serialized_chunks(unsaved)
+                .map_err(|e| e.to_string())
+        }));
+        true
+    }
+
+    /// Outcome of the write started by `start_write` once it is over, with the number of chunks
+    /// written
+    pub fn poll_write(&mut self) -> Option<Result<usize, String>> {
+        let result = block_on(future::poll_once(self.writing.as_mut()?))?;
+        self.writing = None;
+        Some(result)
+    }
+
+    /// Blocks until the running save and write are over, the chunks of a failed save are dirty again
+    fn wait_for_running_save(&mut self, world_map: &mut ServerWorldMap) {
+        if let Some(running) = self.running.take() {
+            if let Err(e) = block_on(running.task) {
+                error!("Failed to save world data: {}", e);
+                world_map.dirty_chunks.extend(running.chunks);
+            }
+        }
+        if let Some(Err(e)) = self.writing.take().map(block_on) {
+            error!("Failed to write generated chunks: {}", e);
+        }
+    }
 }
 
-// System to save the world when "L" is pressed
 pub fn save_world_system(
-    world_map: ResMut<ServerWorldMap>,
+    mut world_map: ResMut<ServerWorldMap>,
     world_seed: Res<WorldSeed>,
-    game_folder_path: Res<GameFolderPaths>,
+    storage: Res<WorldStorage>,
     time: Res<ServerTime>,
+    metadata: Res<WorldMetadata>,
+    mut saver: ResMut<WorldSaver>,
+    mut server: ResMut<RenetServer>,
     mut event: EventReader<SaveRequestEvent>,
 ) {
     // Reads all events to prevent them from being queued forever and repeatedly request a save
-    let mut save_requested = false;
-    for _ in event.read() {
-        save_requested = true;
+    for event in event.read() {
+        saver.queued = true;
+        saver.queued_backup |= event.backup;
+        if let Some(requester) = event.requester {
+            saver.queued_requesters.push(requester);
+        }
     }
 
-    // If a save was requested by the user
-    if save_requested {
-        let world_data = WorldData {
-            map: world_map.clone(),
-            seed: world_seed.clone(),
-            time: time.0,
+    if let Some(running) = saver.running.as_mut() {
+        let Some(result) = block_on(future::poll_once(&mut running.task)) else {
+            return;
+        };
+        let running = saver.running.take().unwrap();
+
+        let response = match result {
+            Ok(count) => {
+                info!(
+                    "World data saved successfully! Name: {}, {} chunks",
+                    world_map.name, count
+                );
+                SaveWorldResponse::Saved { chunks: count }
+            }
+            Err(error) => {
+                error!("Failed to save world data: {}", error);
+                // The chunks have to be written by the next save
+                world_map.dirty_chunks.extend(running.chunks);
+                SaveWorldResponse::Failed { error }
+            }
         };
 
-        // define save file path
-        let save_file_path = format!(
-            "{}{}.ron",
-            get_game_folder(Some(&game_folder_path))
-                .join(SAVE_PATH)
-                .display(),
-            world_map.name
-        );
-
-        // save seed and world data
-        if let Err(e) = save_world_data(&world_data, &save_file_path) {
-            error!("Failed to save world data: {}", e);
-        } else {
-            info!("World data saved successfully! Name: {}", world_map.name);
+        let payload = bincode::options()
+            .serialize(&ServerToClientMessage::SaveWorldResponse(response))
+            .unwrap();
+        for requester in running.requesters {
+            if server.is_connected(requester) {
+                server.send_message(requester, DefaultChannel::ReliableOrdered, payload.clone());
+            }
         }
     }
+
+    // Both rewrite whole region files, running them together could lose chunks
+    if !saver.queued || saver.writing.is_some() {
+        return;
+    }
+
+    let snapshot = WorldSnapshot::take(&mut world_map, &world_seed, &time, &metadata);
+    let chunks = snapshot.chunks.keys().copied().collect();
+    let storage = storage.clone();
+    let backup = saver.queued_backup;
+    let task = IoTaskPool::get().spawn(async move {
+        let count = snapshot.write(&storage).map_err(|e| e.to_string())?;
+        if backup {
+            // A failed backup does not make the save fail
+            match create_backup(&storage) {
+                Ok(name) => info!("Created backup {}", name),
+                Err(e) => error!("Failed to create backup: {}", e),
+            }
+        }
+        Ok(count)
+    });
+
+    let requesters = std::mem::take(&mut saver.queued_requesters);
+    saver.running = Some(RunningSave {
+        task,
+        chunks,
+        requesters,
+    });
+    saver.queued = false;
+    saver.queued_backup = false;
 }
 
-pub fn save_world_data(
-    world_data: &WorldData,
-    file_path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // configure RON serialization
-    let pretty_config = PrettyConfig::new()
-        .with_depth_limit(3)
-        .with_separate_tuple_members(true)
-        .with_enumerate_arrays(true);
-
-    // serialize combined data (map + seed)
-    let serialized = ron::ser::to_string_pretty(world_data, pretty_config)?;
-    let path = Path::new(file_path);
-    let mut file = File::create(path)?;
-    file.write_all(serialized.as_bytes())?;
-    info!("World data saved to {}", file_path);
-    Ok(())
+/// Writes every change to disk before the server stops, waiting for it rather than using a task
+pub fn save_on_exit_system(
+    exit: EventReader<AppExit>,
+    mut world_map: ResMut<ServerWorldMap>,
+    world_seed: Res<WorldSeed>,
+    storage: Res<WorldStorage>,
+    time: Res<ServerTime>,
+    metadata: Res<WorldMetadata>,
+    mut saver: ResMut<WorldSaver>,
+) {
+    if exit.is_empty() {
+        return;
+    }
+
+    saver.wait_for_running_save(&mut world_map);
+    let snapshot = WorldSnapshot::take(&mut world_map, &world_seed, &time, &metadata);
+    match snapshot.write(&storage) {
+        Ok(count) => info!("World saved before shutdown, {} chunks", count),
+        Err(e) => error!("Failed to save world data before shutdown: {}", e),
+    }
 }
@@ -0,0 +1,251 @@
+use crate::world::data::SAVE_PATH;
+use crate::world::generator::{generate_chunk, WorldGenerator};
+use crate::world::region::{
+    contains_chunk, read_chunk, region_file_name, region_pos, write_chunks,
+};
+use bevy::math::IVec3;
+use bevy::prelude::*;
+use bincode::Options;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use shared::world::{
+    get_game_folder, ServerChunk, ServerWorldMap, WorldHeight, WorldMetadata, WorldSeed,
+    METADATA_FILE,
+};
+use shared::GameFolderPaths;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const LEVEL_FILE: &str = "level.ron";
+pub const REGION_DIR: &str = "region";
+
+/// Everything about a world which is not a chunk
+#[derive(Serialize, Deserialize)]
+pub struct LevelData {
+    /// Save format version, see `migrations`
+    pub version: u32,
+    pub seed: WorldSeed,
+    pub time: u64,
+}
+
+/// Location of a world on disk: `saves/<name>/level.ron` and `saves/<name>/region/*.region`
+#[derive(Resource, Debug, Clone)]
+pub struct WorldStorage {
+    pub world_dir: PathBuf,
+}
+
+impl WorldStorage {
+    pub fn new(paths: &GameFolderPaths, world_name: &str) -> Self {
+        Self {
+            world_dir: get_game_folder(Some(paths))
+                .join(SAVE_PATH)
+                .join(world_name),
+        }
+    }
+
+    /// Single-file save of the world, from before region files
+    pub fn legacy_path(&self) -> PathBuf {
+        let name = self.world_dir.file_name().unwrap_or_default();
+        self.world_dir
+            .with_file_name(format!("{}.ron", name.to_string_lossy()))
+    }
+
+    pub fn level_path(&self) -> PathBuf {
+        self.world_dir.join(LEVEL_FILE)
+    }
+
+    fn region_path(&self, region: IVec3) -> PathBuf {
+        self.world_dir
+            .join(REGION_DIR)
+            .join(region_file_name(region))
+    }
+
+    pub fn exists(&self) -> bool {
+        self.level_path().exists()
+    }
+
+    pub fn load_level(&self) -> Result<LevelData, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(self.level_path())?;
+        Ok(ron::de::from_str(&contents)?)
+    }
+
+    pub fn save_level(&self, level: &LevelData) -> Result<(), Box<dyn std::error::Error>> {
+        write_ron(&self.level_path(), level)
+    }
+
+    pub fn metadata_path(&self) -> PathBuf {
+        self.world_dir.join(METADATA_FILE)
+    }
+
+    pub fn load_metadata(&self) -> Result<WorldMetadata, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(self.metadata_path())?;
+        Ok(ron::de::from_str(&contents)?)
+    }
+
+    pub fn save_metadata(
+        &self,
+        metadata: &WorldMetadata,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        write_ron(&self.metadata_path(), metadata)
+    }
+
+    /// Total size of the files of the world, backups included
+    pub fn size_on_disk(&self) -> io::Result<u64> {
+        dir_size(&self.world_dir)
+    }
+
+    /// Reads a chunk from its region file, `None` if it was never saved
+    pub fn load_chunk(
+        &self,
+        chunk_pos: IVec3,
+    ) -> Result<Option<ServerChunk>, Box<dyn std::error::Error>> {
+        match read_chunk(&self.region_path(region_pos(chunk_pos)), chunk_pos)? {
+            Some(data) => Ok(Some(bincode::options().deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a saved chunk, unless it holds terrain nobody modified from an older version of the
+    /// generator, which is generated again instead. Modified chunks are always kept.
+    pub fn load_current_chunk(
+        &self,
+        chunk_pos: IVec3,
+        generator_version: u32,
+    ) -> Result<Option<ServerChunk>, Box<dyn std::error::Error>> {
+        Ok(self.load_chunk(chunk_pos)?.filter(|chunk| {
+            if chunk.generator_version == generator_version {
+                return true;
+            }
+            debug!(
+                "Chunk {:?} was generated by version {} of the generator, now {}: {}",
+                chunk_pos,
+                chunk.generator_version,
+                generator_version,
+                if chunk.modified {
+                    "kept since players modified it"
+                } else {
+                    "generated again"
+                }
+            );
+            chunk.modified
+        }))
+    }
+
+    /// Whether a chunk was ever saved, without reading it
+    pub fn has_chunk(&self, chunk_pos: IVec3) -> io::Result<bool> {
+        contains_chunk(&self.region_path(region_pos(chunk_pos)), chunk_pos)
+    }
+
+    /// Writes chunks back to disk, each touched region file is rewritten once
+    pub fn save_chunks<'a>(
+        &self,
+        chunks: impl IntoIterator<Item = (&'a IVec3, &'a ServerChunk)>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut serialized = Vec::new();
+        for (chunk_pos, chunk) in chunks {
+            serialized.push((*chunk_pos, bincode::options().serialize(chunk)?));
+        }
+        Ok(self.save_serialized_chunks(serialized)?)
+    }
+
+    /// Writes chunks serialized beforehand, each touched region file is rewritten once
+    pub fn save_serialized_chunks(
+        &self,
+        chunks: impl IntoIterator<Item = (IVec3, Vec<u8>)>,
+    ) -> io::Result<usize> {
+        let mut regions: HashMap<IVec3, Vec<(IVec3, Vec<u8>)>> = HashMap::new();
+        let mut count = 0;
+        for (chunk_pos, data) in chunks {
+            regions
+                .entry(region_pos(chunk_pos))
+                .or_default()
+                .push((chunk_pos, data));
+            count += 1;
+        }
+
+        fs::create_dir_all(self.world_dir.join(REGION_DIR))?;
+        for (region, chunks) in regions {
+            write_chunks(&self.region_path(region), &chunks)?;
+        }
+        Ok(count)
+    }
+}
+
+/// Writes to a temporary file first, so that a crash never leaves a truncated file behind
+pub(crate) fn write_ron<T: Serialize>(
+    path: &Path,
+    value: &T,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let serialized = ron::ser::to_string_pretty(value, PrettyConfig::new())?;
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, serialized)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+/// Makes sure a chunk is in memory if it was ever saved, `None` if it has to be generated
+pub fn load_chunk<'a>(
+    world_map: &'a mut ServerWorldMap,
+    storage: &WorldStorage,
+    generator_version: u32,
+    chunk_pos: IVec3,
+) -> Result<Option<&'a ServerChunk>, Box<dyn std::error::Error>> {
+    match world_map.map.entry(chunk_pos) {
+        Entry::Occupied(entry) => Ok(Some(entry.into_mut())),
+        Entry::Vacant(entry) => Ok(storage
+            .load_current_chunk(chunk_pos, generator_version)?
+            .map(|chunk| &*entry.insert(chunk))),
+    }
+}
+
+/// Makes sure a chunk is in memory, reading it from disk or generating it when it was never saved.
+/// Empty generated chunks are only remembered as such. A chunk which fails to load is left alone,
+/// so that the next save does not overwrite it.
+pub fn load_or_generate_chunk<'a>(
+    world_map: &'a mut ServerWorldMap,
+    storage: &WorldStorage,
+    seed: &WorldSeed,
+    generator: &dyn WorldGenerator,
+    height: WorldHeight,
+    chunk_pos: IVec3,
+) -> Result<Option<&'a ServerChunk>, Box<dyn std::error::Error>> {
+    if world_map.empty_chunks.contains(&chunk_pos) {
+        return Ok(None);
+    }
+    match world_map.map.entry(chunk_pos) {
+        Entry::Occupied(entry) => Ok(Some(entry.into_mut())),
+        Entry::Vacant(entry) => {
+            let chunk = match storage.load_current_chunk(chunk_pos, generator.version())? {
+                Some(chunk) => chunk,
+                None => generate_chunk(generator, chunk_pos, seed.0, height),
+            };
+
+            if chunk.map.is_empty() {
+                world_map.empty_chunks.insert(chunk_pos);
+                return Ok(None);
+            }
+            Ok(Some(entry.insert(chunk)))
+        }
+    }
+}
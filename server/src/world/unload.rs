@@ -0,0 +1,208 @@
+use crate::init::ServerLobby;
+use crate::network::broadcast_world::WorldUpdateRequestEvent;
+use crate::world::save::WorldSaver;
+use crate::world::storage::WorldStorage;
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use bevy_renet::renet::ClientId;
+use shared::world::{chunk_in_radius, BlockData, ServerChunk, ServerWorldMap};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Chunks this far beyond a player's render distance stay loaded, so walking back does not reload them
+pub const VIEW_MARGIN: i32 = 2;
+/// How long a chunk out of every player's view stays in memory
+pub const UNLOAD_GRACE_PERIOD: Duration = Duration::from_secs(30);
+const UNLOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const DEFAULT_CHUNK_MEMORY_BUDGET_MB: usize = 512;
+/// Rough memory used by a block in a chunk, hash map overhead included
+const BLOCK_MEMORY_SIZE: usize = size_of::<IVec3>() + size_of::<BlockData>() + 8;
+
+pub const LOADED_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("chunks/loaded");
+pub const DIRTY_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("chunks/dirty");
+pub const EVICTED_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("chunks/evicted");
+pub const CHUNK_MEMORY: DiagnosticPath = DiagnosticPath::const_new("chunks/memory_mb");
+
+fn chunk_memory(chunk: &ServerChunk) -> usize {
+    size_of::<ServerChunk>() + chunk.map.capacity() * BLOCK_MEMORY_SIZE
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PlayerView {
+    chunk_position: IVec3,
+    radius: i32,
+    vertical_radius: i32,
+}
+
+/// Decides which chunks can leave memory: those out of every player's view for a while,
+/// or sooner when the memory budget is exceeded
+#[derive(Resource, Debug)]
+pub struct ChunkUnloader {
+    /// Memory budget for loaded chunks, in bytes
+    pub memory_budget: usize,
+    /// Chunks evicted since the server started
+    pub evicted: u64,
+    views: HashMap<ClientId, PlayerView>,
+    /// Time at which each loaded chunk left the last view it was in
+    unwatched_since: HashMap<IVec3, Duration>,
+    timer: Timer,
+}
+
+impl ChunkUnloader {
+    pub fn new(memory_budget_mb: usize) -> Self {
+        Self {
+            memory_budget: memory_budget_mb * 1024 * 1024,
+            evicted: 0,
+            views: HashMap::new(),
+            unwatched_since: HashMap::new(),
+            timer: Timer::new(UNLOAD_INTERVAL, TimerMode::Repeating),
+        }
+    }
+
+    fn is_watched(&self, chunk_pos: &IVec3) -> bool {
+        self.views.values().any(|view| {
+            chunk_in_radius(
+                &view.chunk_position,
+                chunk_pos,
+                view.radius,
+                view.vertical_radius,
+            )
+        })
+    }
+}
+
+pub fn setup_chunk_unloading(app: &mut App, memory_budget_mb: Option<usize>) {
+    app.insert_resource(ChunkUnloader::new(
+        memory_budget_mb.unwrap_or(DEFAULT_CHUNK_MEMORY_BUDGET_MB),
+    ));
+    app.register_diagnostic(Diagnostic::new(LOADED_CHUNKS))
+        .register_diagnostic(Diagnostic::new(DIRTY_CHUNKS))
+        .register_diagnostic(Diagnostic::new(EVICTED_CHUNKS))
+        .register_diagnostic(Diagnostic::new(CHUNK_MEMORY).with_suffix(" MB"));
+    app.add_systems(
+        Update,
+        (track_player_views_system, unload_chunks_system).chain(),
+    );
+}
+
+/// Players tell which chunks they see each time they request an update
+fn track_player_views_system(
+    mut events: EventReader<WorldUpdateRequestEvent>,
+    mut unloader: ResMut<ChunkUnloader>,
+) {
+    for event in events.read() {
+        unloader.views.insert(
+            event.client,
+            PlayerView {
+                chunk_position: event.player_chunk_position,
+                radius: event.render_distance as i32 + VIEW_MARGIN,
+                vertical_radius: event.vertical_render_distance as i32 + VIEW_MARGIN,
+            },
+        );
+    }
+}
+
+fn unload_chunks_system(
+    mut world_map: ResMut<ServerWorldMap>,
+    storage: Res<WorldStorage>,
+    lobby: Res<ServerLobby>,
+    saver: Res<WorldSaver>,
+    mut unloader: ResMut<ChunkUnloader>,
+    time: Res<Time>,
+    mut diagnostics: Diagnostics,
+) {
+    unloader.timer.tick(time.delta());
+    if !unloader.timer.finished() {
+        return;
+    }
+
+    let now = time.elapsed();
+    let unloader = &mut *unloader;
+    unloader
+        .views
+        .retain(|client_id, _| lobby.players.contains_key(&client_id.raw()));
+
+    let mut unwatched_since = std::mem::take(&mut unloader.unwatched_since);
+    unwatched_since.retain(|pos, _| world_map.map.contains_key(pos));
+    for pos in world_map.map.keys() {
+        if unloader.is_watched(pos) {
+            unwatched_since.remove(pos);
+        } else {
+            unwatched_since.entry(*pos).or_insert(now);
+        }
+    }
+
+    // Empty chunks only cost their position, they are forgotten once nobody sees them
+    world_map
+        .empty_chunks
+        .retain(|pos| unloader.is_watched(pos));
+
+    let memory: usize = world_map.map.values().map(chunk_memory).sum();
+
+    // Chunks out of view for the longest time go first
+    let mut candidates: Vec<(IVec3, Duration)> =
+        unwatched_since.iter().map(|(pos, t)| (*pos, *t)).collect();
+    candidates.sort_by_key(|(_, since)| *since);
+
+    let mut to_evict = Vec::new();
+    let mut freed = 0;
+    for (pos, since) in candidates {
+        let over_budget = memory - freed > unloader.memory_budget;
+        if now - since < UNLOAD_GRACE_PERIOD && !over_budget {
+            break;
+        }
+        freed += chunk_memory(&world_map.map[&pos]);
+        to_evict.push(pos);
+    }
+
+    if memory - freed > unloader.memory_budget {
+        debug!(
+            "Chunk memory over budget ({} MB), every remaining chunk is in view",
+            (memory - freed) / (1024 * 1024)
+        );
+    }
+
+    // A save in progress may be rewriting the region files of the evicted chunks
+    if saver.is_saving() {
+        to_evict.clear();
+        freed = 0;
+    }
+
+    if !to_evict.is_empty() {
+        // Dirty chunks are written back before leaving memory
+        let dirty = to_evict
+            .iter()
+            .filter(|pos| world_map.dirty_chunks.contains(pos))
+            .filter_map(|pos| world_map.map.get_key_value(pos));
+
+        match storage.save_chunks(dirty) {
+            Ok(saved) => {
+                for pos in &to_evict {
+                    world_map.map.remove(pos);
+                    world_map.dirty_chunks.remove(pos);
+                    unwatched_since.remove(pos);
+                }
+                unloader.evicted += to_evict.len() as u64;
+                debug!(
+                    "Unloaded {} chunks, {} of them saved",
+                    to_evict.len(),
+                    saved
+                );
+            }
+            Err(e) => {
+                error!("Failed to save chunks before unloading them: {}", e);
+                freed = 0;
+            }
+        }
+    }
+
+    unloader.unwatched_since = unwatched_since;
+
+    diagnostics.add_measurement(&LOADED_CHUNKS, || world_map.map.len() as f64);
+    diagnostics.add_measurement(&DIRTY_CHUNKS, || world_map.dirty_chunks.len() as f64);
+    diagnostics.add_measurement(&EVICTED_CHUNKS, || unloader.evicted as f64);
+    diagnostics.add_measurement(&CHUNK_MEMORY, || {
+        (memory - freed) as f64 / (1024.0 * 1024.0)
+    });
+}
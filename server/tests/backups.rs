@@ -0,0 +1,99 @@
+//! Backups of a world, and restoring them.
+
+use bevy::math::IVec3;
+use server::{backup_due, create_backup, list_backups, restore_backup, LevelData, WorldStorage};
+use shared::world::{BlockData, BlockDirection, BlockId, ServerChunk, WorldMetadata, WorldSeed};
+use std::fs;
+
+fn world(test: &str) -> WorldStorage {
+    let dir = std::env::temp_dir().join(format!(
+        "rustcraft-backup-tests-{}-{}",
+        std::process::id(),
+        test
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    WorldStorage { world_dir: dir }
+}
+
+/// Saves a world whose state is told apart by `time`, with a chunk holding `block`
+fn save(storage: &WorldStorage, time: u64, block: BlockId) {
+    storage
+        .save_level(&LevelData {
+            version: server::SAVE_VERSION,
+            seed: WorldSeed(1),
+            time,
+        })
+        .unwrap();
+    let mut metadata = WorldMetadata::new("World".into(), 1, server::SAVE_VERSION);
+    metadata.playtime = time;
+    storage.save_metadata(&metadata).unwrap();
+
+    let mut chunk = ServerChunk {
+        modified: true,
+        ..Default::default()
+    };
+    chunk.map.insert(
+        IVec3::ZERO,
+        BlockData::new(block, false, BlockDirection::Front),
+    );
+    storage.save_chunks([(&IVec3::ZERO, &chunk)]).unwrap();
+}
+
+fn block(storage: &WorldStorage) -> BlockId {
+    storage.load_chunk(IVec3::ZERO).unwrap().unwrap().map[&IVec3::ZERO].id
+}
+
+#[test]
+fn backups_hold_the_level_metadata_and_regions() {
+    let storage = world("create");
+    save(&storage, 10, BlockId::Stone);
+    assert!(backup_due(&storage).unwrap());
+
+    let name = create_backup(&storage).unwrap();
+    assert_eq!(list_backups(&storage).unwrap(), vec![name.clone()]);
+    assert!(!backup_due(&storage).unwrap());
+
+    let backup = WorldStorage {
+        world_dir: storage.world_dir.join("backups").join(&name),
+    };
+    assert_eq!(backup.load_level().unwrap().time, 10);
+    assert_eq!(backup.load_metadata().unwrap().playtime, 10);
+    assert_eq!(block(&backup), BlockId::Stone);
+
+    fs::remove_dir_all(&storage.world_dir).unwrap();
+}
+
+#[test]
+fn restoring_keeps_a_backup_of_the_replaced_world() {
+    let storage = world("restore");
+    save(&storage, 10, BlockId::Stone);
+    let old = create_backup(&storage).unwrap();
+    save(&storage, 20, BlockId::Dirt);
+
+    // Backups are named after the second they were made
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let replaced = restore_backup(&storage, &old).unwrap();
+    assert_eq!(storage.load_level().unwrap().time, 10);
+    assert_eq!(storage.load_metadata().unwrap().playtime, 10);
+    assert_eq!(block(&storage), BlockId::Stone);
+
+    let replaced = WorldStorage {
+        world_dir: storage.world_dir.join("backups").join(replaced),
+    };
+    assert_eq!(replaced.load_level().unwrap().time, 20);
+    assert_eq!(block(&replaced), BlockId::Dirt);
+
+    // Nothing is left from the swap
+    let mut entries: Vec<String> = fs::read_dir(&storage.world_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    entries.sort();
+    assert_eq!(
+        entries,
+        vec!["backups", "level.ron", "metadata.ron", "region"]
+    );
+
+    assert!(restore_backup(&storage, "../region").is_err());
+    fs::remove_dir_all(&storage.world_dir).unwrap();
+}
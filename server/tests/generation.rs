@@ -0,0 +1,489 @@
+//! Generates chunks of a fixed seed and compares them with hashes of the terrain they had
+//! when the test was written. A failure means worlds are no longer generated the same way:
+//! either fix the generator, or bump `GENERATOR_VERSION` and update the hashes.
+
+use bevy::math::IVec3;
+use server::{generate_chunk, GeneratorRegistry, TerrainPreset, GENERATOR_VERSION, SEA_LEVEL};
+use shared::world::{
+    BlockId, GeneratorPreset, GeneratorSettings, ServerChunk, WorldHeight, DEFAULT_GENERATOR,
+};
+use shared::CHUNK_SIZE;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const SEED: u32 = 1234;
+
+fn generate(chunk_pos: IVec3, seed: u32, preset: GeneratorPreset) -> ServerChunk {
+    let generator = GeneratorRegistry::default()
+        .create(&preset.settings())
+        .unwrap();
+    generate_chunk(&*generator, chunk_pos, seed, WorldHeight::default())
+}
+
+/// Hash of the blocks of a chunk, independent of the order of its map and of its timestamp
+fn chunk_hash(chunk: &ServerChunk) -> u64 {
+    let mut blocks: Vec<_> = chunk.map.iter().collect();
+    blocks.sort_by_key(|(pos, _)| (pos.x, pos.y, pos.z));
+    let bytes = bincode::serialize(&blocks).unwrap();
+
+    // 64 bits FNV-1a, which unlike the std hashers is guaranteed to stay the same
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[test]
+fn same_seed_generates_the_same_chunks() {
+    for x in -2..2 {
+        for z in -2..2 {
+            let pos = IVec3::new(x, 4, z);
+            let first = generate(pos, SEED, GeneratorPreset::Default);
+            let second = generate(pos, SEED, GeneratorPreset::Default);
+            assert_eq!(first.map, second.map, "chunk {} differs", pos);
+        }
+    }
+}
+
+#[test]
+fn other_seed_generates_other_chunks() {
+    let pos = IVec3::new(0, 4, 0);
+    let first = generate(pos, SEED, GeneratorPreset::Default);
+    let second = generate(pos, SEED + 1, GeneratorPreset::Default);
+    assert_ne!(chunk_hash(&first), chunk_hash(&second));
+}
+
+#[test]
+fn trees_are_not_cut_at_chunk_borders() {
+    // blocks of several chunks around the surface, in world coordinates
+    let mut world = HashMap::new();
+    for x in -3..=3 {
+        for y in 3..7 {
+            for z in -3..=3 {
+                let chunk_pos = IVec3::new(x, y, z);
+                let chunk = generate(chunk_pos, SEED, GeneratorPreset::Default);
+                for (pos, block) in chunk.map {
+                    world.insert(chunk_pos * CHUNK_SIZE + pos, block.id);
+                }
+            }
+        }
+    }
+
+    let mut border_trees = 0;
+    for (pos, block) in &world {
+        let above = *pos + IVec3::Y;
+        if *block != BlockId::OakLog || world.get(&above) == Some(&BlockId::OakLog) {
+            continue;
+        }
+
+        // trees at the edge of the area may overhang chunks which were not generated
+        let chunk_pos = pos.div_euclid(IVec3::splat(CHUNK_SIZE));
+        if chunk_pos.x.abs() > 2 || chunk_pos.z.abs() > 2 {
+            continue;
+        }
+
+        // the leaves of trees growing side by side mix
+        let crowded = (-2..=2).any(|dx| {
+            (-2..=2).any(|dz| {
+                (-6..=6).any(|dy| {
+                    (dx, dz) != (0, 0)
+                        && world.get(&(*pos + IVec3::new(dx, dy, dz))) == Some(&BlockId::OakLog)
+                })
+            })
+        });
+        if crowded {
+            continue;
+        }
+
+        // the top of every trunk is surrounded by leaves, even in the neighbouring chunks
+        assert_eq!(
+            world.get(&above),
+            Some(&BlockId::OakLeaves),
+            "tree at {}",
+            pos
+        );
+        for side in [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z] {
+            assert!(world.contains_key(&(*pos + side)), "tree at {}", pos);
+        }
+
+        let local = pos.rem_euclid(IVec3::splat(CHUNK_SIZE));
+        // leaves spill over a side, or the top leaf is in the chunk above
+        if local.x == 0
+            || local.x == CHUNK_SIZE - 1
+            || local.z == 0
+            || local.z == CHUNK_SIZE - 1
+            || local.y == CHUNK_SIZE - 1
+        {
+            border_trees += 1;
+        }
+    }
+    assert!(border_trees > 0, "no tree crosses a chunk border");
+}
+
+#[test]
+fn spruces_are_conical() {
+    // a taiga for this seed
+    let mut world = HashMap::new();
+    for x in 3..9 {
+        for y in 4..6 {
+            for z in 0..5 {
+                let chunk_pos = IVec3::new(x, y, z);
+                let chunk = generate(chunk_pos, SEED, GeneratorPreset::Default);
+                for (pos, block) in chunk.map {
+                    world.insert(chunk_pos * CHUNK_SIZE + pos, block.id);
+                }
+            }
+        }
+    }
+    let block_at = |pos: IVec3| world.get(&pos).copied();
+
+    let mut spruces = 0;
+    for (pos, block) in &world {
+        if *block != BlockId::SpruceLog || block_at(*pos + IVec3::Y) == Some(BlockId::SpruceLog) {
+            continue;
+        }
+        // the leaves of trees growing side by side mix
+        let crowded = (-4..=4).any(|dx| {
+            (-4..=4).any(|dz| {
+                (-9..=9).any(|dy| {
+                    (dx, dz) != (0, 0)
+                        && block_at(*pos + IVec3::new(dx, dy, dz)) == Some(BlockId::SpruceLog)
+                })
+            })
+        });
+        // trees at the edge of the area may overhang chunks which were not generated
+        let inside = (3 * CHUNK_SIZE + 2..9 * CHUNK_SIZE - 2).contains(&pos.x)
+            && (2..5 * CHUNK_SIZE - 2).contains(&pos.z);
+        if crowded || !inside {
+            continue;
+        }
+        spruces += 1;
+
+        // a narrow top, with a leaf above the trunk and none in the corners
+        assert_eq!(block_at(*pos + IVec3::Y), Some(BlockId::SpruceLeaves));
+        assert_eq!(block_at(*pos + IVec3::X), Some(BlockId::SpruceLeaves));
+        assert_ne!(
+            block_at(*pos + IVec3::new(1, 0, 1)),
+            Some(BlockId::SpruceLeaves)
+        );
+        // wider layers below
+        assert!(
+            (1..6).any(|dy| block_at(*pos + IVec3::new(2, -dy, 0)) == Some(BlockId::SpruceLeaves)),
+            "spruce at {}",
+            pos
+        );
+    }
+    assert!(spruces > 0, "no spruce generated");
+}
+
+#[test]
+fn caves_carve_the_underground_but_not_the_bedrock() {
+    let mut carved = 0;
+    for x in -2..2 {
+        for z in -2..2 {
+            let bottom = generate(IVec3::new(x, 0, z), SEED, GeneratorPreset::Default);
+            let bedrock = bottom
+                .map
+                .iter()
+                .filter(|(pos, block)| pos.y == 0 && block.id == BlockId::Bedrock)
+                .count();
+            assert_eq!(bedrock as i32, CHUNK_SIZE * CHUNK_SIZE);
+
+            // always underground, whatever the biome
+            for y in 1..3 {
+                let chunk = generate(IVec3::new(x, y, z), SEED, GeneratorPreset::Default);
+                carved += CHUNK_SIZE.pow(3) as usize - chunk.map.len();
+            }
+        }
+    }
+
+    let underground = 2 * 4 * 4 * CHUNK_SIZE.pow(3) as usize;
+    assert!(carved > 0, "no cave was carved");
+    assert!(
+        carved < underground / 4,
+        "{} blocks out of {} carved",
+        carved,
+        underground
+    );
+}
+
+#[test]
+fn ores_are_spread_by_height() {
+    let mut ores = HashMap::new();
+    for x in -2..2 {
+        for y in 0..4 {
+            for z in -2..2 {
+                let chunk_pos = IVec3::new(x, y, z);
+                let chunk = generate(chunk_pos, SEED, GeneratorPreset::Default);
+                for (pos, block) in chunk.map {
+                    let world_y = chunk_pos.y * CHUNK_SIZE + pos.y;
+                    let (count, highest) = ores.entry(block.id).or_insert((0, world_y));
+                    *count += 1;
+                    *highest = world_y.max(*highest);
+                }
+            }
+        }
+    }
+
+    for ore in [
+        BlockId::CoalOre,
+        BlockId::IronOre,
+        BlockId::GoldOre,
+        BlockId::DiamondOre,
+    ] {
+        assert!(ores.contains_key(&ore), "no {:?} generated", ore);
+    }
+    assert!(ores[&BlockId::CoalOre].0 > ores[&BlockId::DiamondOre].0);
+    // a diamond vein starts at most at height 16 and grows 3 blocks around
+    assert!(ores[&BlockId::DiamondOre].1 <= 19);
+}
+
+#[test]
+fn low_terrain_fills_with_water_up_to_sea_level() {
+    // an ocean and its shore for this seed
+    let mut world = HashMap::new();
+    for x in -40..-34 {
+        for y in 2..5 {
+            for z in -11..-8 {
+                let chunk_pos = IVec3::new(x, y, z);
+                let chunk = generate(chunk_pos, SEED, GeneratorPreset::Default);
+                for (pos, block) in chunk.map {
+                    world.insert(chunk_pos * CHUNK_SIZE + pos, block.id);
+                }
+            }
+        }
+    }
+
+    let mut water = 0;
+    for (pos, block) in &world {
+        if *block != BlockId::Water {
+            continue;
+        }
+        water += 1;
+        assert!(pos.y <= SEA_LEVEL, "water above the sea at {}", pos);
+        // water lies on the sand of the sea floor
+        let below = world.get(&(*pos - IVec3::Y));
+        assert!(
+            matches!(below, Some(BlockId::Water | BlockId::Sand)),
+            "{:?} under the water at {}",
+            below,
+            pos
+        );
+    }
+    assert!(water > 0, "no water generated");
+}
+
+#[test]
+fn chunks_match_golden_hashes() {
+    assert_eq!(
+        GENERATOR_VERSION, 7,
+        "update the hashes for the new version"
+    );
+
+    let golden = [
+        (
+            IVec3::new(0, 4, 0),
+            GeneratorPreset::Default,
+            14773832609546117894,
+        ),
+        (
+            IVec3::new(3, 4, -5),
+            GeneratorPreset::Default,
+            9460144758125089464,
+        ),
+        (
+            IVec3::new(9, 4, 4),
+            GeneratorPreset::Default,
+            6881379194694932552,
+        ),
+        (
+            IVec3::new(-6, 4, 8),
+            GeneratorPreset::Default,
+            3108807181772619630,
+        ),
+        (
+            IVec3::new(0, 0, 0),
+            GeneratorPreset::Default,
+            9855824744145387821,
+        ),
+        (
+            IVec3::new(0, 4, 0),
+            GeneratorPreset::Flat,
+            2374805170554931306,
+        ),
+    ];
+    for (pos, preset, hash) in golden {
+        let chunk = generate(pos, SEED, preset);
+        assert_eq!(
+            chunk_hash(&chunk),
+            hash,
+            "chunk {} of the {:?} preset",
+            pos,
+            preset
+        );
+    }
+}
+
+#[test]
+fn shipped_preset_is_the_built_in_one() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../data/generator_presets/default.ron");
+    let options = fs::read_to_string(path).unwrap();
+    assert_eq!(
+        TerrainPreset::from_options(&options).unwrap(),
+        TerrainPreset::default()
+    );
+
+    let generator = GeneratorRegistry::default()
+        .create(&GeneratorSettings {
+            name: DEFAULT_GENERATOR.into(),
+            options,
+        })
+        .unwrap();
+    let pos = IVec3::new(3, 4, -5);
+    assert_eq!(
+        generate_chunk(&*generator, pos, SEED, WorldHeight::default()).map,
+        generate(pos, SEED, GeneratorPreset::Default).map
+    );
+}
+
+#[test]
+fn presets_change_the_terrain() {
+    let generator = GeneratorRegistry::default()
+        .create(&GeneratorSettings {
+            name: DEFAULT_GENERATOR.into(),
+            options: "(sea_level: 90)".into(),
+        })
+        .unwrap();
+
+    // the ground is under water, which leaves no room for caves nor trees
+    let chunk = generate_chunk(
+        &*generator,
+        IVec3::new(0, 4, 0),
+        SEED,
+        WorldHeight::default(),
+    );
+    assert_eq!(chunk.map.len(), CHUNK_SIZE.pow(3) as usize);
+    assert_eq!(chunk.map[&IVec3::new(0, 15, 0)].id, BlockId::Water);
+    assert_eq!(chunk.map[&IVec3::new(15, 15, 15)].id, BlockId::Water);
+}
+
+#[test]
+fn presets_need_a_biome_for_every_climate() {
+    let settings = GeneratorSettings {
+        name: DEFAULT_GENERATOR.into(),
+        options: "(biome_rules: [(biome: Plains, humidity: (above: 0.5))])".into(),
+    };
+    assert!(GeneratorRegistry::default().create(&settings).is_err());
+}
+
+/// Checks the bottom layer of a world is bedrock in every column, with nothing under it
+fn assert_bedrock_floor(world: WorldHeight) {
+    let generator = GeneratorRegistry::default()
+        .create(&GeneratorPreset::Default.settings())
+        .unwrap();
+    let generate = |chunk_pos| generate_chunk(&*generator, chunk_pos, SEED, world);
+    let layer = world.min.div_euclid(CHUNK_SIZE);
+    let floor = world.min.rem_euclid(CHUNK_SIZE);
+
+    for x in -2..2 {
+        for z in -2..2 {
+            let bottom = generate(IVec3::new(x, layer, z));
+            assert!((0..CHUNK_SIZE).all(|dx| (0..CHUNK_SIZE)
+                .all(|dz| { bottom.map[&IVec3::new(dx, floor, dz)].id == BlockId::Bedrock })));
+            assert!(bottom
+                .map
+                .iter()
+                .all(|(pos, block)| pos.y >= floor
+                    && (pos.y == floor) == (block.id == BlockId::Bedrock)));
+
+            assert!(generate(IVec3::new(x, layer - 1, z)).map.is_empty());
+        }
+    }
+}
+
+#[test]
+fn deep_worlds_have_bedrock_at_their_bottom() {
+    let world = WorldHeight::new(-64, 320).unwrap();
+    assert_bedrock_floor(world);
+
+    let generator = GeneratorRegistry::default()
+        .create(&GeneratorPreset::Default.settings())
+        .unwrap();
+    assert!(
+        generate_chunk(&*generator, IVec3::new(0, 20, 0), SEED, world)
+            .map
+            .is_empty()
+    );
+}
+
+#[test]
+fn caves_do_not_break_through_a_raised_floor() {
+    // caves cross this height in worlds starting at 0
+    let mut carved = false;
+    for x in -2..2 {
+        for z in -2..2 {
+            let chunk = generate(IVec3::new(x, 1, z), SEED, GeneratorPreset::Default);
+            carved |= (0..CHUNK_SIZE).any(|dx| {
+                (0..CHUNK_SIZE).any(|dz| !chunk.map.contains_key(&IVec3::new(dx, 4, dz)))
+            });
+        }
+    }
+    assert!(carved);
+
+    assert_bedrock_floor(WorldHeight::new(20, 320).unwrap());
+}
+
+#[test]
+fn deep_worlds_have_ores_and_caves_below_zero() {
+    let generator = GeneratorRegistry::default()
+        .create(&GeneratorPreset::Default.settings())
+        .unwrap();
+    let world = WorldHeight::new(-64, 320).unwrap();
+
+    let mut ores = HashMap::new();
+    let mut carved = 0;
+    for x in -2..2 {
+        for z in -2..2 {
+            for y in -4..0 {
+                let chunk = generate_chunk(&*generator, IVec3::new(x, y, z), SEED, world);
+                carved += CHUNK_SIZE.pow(3) as usize - chunk.map.len();
+                for block in chunk.map.values() {
+                    *ores.entry(block.id).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    assert!(carved > 0);
+    for ore in [
+        BlockId::CoalOre,
+        BlockId::IronOre,
+        BlockId::GoldOre,
+        BlockId::DiamondOre,
+    ] {
+        assert!(ores.get(&ore).copied().unwrap_or(0) > 0, "no {:?}", ore);
+    }
+}
+
+#[test]
+fn blocks_above_the_world_are_not_generated() {
+    let generator = GeneratorRegistry::default()
+        .create(&GeneratorPreset::Default.settings())
+        .unwrap();
+    // the top of the world cuts through the terrain
+    let world = WorldHeight::new(0, 40).unwrap();
+    let full = generate_chunk(
+        &*generator,
+        IVec3::new(0, 2, 0),
+        SEED,
+        WorldHeight::default(),
+    );
+    let cut = generate_chunk(&*generator, IVec3::new(0, 2, 0), SEED, world);
+
+    assert!(full.map.keys().any(|pos| pos.y >= 8));
+    assert!(!cut.map.is_empty());
+    assert!(cut.map.keys().all(|pos| pos.y < 8));
+    assert!(cut.map.iter().all(|(pos, block)| full.map[pos] == *block));
+}
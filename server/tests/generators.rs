@@ -0,0 +1,240 @@
+//! Generators other than the default one, and the registry worlds pick them from.
+
+use bevy::math::{IVec3, Vec3};
+use server::{
+    generate_chunk, DebugGenerator, GeneratorError, GeneratorRegistry, WorldGenerator, WorldStorage,
+};
+use shared::world::{
+    BlockData, BlockDirection, BlockId, GeneratorPreset, GeneratorSettings, WorldHeight,
+    FLAT_GENERATOR,
+};
+use shared::CHUNK_SIZE;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+fn create(settings: &GeneratorSettings) -> std::sync::Arc<dyn WorldGenerator> {
+    GeneratorRegistry::default().create(settings).unwrap()
+}
+
+/// Blocks of the column at `(x, z)`, from the bottom of the world up to `top`
+fn column(
+    generator: &dyn WorldGenerator,
+    world: WorldHeight,
+    x: i32,
+    z: i32,
+    top: i32,
+) -> Vec<Option<BlockId>> {
+    (world.min..top)
+        .map(|y| {
+            let pos = IVec3::new(x, y, z);
+            let chunk_pos = pos.div_euclid(IVec3::splat(CHUNK_SIZE));
+            let chunk = generate_chunk(generator, chunk_pos, 0, world);
+            chunk
+                .map
+                .get(&pos.rem_euclid(IVec3::splat(CHUNK_SIZE)))
+                .map(|block| block.id)
+        })
+        .collect()
+}
+
+#[test]
+fn flat_worlds_stack_their_layers() {
+    let generator = create(&GeneratorSettings {
+        name: FLAT_GENERATOR.into(),
+        options: "(layers: [(block: Bedrock, thickness: 1), (block: Sand, thickness: 20)])".into(),
+    });
+
+    let blocks = column(&*generator, WorldHeight::default(), 5, -40, 40);
+    assert_eq!(blocks[0], Some(BlockId::Bedrock));
+    assert!(blocks[1..21]
+        .iter()
+        .all(|block| *block == Some(BlockId::Sand)));
+    assert!(blocks[21..].iter().all(|block| block.is_none()));
+}
+
+#[test]
+fn flat_worlds_default_to_grass_at_height_64() {
+    let generator = create(&GeneratorPreset::Flat.settings());
+
+    let blocks = column(&*generator, WorldHeight::default(), 0, 0, 80);
+    assert_eq!(blocks[0], Some(BlockId::Bedrock));
+    assert_eq!(blocks[60], Some(BlockId::Stone));
+    assert_eq!(blocks[63], Some(BlockId::Dirt));
+    assert_eq!(blocks[64], Some(BlockId::Grass));
+    assert_eq!(blocks[65], None);
+}
+
+#[test]
+fn flat_worlds_start_at_the_bottom_of_the_world() {
+    let generator = create(&GeneratorPreset::Flat.settings());
+    let world = WorldHeight::new(-64, 320).unwrap();
+
+    let blocks = column(&*generator, world, 0, 0, 16);
+    assert_eq!(blocks[0], Some(BlockId::Bedrock));
+    assert_eq!(blocks[64], Some(BlockId::Grass));
+    assert_eq!(blocks[65], None);
+    assert!(generate_chunk(&*generator, IVec3::new(0, -5, 0), 0, world)
+        .map
+        .is_empty());
+    assert!(generator.spawn_point(0, world).y > 0.);
+}
+
+#[test]
+fn platforms_stay_within_low_worlds() {
+    let world = WorldHeight::new(0, 32).unwrap();
+
+    let void = create(&GeneratorPreset::Void.settings());
+    let blocks = column(&*void, world, 0, 0, 32);
+    assert_eq!(blocks[31], Some(BlockId::Stone));
+    assert_eq!(blocks.iter().flatten().count(), 1);
+
+    let debug = create(&GeneratorPreset::Debug.settings());
+    let pos = DebugGenerator::block_position(0, world);
+    assert_eq!(pos.y, 31);
+    let chunk = generate_chunk(&*debug, pos.div_euclid(IVec3::splat(CHUNK_SIZE)), 0, world);
+    assert_eq!(
+        chunk.map[&pos.rem_euclid(IVec3::splat(CHUNK_SIZE))].id,
+        BlockId::ALL[0]
+    );
+}
+
+#[test]
+fn wrong_options_are_an_error() {
+    let settings = GeneratorSettings {
+        name: FLAT_GENERATOR.into(),
+        options: "(layers: [(block: NotABlock, thickness: 1)])".into(),
+    };
+    assert!(matches!(
+        GeneratorRegistry::default().create(&settings),
+        Err(GeneratorError::InvalidOptions { .. })
+    ));
+}
+
+#[test]
+fn void_worlds_only_have_a_platform_under_the_spawn() {
+    let generator = create(&GeneratorPreset::Void.settings());
+    let world = WorldHeight::default();
+    let spawn = generator.spawn_point(0, world);
+
+    let under_spawn = column(
+        &*generator,
+        world,
+        spawn.x as i32,
+        spawn.z as i32,
+        spawn.y as i32,
+    );
+    assert_eq!(under_spawn.iter().flatten().count(), 1);
+    assert!(generate_chunk(&*generator, IVec3::new(3, 4, 3), 0, world)
+        .map
+        .is_empty());
+}
+
+#[test]
+fn debug_worlds_lay_out_every_block() {
+    let generator = create(&GeneratorPreset::Debug.settings());
+
+    let mut positions = HashSet::new();
+    for (index, block) in BlockId::ALL.iter().enumerate() {
+        let pos = DebugGenerator::block_position(index, WorldHeight::default());
+        let chunk_pos = pos.div_euclid(IVec3::splat(CHUNK_SIZE));
+        let chunk = generate_chunk(&*generator, chunk_pos, 0, WorldHeight::default());
+        let local_pos = pos.rem_euclid(IVec3::splat(CHUNK_SIZE));
+        assert_eq!(chunk.map[&local_pos].id, *block);
+        positions.insert(pos);
+    }
+    assert_eq!(positions.len(), BlockId::ALL.len());
+}
+
+/// Water everywhere below a given height
+struct WaterWorld {
+    height: i32,
+}
+
+impl WorldGenerator for WaterWorld {
+    fn generate_chunk(
+        &self,
+        chunk_pos: IVec3,
+        _seed: u32,
+        _height: WorldHeight,
+    ) -> HashMap<IVec3, BlockData> {
+        let mut blocks = HashMap::new();
+        for dy in 0..CHUNK_SIZE {
+            if chunk_pos.y * CHUNK_SIZE + dy < self.height {
+                blocks.insert(
+                    IVec3::new(0, dy, 0),
+                    BlockData::new(BlockId::Water, false, BlockDirection::Front),
+                );
+            }
+        }
+        blocks
+    }
+
+    fn spawn_point(&self, _seed: u32, _height: WorldHeight) -> Vec3 {
+        Vec3::new(0., self.height as f32, 0.)
+    }
+}
+
+#[test]
+fn generators_can_be_registered() {
+    let mut registry = GeneratorRegistry::default();
+    registry.register("water", |options| {
+        Ok(WaterWorld {
+            height: options.parse().map_err(|_| "not a height".to_string())?,
+        })
+    });
+
+    let generator = registry
+        .create(&GeneratorSettings {
+            name: "water".into(),
+            options: "10".into(),
+        })
+        .unwrap();
+    assert_eq!(generator.spawn_point(0, WorldHeight::default()).y, 10.);
+    assert_eq!(
+        generate_chunk(&*generator, IVec3::ZERO, 0, WorldHeight::default())
+            .map
+            .len(),
+        10
+    );
+
+    assert_eq!(
+        registry.create(&GeneratorSettings::new("lava")).err(),
+        Some(GeneratorError::Unknown("lava".into()))
+    );
+}
+
+#[test]
+fn chunks_of_an_older_generator_are_generated_again_unless_modified() {
+    let dir =
+        std::env::temp_dir().join(format!("rustcraft-generator-tests-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let storage = WorldStorage {
+        world_dir: dir.clone(),
+    };
+
+    let generator = create(&GeneratorPreset::Flat.settings());
+    let untouched = IVec3::new(0, 1, 0);
+    let modified = IVec3::new(1, 1, 0);
+    let mut old_untouched = generate_chunk(&*generator, untouched, 0, WorldHeight::default());
+    old_untouched.generator_version = generator.version() - 1;
+    let mut old_modified = old_untouched.clone();
+    old_modified.modified = true;
+    storage
+        .save_chunks([(&untouched, &old_untouched), (&modified, &old_modified)])
+        .unwrap();
+
+    assert!(storage
+        .load_current_chunk(untouched, generator.version())
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .load_current_chunk(modified, generator.version())
+        .unwrap()
+        .is_some());
+    assert!(storage
+        .load_current_chunk(untouched, generator.version() - 1)
+        .unwrap()
+        .is_some());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
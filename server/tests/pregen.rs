@@ -0,0 +1,101 @@
+//! Order and persistence of world pre-generations.
+
+use bevy::math::{IVec2, IVec3};
+use server::{PregenJob, WorldStorage, MAX_PREGEN_RADIUS, PREGEN_FILE};
+use std::collections::HashSet;
+use std::fs;
+
+/// Every column of a job, in the order they are generated
+fn columns(job: &PregenJob) -> Vec<IVec2> {
+    job.regions()
+        .flat_map(|region| job.region_columns(region))
+        .collect()
+}
+
+#[test]
+fn columns_cover_the_area_once() {
+    let job = PregenJob::new(IVec2::new(-20, 45), 30);
+    let columns = columns(&job);
+    assert_eq!(columns.len() as u64, job.total_columns());
+
+    let unique: HashSet<IVec2> = columns.iter().copied().collect();
+    assert_eq!(unique.len() as u64, job.total_columns());
+    assert!(columns
+        .iter()
+        .all(|column| (*column - job.center).abs().max_element() <= job.radius));
+}
+
+#[test]
+fn columns_are_generated_region_by_region_from_the_center() {
+    let job = PregenJob::new(IVec2::new(10, 10), 40);
+    let columns = columns(&job);
+    let region = |column: &IVec2| column.div_euclid(IVec2::splat(32));
+
+    assert_eq!(region(&columns[0]), region(&job.center));
+
+    // once a region is left, it is never visited again
+    let mut visited = HashSet::new();
+    for pair in columns.windows(2) {
+        if region(&pair[0]) != region(&pair[1]) {
+            assert!(visited.insert(region(&pair[0])));
+            assert!(!visited.contains(&region(&pair[1])));
+        }
+    }
+
+    // regions further from the center come later
+    let distances: Vec<i32> = job
+        .regions()
+        .map(|r| (r - region(&job.center)).abs().max_element())
+        .collect();
+    assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+#[test]
+fn batches_are_region_files() {
+    let job = PregenJob::new(IVec2::new(0, 0), 20);
+    let batches: Vec<IVec3> = job.batches(-1..=2).collect();
+    assert_eq!(batches.len(), 4 * 4);
+    assert_eq!(
+        batches[..4],
+        [
+            IVec3::new(0, -1, 0),
+            IVec3::new(0, 0, 0),
+            IVec3::new(0, 1, 0),
+            IVec3::new(0, 2, 0),
+        ]
+    );
+}
+
+#[test]
+fn largest_jobs_are_listed_lazily() {
+    let job = PregenJob::new(IVec2::new(i32::MAX / 32, -7), MAX_PREGEN_RADIUS / 32);
+    let regions = job.regions().count() as u64;
+    assert_eq!(regions, 33 * 33);
+    assert_eq!(job.total_columns(), 1025 * 1025);
+    assert_eq!(
+        job.regions()
+            .map(|r| job.region_columns(r).count() as u64)
+            .sum::<u64>(),
+        job.total_columns()
+    );
+}
+
+#[test]
+fn jobs_resume_where_they_stopped() {
+    let dir = std::env::temp_dir().join(format!("rustcraft-pregen-tests-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let storage = WorldStorage {
+        world_dir: dir.clone(),
+    };
+    assert_eq!(PregenJob::load(&storage).unwrap(), None);
+
+    let mut job = PregenJob::new(IVec2::new(3, -7), 12);
+    job.done = 128;
+    job.save(&storage).unwrap();
+    assert!(dir.join(PREGEN_FILE).is_file());
+    assert_eq!(PregenJob::load(&storage).unwrap(), Some(job));
+
+    PregenJob::remove(&storage).unwrap();
+    assert_eq!(PregenJob::load(&storage).unwrap(), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,336 @@
+//! Loads the worlds of the `saves` corpus, one per save format version, with the current code.
+//! A new corpus entry should be added every time `SAVE_VERSION` is bumped.
+
+use bevy::math::{IVec3, Vec3};
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+use server::{load_metadata, load_world, WorldLoadError, WorldStorage, SAVE_VERSION};
+use shared::world::{
+    seed_from_text, BlockData, BlockDirection, BlockId, GameMode, GeneratorPreset, ServerChunk,
+    TimeOfDay, WorldCreationSettings, WorldHeight, WorldMetadata, WorldSeed,
+};
+use shared::GameFolderPaths;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()));
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+        }
+    }
+}
+
+/// Copies a game folder of the corpus somewhere tests can modify it
+fn game_folder(fixture: &str, test: &str) -> (PathBuf, GameFolderPaths) {
+    let dir = std::env::temp_dir().join(format!(
+        "rustcraft-save-tests-{}-{}",
+        std::process::id(),
+        test
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    if !fixture.is_empty() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/saves")
+            .join(fixture);
+        copy_dir(&corpus, &dir);
+    }
+
+    let paths = GameFolderPaths {
+        game_folder_path: dir.display().to_string(),
+        assets_folder_path: dir.join("data").display().to_string(),
+    };
+    (dir, paths)
+}
+
+#[test]
+fn v0_single_file_save_is_split_into_region_files() {
+    let (dir, paths) = game_folder("v0", "v0");
+    let storage = WorldStorage::new(&paths, "v0");
+
+    let level = load_world(&storage, &WorldCreationSettings::default()).unwrap();
+    assert_eq!(level.version, SAVE_VERSION);
+    assert_eq!(level.seed.0, 1234);
+    assert_eq!(level.time, 5678);
+
+    let chunk = storage.load_chunk(IVec3::new(-3, 4, 70)).unwrap().unwrap();
+    assert_eq!(chunk.map.len(), 2);
+    assert_eq!(chunk.map[&IVec3::new(1, 2, 3)].id, BlockId::Debug);
+    assert_eq!(chunk.map[&IVec3::new(0, 0, 0)].id, BlockId::OakPlanks);
+    assert!(chunk.map[&IVec3::new(0, 0, 0)].flipped);
+    // Such saves cannot tell edited chunks apart, so none is dropped
+    assert!(chunk.modified);
+
+    assert!(!dir.join("saves/v0.ron").exists());
+    assert!(dir.join("saves/v0/legacy.ron").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn v1_level_file_gets_a_version() {
+    let (dir, paths) = game_folder("v1", "v1");
+    let storage = WorldStorage::new(&paths, "v1");
+
+    let level = load_world(&storage, &WorldCreationSettings::default()).unwrap();
+    assert_eq!(level.version, SAVE_VERSION);
+    assert_eq!(level.seed.0, 4321);
+    assert_eq!(level.time, 8765);
+    assert_eq!(storage.load_level().unwrap().version, SAVE_VERSION);
+
+    let chunk = storage.load_chunk(IVec3::new(33, -1, -2)).unwrap().unwrap();
+    assert_eq!(chunk.map[&IVec3::new(15, 15, 15)].id, BlockId::Stone);
+    assert!(storage
+        .load_chunk(IVec3::new(32, -1, -2))
+        .unwrap()
+        .is_none());
+
+    // The world is backed up before being upgraded
+    assert_eq!(
+        fs::read_dir(dir.join("saves/v1/backups")).unwrap().count(),
+        1
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn v2_metadata_gets_the_default_generator() {
+    let (dir, paths) = game_folder("v2", "v2");
+    let storage = WorldStorage::new(&paths, "v2");
+
+    let level = load_world(&storage, &WorldCreationSettings::default()).unwrap();
+    assert_eq!(level.version, SAVE_VERSION);
+    assert_eq!(level.seed.0, 99);
+    assert_eq!(level.time, 42);
+
+    // The metadata already existed, only the generator is added
+    let metadata = storage.load_metadata().unwrap();
+    assert_eq!(metadata.generator, GeneratorPreset::Default.settings());
+    assert_eq!(metadata.display_name, "Old world");
+    assert_eq!(metadata.game_mode, GameMode::Creative);
+    assert_eq!(metadata.height, WorldHeight::default());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn migrated_world_loads_without_migrating_again() {
+    let (dir, paths) = game_folder("v1", "reload");
+    let storage = WorldStorage::new(&paths, "v1");
+
+    load_world(&storage, &WorldCreationSettings::default()).unwrap();
+    let level = load_world(&storage, &WorldCreationSettings::default()).unwrap();
+    assert_eq!(level.seed.0, 4321);
+    assert_eq!(
+        fs::read_dir(dir.join("saves/v1/backups")).unwrap().count(),
+        1
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn world_from_a_newer_version_is_rejected() {
+    let (dir, paths) = game_folder("v1", "newer");
+    let storage = WorldStorage::new(&paths, "v1");
+    let level = format!("(version: {}, seed: (1), time: 0)", SAVE_VERSION + 1);
+    fs::write(storage.level_path(), &level).unwrap();
+
+    assert_eq!(
+        load_world(&storage, &WorldCreationSettings::default()).err(),
+        Some(WorldLoadError::UnsupportedVersion {
+            version: SAVE_VERSION + 1
+        })
+    );
+    assert_eq!(fs::read_to_string(storage.level_path()).unwrap(), level);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn corrupted_level_file_is_an_error() {
+    let (dir, paths) = game_folder("v1", "corrupted");
+    let storage = WorldStorage::new(&paths, "v1");
+    fs::write(storage.level_path(), "(seed: ").unwrap();
+
+    assert!(matches!(
+        load_world(&storage, &WorldCreationSettings::default()),
+        Err(WorldLoadError::Read(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_world_is_created() {
+    let (dir, paths) = game_folder("", "missing");
+    let storage = WorldStorage::new(&paths, "new");
+
+    let level = load_world(&storage, &WorldCreationSettings::default()).unwrap();
+    assert_eq!(level.version, SAVE_VERSION);
+    assert_eq!(level.time, 0);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_world_is_created_with_the_creation_settings() {
+    let (dir, paths) = game_folder("", "creation");
+    let storage = WorldStorage::new(&paths, "new");
+    let creation = WorldCreationSettings {
+        seed: seed_from_text("rustcraft"),
+        generator: GeneratorPreset::Flat.settings(),
+        time_of_day: TimeOfDay::Noon,
+        height: WorldHeight::new(-64, 320).unwrap(),
+        ..Default::default()
+    };
+
+    let level = load_world(&storage, &creation).unwrap();
+    assert_eq!(Some(level.seed.0), seed_from_text("rustcraft"));
+    let metadata = load_metadata(&storage, "new", &level, &creation).unwrap();
+    assert_eq!(metadata.generator, GeneratorPreset::Flat.settings());
+    assert_eq!(metadata.height.chunk_layers(), -4..=19);
+    assert_eq!(level.time, TimeOfDay::Noon.start_time());
+
+    // Settings only apply to new worlds
+    let (dir_v1, paths_v1) = game_folder("v1", "creation-v1");
+    let storage_v1 = WorldStorage::new(&paths_v1, "v1");
+    let level = load_world(&storage_v1, &creation).unwrap();
+    assert_eq!(level.seed.0, 4321);
+    let metadata = load_metadata(&storage_v1, "v1", &level, &creation).unwrap();
+    assert_eq!(metadata.generator, GeneratorPreset::Default.settings());
+    assert_eq!(metadata.height.chunk_layers(), 0..=8);
+
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_dir_all(dir_v1).unwrap();
+}
+
+/// Version 0 chunk, from before the modified flag
+#[derive(Serialize)]
+struct ChunkV0 {
+    map: HashMap<IVec3, BlockData>,
+    ts: u64,
+}
+
+#[derive(Serialize)]
+struct WorldMapV0 {
+    name: String,
+    map: HashMap<IVec3, ChunkV0>,
+    chunks_to_update: Vec<IVec3>,
+    player_positions: HashMap<u64, Vec3>,
+    time: u64,
+}
+
+#[derive(Serialize)]
+struct WorldDataV0 {
+    seed: WorldSeed,
+    map: WorldMapV0,
+    time: u64,
+}
+
+/// Writes the v0 and v1 worlds of the corpus, whose files cannot be written by hand.
+/// Run with `cargo test --test save_migrations -- --ignored` after changing them.
+#[test]
+#[ignore]
+fn write_binary_fixtures() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/saves");
+
+    let blocks = HashMap::from([
+        (
+            IVec3::new(1, 2, 3),
+            BlockData::new(BlockId::Debug, false, BlockDirection::Front),
+        ),
+        (
+            IVec3::new(0, 0, 0),
+            BlockData::new(BlockId::OakPlanks, true, BlockDirection::Back),
+        ),
+    ]);
+    let world = WorldDataV0 {
+        seed: WorldSeed(1234),
+        map: WorldMapV0 {
+            name: "v0".into(),
+            map: HashMap::from([(
+                IVec3::new(-3, 4, 70),
+                ChunkV0 {
+                    map: blocks,
+                    ts: 1_700_000_000_000,
+                },
+            )]),
+            chunks_to_update: vec![],
+            player_positions: HashMap::new(),
+            time: 5678,
+        },
+        time: 5678,
+    };
+    let config = PrettyConfig::new()
+        .with_depth_limit(3)
+        .with_separate_tuple_members(true)
+        .with_enumerate_arrays(true);
+    fs::create_dir_all(corpus.join("v0/saves")).unwrap();
+    fs::write(
+        corpus.join("v0/saves/v0.ron"),
+        ron::ser::to_string_pretty(&world, config).unwrap(),
+    )
+    .unwrap();
+
+    // Chunks have kept the same layout since version 1, the current one writes its regions
+    let dir = corpus.join("v1/saves/v1");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("level.ron"),
+        "(\n    seed: (4321),\n    time: 8765,\n)",
+    )
+    .unwrap();
+    let mut chunk = ServerChunk {
+        map: HashMap::new(),
+        ts: 1_700_000_000_000,
+        modified: true,
+        generator_version: 1,
+    };
+    chunk.map.insert(
+        IVec3::new(15, 15, 15),
+        BlockData::new(BlockId::Stone, false, BlockDirection::Right),
+    );
+    let storage = WorldStorage { world_dir: dir };
+    storage
+        .save_chunks([(&IVec3::new(33, -1, -2), &chunk)])
+        .unwrap();
+}
+
+#[test]
+fn unreadable_metadata_is_an_error() {
+    let (dir, paths) = game_folder("v2", "bad-metadata");
+    let storage = WorldStorage::new(&paths, "v2");
+    let creation = WorldCreationSettings::default();
+    let level = load_world(&storage, &creation).unwrap();
+
+    // the settings of the world are kept for the file to be fixed
+    fs::write(storage.metadata_path(), "(display_name: ").unwrap();
+    assert!(matches!(
+        load_metadata(&storage, "v2", &level, &creation),
+        Err(WorldLoadError::Read(_))
+    ));
+    assert_eq!(
+        fs::read_to_string(storage.metadata_path()).unwrap(),
+        "(display_name: "
+    );
+
+    let mut metadata = WorldMetadata::new("v2".into(), level.seed.0, SAVE_VERSION);
+    metadata.height.min = metadata.height.max;
+    storage.save_metadata(&metadata).unwrap();
+    assert!(matches!(
+        load_metadata(&storage, "v2", &level, &creation),
+        Err(WorldLoadError::InvalidHeight(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -6,6 +6,8 @@ use bevy_renet::renet::{ChannelConfig, ConnectionConfig, SendType};
 pub mod messages;
 pub mod world;
 
+use world::WorldCreationSettings;
+
 #[derive(Resource, Debug, Clone)]
 pub struct GameFolderPaths {
     pub game_folder_path: String,
@@ -21,10 +23,18 @@ pub struct SpecialFlag {
 pub struct GameServerConfig {
     pub world_name: String,
     pub is_solo: bool,
+    /// Memory the server may use for loaded chunks, in megabytes, the server default when `None`
+    pub chunk_memory_budget_mb: Option<usize>,
+    /// Used when the world does not exist yet
+    pub creation: WorldCreationSettings,
 }
 
 pub const PROTOCOL_ID: u64 = 0;
+/// Version of the game, a client can only play on a server with the same version
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const CHUNK_SIZE: i32 = 16;
+/// Length of a day and night cycle, in server time units (seconds)
+pub const DAY_DURATION: u64 = 60;
 
 fn get_customized_default_channels() -> Vec<ChannelConfig> {
     let memory = 128 * 1024 * 1024;
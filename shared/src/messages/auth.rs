@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use super::PlayerSpawnEvent;
+use crate::world::WorldHeight;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct AuthRegisterRequest {
@@ -12,4 +13,6 @@ pub struct AuthRegisterResponse {
     pub username: String,
     pub session_token: u128,
     pub spawn_event: PlayerSpawnEvent,
+    pub server_version: String,
+    pub world_height: WorldHeight,
 }
@@ -24,6 +24,7 @@ pub enum ClientToServerMessage {
     WorldUpdateRequest {
         player_chunk_position: IVec3,
         render_distance: u32,
+        vertical_render_distance: u32,
         requested_chunks: Vec<IVec3>,
     },
     SaveWorldRequest(SaveWorldRequest),
@@ -35,6 +36,7 @@ pub enum ClientToServerMessage {
         // should be deprecated in the long run
         position: Vec3,
     },
+    OpenToLan,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -43,4 +45,13 @@ pub enum ServerToClientMessage {
     ChatConversation(ChatConversation),
     WorldUpdate(WorldUpdate),
     PlayerSpawn(PlayerSpawnEvent),
+    SaveWorldResponse(SaveWorldResponse),
+    /// Answer to a command, only sent to the player who issued it
+    CommandReply {
+        content: String,
+    },
+    /// Sent right before the server closes the connection
+    Disconnect {
+        reason: DisconnectReason,
+    },
 }
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct ExitOrder {
@@ -9,3 +11,61 @@ pub struct ExitOrder {
 pub struct SaveWorldRequest {
     pub session_token: u128,
 }
+
+/// Outcome of a `SaveWorldRequest`, sent once the world is written to disk
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum SaveWorldResponse {
+    Saved { chunks: usize },
+    Failed { error: String },
+}
+
+/// Why the server is about to close a client's connection
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum DisconnectReason {
+    Kicked {
+        message: Option<String>,
+    },
+    Banned {
+        reason: Option<String>,
+        /// Timestamp in ms, `None` for a permanent ban
+        expires_at: Option<u64>,
+    },
+    NotWhitelisted,
+    Shutdown,
+    DuplicateUsername,
+    ServerFull,
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisconnectReason::Kicked { message: None } => write!(f, "Kicked from the server"),
+            DisconnectReason::Kicked {
+                message: Some(message),
+            } => write!(f, "Kicked from the server: {}", message),
+            DisconnectReason::Banned { reason, expires_at } => {
+                write!(f, "Banned from the server")?;
+                if let Some(reason) = reason {
+                    write!(f, ": {}", reason)?;
+                }
+                if let Some(expires_at) = expires_at {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64;
+                    let minutes = expires_at.saturating_sub(now).div_ceil(60_000);
+                    write!(f, " (expires in {} min)", minutes)?;
+                }
+                Ok(())
+            }
+            DisconnectReason::NotWhitelisted => {
+                write!(f, "You are not whitelisted on this server")
+            }
+            DisconnectReason::Shutdown => write!(f, "Server closed"),
+            DisconnectReason::DuplicateUsername => {
+                write!(f, "A player with the same name is already connected")
+            }
+            DisconnectReason::ServerFull => write!(f, "Server is full"),
+        }
+    }
+}
@@ -10,6 +10,9 @@ use super::PlayerId;
 pub struct WorldUpdate {
     pub tick: u64,
     pub new_map: HashMap<IVec3, ServerChunk>,
+    /// Requested chunks without any block, listed rather than sent so that they are not
+    /// requested again while they stay in view
+    pub empty_chunks: Vec<IVec3>,
     pub player_positions: HashMap<PlayerId, Vec3>,
     pub time: u64,
 }
@@ -38,6 +38,11 @@ pub enum BlockId {
     Snow,
     SpruceLeaves,
     SpruceLog,
+    CoalOre,
+    IronOre,
+    GoldOre,
+    DiamondOre,
+    Water,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -80,11 +85,40 @@ pub enum BlockTransparency {
 }
 
 impl BlockId {
+    /// Every block, in declaration order. New blocks have to be added here too, which the tests
+    /// of this file check.
+    pub const ALL: [BlockId; 24] = [
+        BlockId::Dirt,
+        BlockId::Debug,
+        BlockId::Grass,
+        BlockId::Stone,
+        BlockId::OakLog,
+        BlockId::OakPlanks,
+        BlockId::OakLeaves,
+        BlockId::Sand,
+        BlockId::Cactus,
+        BlockId::Ice,
+        BlockId::Glass,
+        BlockId::Bedrock,
+        BlockId::Dandelion,
+        BlockId::Poppy,
+        BlockId::TallGrass,
+        BlockId::Cobblestone,
+        BlockId::Snow,
+        BlockId::SpruceLeaves,
+        BlockId::SpruceLog,
+        BlockId::CoalOre,
+        BlockId::IronOre,
+        BlockId::GoldOre,
+        BlockId::DiamondOre,
+        BlockId::Water,
+    ];
+
     pub fn has_hitbox(&self) -> bool {
-        match *self {
-            BlockId::Dandelion | BlockId::Poppy | BlockId::TallGrass => false,
-            _ => true,
-        }
+        !matches!(
+            *self,
+            BlockId::Dandelion | BlockId::Poppy | BlockId::TallGrass | BlockId::Water
+        )
     }
 
     pub fn is_biome_colored() -> bool {
@@ -93,7 +127,7 @@ impl BlockId {
 
     pub fn get_break_time(&self) -> f32 {
         match *self {
-            Self::Bedrock => -1.,
+            Self::Bedrock | Self::Water => -1.,
             _ => 5.,
         }
     }
@@ -150,13 +184,22 @@ impl BlockId {
             BlockId::TallGrass => vec![(1, ItemId::TallGrass, 1)],
             BlockId::SpruceLog => vec![(1, ItemId::SpruceLog, 1)],
             BlockId::Snow => vec![(1, ItemId::Snowball, 4)],
+            BlockId::CoalOre => vec![(1, ItemId::Coal, 1)],
+            BlockId::IronOre => vec![(1, ItemId::RawIron, 1)],
+            BlockId::GoldOre => vec![(1, ItemId::RawGold, 1)],
+            BlockId::DiamondOre => vec![(1, ItemId::Diamond, 1)],
             _ => vec![],
         }
     }
 
     pub fn get_tags(&self) -> Vec<BlockTags> {
         match *self {
-            BlockId::Stone => vec![BlockTags::Stone, BlockTags::Solid],
+            BlockId::Stone
+            | BlockId::CoalOre
+            | BlockId::IronOre
+            | BlockId::GoldOre
+            | BlockId::DiamondOre => vec![BlockTags::Stone, BlockTags::Solid],
+            BlockId::Water => vec![],
             _ => vec![BlockTags::Solid],
         }
     }
@@ -165,9 +208,63 @@ impl BlockId {
         match *self {
             Self::Dandelion | Self::Poppy | Self::TallGrass => BlockTransparency::Decoration,
             Self::Glass | Self::OakLeaves | Self::SpruceLeaves => BlockTransparency::Transparent,
+            Self::Water => BlockTransparency::Liquid,
             _ => BlockTransparency::Solid,
         }
     }
 }
 
 impl GameElementId for BlockId {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error, U32Deserializer};
+
+    /// Position of a block in `BlockId::ALL`. There is no wildcard arm, so that a new block
+    /// does not compile until it is given its place in the list.
+    fn position(block: BlockId) -> usize {
+        match block {
+            BlockId::Dirt => 0,
+            BlockId::Debug => 1,
+            BlockId::Grass => 2,
+            BlockId::Stone => 3,
+            BlockId::OakLog => 4,
+            BlockId::OakPlanks => 5,
+            BlockId::OakLeaves => 6,
+            BlockId::Sand => 7,
+            BlockId::Cactus => 8,
+            BlockId::Ice => 9,
+            BlockId::Glass => 10,
+            BlockId::Bedrock => 11,
+            BlockId::Dandelion => 12,
+            BlockId::Poppy => 13,
+            BlockId::TallGrass => 14,
+            BlockId::Cobblestone => 15,
+            BlockId::Snow => 16,
+            BlockId::SpruceLeaves => 17,
+            BlockId::SpruceLog => 18,
+            BlockId::CoalOre => 19,
+            BlockId::IronOre => 20,
+            BlockId::GoldOre => 21,
+            BlockId::DiamondOre => 22,
+            BlockId::Water => 23,
+        }
+    }
+
+    #[test]
+    fn every_block_is_listed() {
+        for (index, block) in BlockId::ALL.iter().enumerate() {
+            assert_eq!(position(*block), index, "{:?}", block);
+        }
+
+        // every variant, by declaration index, is in the list and nothing comes after it
+        for index in 0..=BlockId::ALL.len() {
+            let block = BlockId::deserialize(U32Deserializer::<Error>::new(index as u32));
+            match BlockId::ALL.get(index) {
+                Some(listed) => assert_eq!(block.ok(), Some(*listed)),
+                None => assert!(block.is_err(), "{:?} is not listed", block),
+            }
+        }
+    }
+}
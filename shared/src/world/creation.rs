@@ -0,0 +1,194 @@
+use super::{block_to_chunk_coord, GameMode};
+use crate::DAY_DURATION;
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Names the generators shipped with the server are registered with
+pub const DEFAULT_GENERATOR: &str = "default";
+pub const FLAT_GENERATOR: &str = "flat";
+pub const VOID_GENERATOR: &str = "void";
+pub const DEBUG_GENERATOR: &str = "debug";
+
+/// Terrain generator of a world, chosen when it is created and kept in its metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratorSettings {
+    /// Name the generator is registered with on the server
+    pub name: String,
+    /// Options of the generator in RON, empty for its defaults
+    #[serde(default)]
+    pub options: String,
+}
+
+impl GeneratorSettings {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            options: String::new(),
+        }
+    }
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self::new(DEFAULT_GENERATOR)
+    }
+}
+
+/// Generators offered when creating a world, with their default options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GeneratorPreset {
+    #[default]
+    Default,
+    /// Grass on a few layers of dirt and stone, the same everywhere
+    Flat,
+    /// Nothing but a small platform to spawn on
+    Void,
+    /// Every block laid out in a grid
+    Debug,
+}
+
+impl GeneratorPreset {
+    pub fn settings(self) -> GeneratorSettings {
+        GeneratorSettings::new(match self {
+            GeneratorPreset::Default => DEFAULT_GENERATOR,
+            GeneratorPreset::Flat => FLAT_GENERATOR,
+            GeneratorPreset::Void => VOID_GENERATOR,
+            GeneratorPreset::Debug => DEBUG_GENERATOR,
+        })
+    }
+}
+
+/// Lowest and highest heights a world can be created with, keeping the chunk layers of a world
+/// few enough to be loaded around players
+pub const MIN_WORLD_HEIGHT: i32 = -2048;
+pub const MAX_WORLD_HEIGHT: i32 = 2048;
+
+/// Heights blocks can be placed at, from `min` included to `max` excluded
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldHeight {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl Default for WorldHeight {
+    /// The 9 chunk layers above 0 worlds had before their height could be changed
+    fn default() -> Self {
+        Self { min: 0, max: 144 }
+    }
+}
+
+impl WorldHeight {
+    pub fn new(min: i32, max: i32) -> Result<Self, String> {
+        let height = Self { min, max };
+        height.validate()?;
+        Ok(height)
+    }
+
+    /// Checks the heights are ordered and within the bounds worlds can have, which a height
+    /// read from the metadata of a world has to be checked against too
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min >= self.max {
+            return Err(format!(
+                "the minimum height ({}) has to be below the maximum height ({})",
+                self.min, self.max
+            ));
+        }
+        if self.min < MIN_WORLD_HEIGHT || self.max > MAX_WORLD_HEIGHT {
+            return Err(format!(
+                "the heights ({} to {}) have to be between {} and {}",
+                self.min, self.max, MIN_WORLD_HEIGHT, MAX_WORLD_HEIGHT
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, y: i32) -> bool {
+        y >= self.min && y < self.max
+    }
+
+    /// Vertical positions of the chunks holding the heights of the world
+    pub fn chunk_layers(&self) -> RangeInclusive<i32> {
+        block_to_chunk_coord(self.min)..=block_to_chunk_coord(self.max - 1)
+    }
+}
+
+/// Time of day a new world starts at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeOfDay {
+    Sunrise,
+    Noon,
+    /// Time 0, new worlds started at sunset before this setting existed
+    #[default]
+    Sunset,
+    Midnight,
+}
+
+impl TimeOfDay {
+    /// Server time at which the first day is at this point.
+    /// The sun rises half a day after time 0 and sets at every multiple of `DAY_DURATION`.
+    pub fn start_time(self) -> u64 {
+        match self {
+            TimeOfDay::Sunset => 0,
+            TimeOfDay::Midnight => DAY_DURATION / 4,
+            TimeOfDay::Sunrise => DAY_DURATION / 2,
+            TimeOfDay::Noon => DAY_DURATION * 3 / 4,
+        }
+    }
+}
+
+/// Settings of a world created by the server, ignored when the world already exists
+#[derive(Debug, Clone, Default)]
+pub struct WorldCreationSettings {
+    /// Random when `None`
+    pub seed: Option<u32>,
+    pub generator: GeneratorSettings,
+    pub height: WorldHeight,
+    pub game_mode: GameMode,
+    pub time_of_day: TimeOfDay,
+}
+
+/// Seed typed by a player: numbers are used as is, any other text is hashed.
+/// `None` for an empty text, so that a random seed is picked.
+pub fn seed_from_text(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if let Ok(seed) = text.parse::<u32>() {
+        return Some(seed);
+    }
+
+    // 32 bits FNV-1a, which unlike the std hashers is guaranteed to stay the same
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in text.bytes() {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    Some(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_layers_hold_every_height() {
+        assert_eq!(WorldHeight::default().chunk_layers(), 0..=8);
+        assert_eq!(WorldHeight::new(-64, 320).unwrap().chunk_layers(), -4..=19);
+        // partial chunks at both ends
+        assert_eq!(WorldHeight::new(-65, 321).unwrap().chunk_layers(), -5..=20);
+        assert_eq!(WorldHeight::new(-1, 1).unwrap().chunk_layers(), -1..=0);
+        assert_eq!(WorldHeight::new(5, 6).unwrap().chunk_layers(), 0..=0);
+    }
+
+    #[test]
+    fn heights_are_bounded() {
+        assert!(WorldHeight::new(0, 0).is_err());
+        assert!(WorldHeight::new(10, -10).is_err());
+        assert!(WorldHeight::new(MIN_WORLD_HEIGHT, MAX_WORLD_HEIGHT).is_ok());
+        assert!(WorldHeight::new(MIN_WORLD_HEIGHT - 1, 0).is_err());
+        assert!(WorldHeight::new(0, MAX_WORLD_HEIGHT + 1).is_err());
+        assert!(WorldHeight::new(i32::MIN, i32::MAX).is_err());
+    }
+}
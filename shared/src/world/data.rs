@@ -8,7 +8,7 @@ use bevy::math::IVec3;
 use bevy::math::Vec3;
 use bevy::prelude::Resource;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
 use super::BlockData;
@@ -20,6 +20,19 @@ pub struct ServerChunk {
     pub map: HashMap<IVec3, BlockData>,
     /// Timestamp marking the last update this chunk has received
     pub ts: u64,
+    /// Whether a player changed this chunk since it was generated.
+    /// Unmodified chunks are not saved, they are generated again from the seed.
+    #[serde(default = "modified_by_default")]
+    pub modified: bool,
+    /// Version of the generator which produced the terrain of this chunk, 0 if unknown.
+    /// Unmodified chunks saved by an older version are generated again when loaded.
+    #[serde(default)]
+    pub generator_version: u32,
+}
+
+/// Chunks from saves which predate the flag cannot be told apart, so they are all kept
+fn modified_by_default() -> bool {
+    true
 }
 
 #[derive(Resource, Default, Clone, Serialize, Deserialize, Debug)]
@@ -27,6 +40,13 @@ pub struct ServerWorldMap {
     pub name: String,
     pub map: HashMap<IVec3, ServerChunk>,
     pub chunks_to_update: Vec<IVec3>,
+    /// Chunks changed since they were last written to disk
+    #[serde(skip)]
+    pub dirty_chunks: HashSet<IVec3>,
+    /// Chunks generated without a single block, which are not kept in `map` but are not
+    /// generated again either
+    #[serde(skip)]
+    pub empty_chunks: HashSet<IVec3>,
     pub player_positions: HashMap<PlayerId, Vec3>,
     pub time: u64,
 }
@@ -41,7 +61,7 @@ pub struct ItemStack {
     pub nb: u32,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BiomeType {
     Plains,
     Forest,
@@ -50,17 +70,70 @@ pub enum BiomeType {
     Desert,
     IcePlain,
     FlowerPlains,
+    Ocean,
+    River,
+    Beach,
+    Taiga,
+    Swamp,
+    Savanna,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// How the trees of a biome are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TreeShape {
+    /// A short trunk under a small ball of leaves
+    Round,
+    /// Layers of leaves around a tall trunk, wider and wider towards the bottom
+    Conical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tree {
+    pub shape: TreeShape,
+    pub trunk: BlockId,
+    pub leaves: BlockId,
+}
+
+impl Tree {
+    pub const OAK: Tree = Tree {
+        shape: TreeShape::Round,
+        trunk: BlockId::OakLog,
+        leaves: BlockId::OakLeaves,
+    };
+    pub const SPRUCE: Tree = Tree {
+        shape: TreeShape::Conical,
+        trunk: BlockId::SpruceLog,
+        leaves: BlockId::SpruceLeaves,
+    };
+}
+
+/// What grows on the surface of a biome, chances being per column
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Flora {
+    pub flower_chance: f32,
+    /// Flowers are picked among these, all as likely
+    pub flowers: Vec<BlockId>,
+    pub tall_grass_chance: f32,
+    pub tree_chance: f32,
+    pub tree: Option<Tree>,
+    pub cactus_chance: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Biome {
     pub biome_type: BiomeType,
     pub base_height: i32,
     pub height_variation: i32,
     pub surface_block: BlockId,
     pub sub_surface_block: BlockId,
+    /// How much of the underground is carved by caves, 1 being the usual amount and 0 none
+    pub cave_density: f64,
+    #[serde(default)]
+    pub flora: Flora,
 }
 
+/// Built-in parameters of a biome, which generator presets can change
 pub fn get_biome_data(biome_type: BiomeType) -> Biome {
     match biome_type {
         BiomeType::Plains => Biome {
@@ -69,6 +142,13 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 1,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            cave_density: 1.0,
+            flora: Flora {
+                flower_chance: 0.02,
+                flowers: vec![BlockId::Dandelion, BlockId::Poppy],
+                tall_grass_chance: 0.1,
+                ..Default::default()
+            },
         },
         BiomeType::Forest => Biome {
             biome_type: BiomeType::Forest,
@@ -76,6 +156,15 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 2,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            cave_density: 1.0,
+            flora: Flora {
+                flower_chance: 0.02,
+                flowers: vec![BlockId::Dandelion, BlockId::Poppy],
+                tall_grass_chance: 0.1,
+                tree_chance: 0.06,
+                tree: Some(Tree::OAK),
+                ..Default::default()
+            },
         },
         BiomeType::MediumMountain => Biome {
             biome_type: BiomeType::MediumMountain,
@@ -83,6 +172,15 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 4,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            cave_density: 1.3,
+            flora: Flora {
+                flower_chance: 0.02,
+                flowers: vec![BlockId::Dandelion, BlockId::Poppy],
+                tall_grass_chance: 0.1,
+                tree_chance: 0.02,
+                tree: Some(Tree::OAK),
+                ..Default::default()
+            },
         },
         BiomeType::HighMountainGrass => Biome {
             biome_type: BiomeType::HighMountainGrass,
@@ -90,6 +188,8 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 7,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            cave_density: 1.5,
+            flora: Flora::default(),
         },
         BiomeType::Desert => Biome {
             biome_type: BiomeType::Desert,
@@ -97,6 +197,11 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 1,
             surface_block: BlockId::Sand,
             sub_surface_block: BlockId::Sand,
+            cave_density: 0.6,
+            flora: Flora {
+                cactus_chance: 0.01,
+                ..Default::default()
+            },
         },
         BiomeType::IcePlain => Biome {
             biome_type: BiomeType::IcePlain,
@@ -104,6 +209,8 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 1,
             surface_block: BlockId::Snow,
             sub_surface_block: BlockId::Ice,
+            cave_density: 0.8,
+            flora: Flora::default(),
         },
         BiomeType::FlowerPlains => Biome {
             biome_type: BiomeType::FlowerPlains,
@@ -111,6 +218,83 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 1,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            cave_density: 1.0,
+            flora: Flora {
+                flower_chance: 0.1,
+                flowers: vec![BlockId::Dandelion, BlockId::Poppy],
+                tall_grass_chance: 0.1,
+                tree_chance: 0.02,
+                tree: Some(Tree::OAK),
+                ..Default::default()
+            },
+        },
+        BiomeType::Ocean => Biome {
+            biome_type: BiomeType::Ocean,
+            base_height: 50,
+            height_variation: 4,
+            surface_block: BlockId::Sand,
+            sub_surface_block: BlockId::Sand,
+            cave_density: 0.,
+            flora: Flora::default(),
+        },
+        BiomeType::River => Biome {
+            biome_type: BiomeType::River,
+            base_height: 52,
+            height_variation: 1,
+            surface_block: BlockId::Sand,
+            sub_surface_block: BlockId::Dirt,
+            cave_density: 0.,
+            flora: Flora::default(),
+        },
+        BiomeType::Beach => Biome {
+            biome_type: BiomeType::Beach,
+            base_height: 63,
+            height_variation: 1,
+            surface_block: BlockId::Sand,
+            sub_surface_block: BlockId::Sand,
+            cave_density: 0.5,
+            flora: Flora::default(),
+        },
+        BiomeType::Taiga => Biome {
+            biome_type: BiomeType::Taiga,
+            base_height: 66,
+            height_variation: 3,
+            surface_block: BlockId::Snow,
+            sub_surface_block: BlockId::Dirt,
+            cave_density: 1.0,
+            flora: Flora {
+                tree_chance: 0.05,
+                tree: Some(Tree::SPRUCE),
+                ..Default::default()
+            },
+        },
+        BiomeType::Swamp => Biome {
+            biome_type: BiomeType::Swamp,
+            base_height: 62,
+            height_variation: 1,
+            surface_block: BlockId::Grass,
+            sub_surface_block: BlockId::Dirt,
+            cave_density: 0.6,
+            flora: Flora {
+                tall_grass_chance: 0.1,
+                tree_chance: 0.04,
+                tree: Some(Tree::OAK),
+                ..Default::default()
+            },
+        },
+        BiomeType::Savanna => Biome {
+            biome_type: BiomeType::Savanna,
+            base_height: 66,
+            height_variation: 2,
+            surface_block: BlockId::Grass,
+            sub_surface_block: BlockId::Dirt,
+            cave_density: 1.0,
+            flora: Flora {
+                tall_grass_chance: 0.3,
+                tree_chance: 0.005,
+                tree: Some(Tree::OAK),
+                ..Default::default()
+            },
         },
     }
 }
@@ -151,7 +335,9 @@ impl ServerWorldMap {
         let local_block_pos: IVec3 = to_local_pos(global_block_pos);
 
         chunk_map.map.remove(&local_block_pos);
+        chunk_map.modified = true;
         self.chunks_to_update.push(IVec3::new(cx, cy, cz));
+        self.dirty_chunks.insert(IVec3::new(cx, cy, cz));
 
         Some(kind)
     }
@@ -163,13 +349,16 @@ impl ServerWorldMap {
         let cx: i32 = block_to_chunk_coord(x);
         let cy: i32 = block_to_chunk_coord(y);
         let cz: i32 = block_to_chunk_coord(z);
+        self.empty_chunks.remove(&IVec3::new(cx, cy, cz));
         let chunk: &mut ServerChunk = self.map.entry(IVec3::new(cx, cy, cz)).or_default();
         let sub_x: i32 = ((x % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
         let sub_y: i32 = ((y % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
         let sub_z: i32 = ((z % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
 
         chunk.map.insert(IVec3::new(sub_x, sub_y, sub_z), block);
+        chunk.modified = true;
         self.chunks_to_update.push(IVec3::new(cx, cy, cz));
+        self.dirty_chunks.insert(IVec3::new(cx, cy, cz));
     }
 }
 
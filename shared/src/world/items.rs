@@ -37,6 +37,10 @@ pub enum ItemId {
     Snow,
     Snowball,
     SpruceLog,
+    Coal,
+    RawIron,
+    RawGold,
+    Diamond,
 }
 
 impl ItemId {
@@ -64,7 +68,9 @@ impl ItemId {
             Self::Snow => ItemType::Block(BlockId::Snow),
             Self::SpruceLog => ItemType::Block(BlockId::SpruceLog),
 
-            Self::Snowball => ItemType::Generic,
+            Self::Snowball | Self::Coal | Self::RawIron | Self::RawGold | Self::Diamond => {
+                ItemType::Generic
+            }
         }
     }
 }
@@ -0,0 +1,106 @@
+use super::{GeneratorSettings, WorldHeight};
+use bevy::math::Vec3;
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Small file next to the level file, so that worlds can be listed without loading them
+pub const METADATA_FILE: &str = "metadata.ron";
+
+/// Where players appear when they join a world which does not say otherwise
+pub const DEFAULT_SPAWN: Vec3 = Vec3::new(0.0, 80.0, 0.0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Survival,
+    Creative,
+}
+
+/// Summary of a world, written by the server on every save and read by the world list
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct WorldMetadata {
+    pub display_name: String,
+    pub seed: u32,
+    /// Unix timestamps, in seconds
+    pub created_at: u64,
+    pub last_played: u64,
+    pub game_mode: GameMode,
+    pub spawn: Vec3,
+    /// Time players spent in the world, in seconds
+    pub playtime: u64,
+    /// Save format version the world was last written with
+    pub version: u32,
+    /// Size of the world folder, in bytes
+    pub size_on_disk: u64,
+    /// Worlds from before generators were stored here use the default one
+    #[serde(default)]
+    pub generator: GeneratorSettings,
+    /// Worlds from before their height could be changed keep the default one
+    #[serde(default)]
+    pub height: WorldHeight,
+}
+
+impl WorldMetadata {
+    pub fn new(display_name: String, seed: u32, version: u32) -> Self {
+        let now = unix_timestamp();
+        Self {
+            display_name,
+            seed,
+            created_at: now,
+            last_played: now,
+            game_mode: GameMode::default(),
+            spawn: DEFAULT_SPAWN,
+            playtime: 0,
+            version,
+            size_on_disk: 0,
+            generator: GeneratorSettings::default(),
+            height: WorldHeight::default(),
+        }
+    }
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// UTC calendar date and time of a unix timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: u64,
+    pub minute: u64,
+    pub second: u64,
+}
+
+impl DateTime {
+    pub fn from_unix(secs: u64) -> Self {
+        let days = (secs / 86_400) as i64;
+
+        // Civil date from days since 1970-01-01, in the proleptic Gregorian calendar
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: secs / 3600 % 24,
+            minute: secs / 60 % 60,
+            second: secs % 60,
+        }
+    }
+}
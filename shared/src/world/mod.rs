@@ -1,9 +1,13 @@
 pub mod blocks;
+pub mod creation;
 pub mod data;
 pub mod items;
+pub mod metadata;
 mod utils;
 
 pub use blocks::*;
+pub use creation::*;
 pub use data::*;
 pub use items::*;
+pub use metadata::*;
 pub use utils::*;
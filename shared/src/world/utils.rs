@@ -60,6 +60,33 @@ pub const SIX_OFFSETS: [IVec3; 6] = [
     IVec3::new(0, 0, -1),
 ];
 
-pub fn chunk_in_radius(player_pos: &IVec3, chunk_pos: &IVec3, radius: i32) -> bool {
-    (player_pos.x - chunk_pos.x).abs() <= radius && (player_pos.z - chunk_pos.z).abs() <= radius
+/// Whether a chunk is at most `radius` chunks away from a player horizontally,
+/// and `vertical_radius` chunks vertically
+pub fn chunk_in_radius(
+    player_pos: &IVec3,
+    chunk_pos: &IVec3,
+    radius: i32,
+    vertical_radius: i32,
+) -> bool {
+    (player_pos.x - chunk_pos.x).abs() <= radius
+        && (player_pos.z - chunk_pos.z).abs() <= radius
+        && (player_pos.y - chunk_pos.y).abs() <= vertical_radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_in_radius_use_the_vertical_radius() {
+        let player = IVec3::new(0, 2, 0);
+        assert!(chunk_in_radius(&player, &IVec3::new(5, 2, -5), 5, 1));
+        assert!(!chunk_in_radius(&player, &IVec3::new(6, 2, 0), 5, 1));
+
+        assert!(chunk_in_radius(&player, &IVec3::new(0, 3, 0), 5, 1));
+        assert!(chunk_in_radius(&player, &IVec3::new(0, 1, 0), 5, 1));
+        assert!(!chunk_in_radius(&player, &IVec3::new(0, 4, 0), 5, 1));
+        assert!(!chunk_in_radius(&player, &IVec3::new(0, 0, 0), 5, 1));
+        assert!(!chunk_in_radius(&player, &IVec3::new(0, -3, 0), 5, 4));
+    }
 }